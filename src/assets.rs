@@ -91,12 +91,44 @@ pub struct ItemAssets {
     // axe
     // reinforced axe
     // opalescent axe
-    #[asset(path = "item/placeholder.png")]
+    #[asset(path = "item/makeshift_fishing_rod.png")]
     pub makeshift_fishing_rod: Handle<Image>,
     #[asset(path = "item/blueprint.png")]
     pub blueprint: Handle<Image>,
     #[asset(path = "item/wood.png")]
     pub wood: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub makeshift_hoe: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub watering_can: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub wheat_seeds: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub carrot_seeds: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub wheat: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub carrot: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub minnow: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub trout: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub catfish: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub old_boot: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub makeshift_pickaxe: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub copper_pickaxe: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub iron_pickaxe: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub stone: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub copper_ore: Handle<Image>,
+    #[asset(path = "item/placeholder.png")]
+    pub iron_ore: Handle<Image>,
 }
 
 impl ItemAssets {
@@ -109,6 +141,22 @@ impl ItemAssets {
             Item::MakeshiftFishingRod => self.makeshift_fishing_rod.clone(),
             Item::Blueprint => self.blueprint.clone(),
             Item::Wood => self.wood.clone(),
+            Item::MakeshiftHoe => self.makeshift_hoe.clone(),
+            Item::WateringCan => self.watering_can.clone(),
+            Item::WheatSeeds => self.wheat_seeds.clone(),
+            Item::CarrotSeeds => self.carrot_seeds.clone(),
+            Item::Wheat => self.wheat.clone(),
+            Item::Carrot => self.carrot.clone(),
+            Item::Minnow => self.minnow.clone(),
+            Item::Trout => self.trout.clone(),
+            Item::Catfish => self.catfish.clone(),
+            Item::OldBoot => self.old_boot.clone(),
+            Item::MakeshiftPickaxe => self.makeshift_pickaxe.clone(),
+            Item::CopperPickaxe => self.copper_pickaxe.clone(),
+            Item::IronPickaxe => self.iron_pickaxe.clone(),
+            Item::Stone => self.stone.clone(),
+            Item::CopperOre => self.copper_ore.clone(),
+            Item::IronOre => self.iron_ore.clone(),
         }
     }
 }
@@ -117,4 +165,12 @@ impl ItemAssets {
 pub struct ObjectAssets {
     #[asset(path = "object/tree_ly.png")]
     pub tree: Handle<Image>,
+    #[asset(path = "object/placeholder.png")]
+    pub farmland: Handle<Image>,
+    #[asset(path = "object/bobber.png")]
+    pub bobber: Handle<Image>,
+    #[asset(path = "object/rock.png")]
+    pub rock: Handle<Image>,
+    #[asset(path = "object/placeholder.png")]
+    pub station: Handle<Image>,
 }
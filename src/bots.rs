@@ -0,0 +1,381 @@
+//! Headless clients for load testing a server, run with `socktile bots`. Each
+//! bot speaks the real protocol on its own connection: it makes a user, joins
+//! a shared world, walks around at random, chats and requests chunks.
+
+use crate::prelude::*;
+use crate::resources::{ChatChannel, ChatMessage, ServerAddress};
+use crate::server::max_players_reply;
+use crate::shared::version::ProtocolVersion;
+use bevy::utils::HashMap;
+use netty::client::{Client, ClientConfig};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often bots check for packets and act
+const FRAME: Duration = Duration::from_millis(16);
+const MOVE_INTERVAL: Duration = Duration::from_millis(50);
+const CHUNK_INTERVAL: Duration = Duration::from_secs(1);
+const CHAT_INTERVAL: Duration = Duration::from_secs(10);
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+/// Distance a bot walks every move
+const STEP: f32 = 8.0;
+
+/// Totals shared by every bot.
+#[derive(Default)]
+struct Report {
+    sent: u64,
+    recieved: u64,
+    bytes_sent: u64,
+    bytes_recieved: u64,
+    /// Bots that have joined the world
+    playing: usize,
+    /// Bots that gave up, and why
+    failures: Vec<String>,
+    /// Time from sending `JoinWorld` to `JoinedGame`
+    join_latencies: Vec<Duration>,
+    /// Time from sending `RequestChunk` to `ChunkData`
+    chunk_latencies: Vec<Duration>,
+}
+
+impl Report {
+    fn log(&self, elapsed: Duration, previous: (u64, u64)) {
+        let seconds = elapsed.as_secs_f32().max(0.001);
+        info!(
+            "{} bots playing, {} failed | {:.0} packets/s out, {:.0} packets/s in | chunk latency {}",
+            self.playing,
+            self.failures.len(),
+            (self.sent - previous.0) as f32 / seconds,
+            (self.recieved - previous.1) as f32 / seconds,
+            summarize(&self.chunk_latencies),
+        );
+    }
+}
+
+/// Describes a set of latencies as "average / 99th percentile".
+fn summarize(latencies: &[Duration]) -> String {
+    if latencies.is_empty() {
+        return String::from("n/a");
+    }
+    let mut sorted = latencies.to_vec();
+    sorted.sort_unstable();
+    let average = sorted.iter().sum::<Duration>() / sorted.len() as u32;
+    let p99 = sorted[((sorted.len() - 1) as f32 * 0.99) as usize];
+    format!("{:.1}ms avg / {:.1}ms p99", as_millis(average), as_millis(p99))
+}
+
+fn as_millis(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+/// What the bots share besides their report.
+struct Shared {
+    report: Mutex<Report>,
+    /// The world every bot joins, once the first bot has made it
+    world: Mutex<Option<usize>>,
+    stop: AtomicBool,
+    count: usize,
+}
+
+/// Runs `socktile bots [count] [--server host:port] [--seconds n]`.
+pub fn run(arguments: Vec<String>) -> ! {
+    let count = arguments
+        .get(1)
+        .and_then(|count| count.parse::<usize>().ok())
+        .unwrap_or(10);
+    let option = |name: &str| {
+        let index = arguments.iter().position(|argument| argument == name)?;
+        arguments.get(index + 1).cloned()
+    };
+    let server = option("--server").unwrap_or_else(|| String::from("127.0.0.1"));
+    let server = match ServerAddress::parse(&server) {
+        Ok(server) => server,
+        Err(e) => {
+            error!("Unable to use {server}: {e}");
+            std::process::exit(1);
+        }
+    };
+    let seconds = option("--seconds")
+        .and_then(|seconds| seconds.parse::<u64>().ok())
+        .unwrap_or(60);
+    let address = if let Some(address) = server.resolve() {
+        address
+    }
+    else {
+        error!("Unable to find {server}");
+        std::process::exit(1);
+    };
+    info!("Running {count} bots against {server} for {seconds} seconds");
+
+    let shared = Arc::new(Shared {
+        report: default(),
+        world: Mutex::new(None),
+        stop: AtomicBool::new(false),
+        count,
+    });
+    let started = Instant::now();
+    let mut bots = vec![];
+    for index in 0..count {
+        let shared = shared.clone();
+        let (tcp_port, ws_port) = (server.tcp_port, server.ws_port);
+        bots.push(std::thread::spawn(move || {
+            if let Err(reason) = Bot::run(index, address, tcp_port, ws_port, &shared) {
+                warn!("Bot {index} stopped: {reason}");
+                shared.report.lock().unwrap().failures.push(reason);
+            }
+        }));
+        // Don't connect everyone in the same instant
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    let mut last_report = Instant::now();
+    let mut previous = (0, 0);
+    while started.elapsed() < Duration::from_secs(seconds) {
+        std::thread::sleep(Duration::from_millis(100));
+        if last_report.elapsed() >= REPORT_INTERVAL {
+            let report = shared.report.lock().unwrap();
+            report.log(last_report.elapsed(), previous);
+            previous = (report.sent, report.recieved);
+            last_report = Instant::now();
+        }
+    }
+    shared.stop.store(true, Ordering::Relaxed);
+    for bot in bots {
+        bot.join().ok();
+    }
+
+    let report = shared.report.lock().unwrap();
+    let elapsed = started.elapsed().as_secs_f32();
+    info!("Finished after {elapsed:.1}s");
+    info!("Bots that joined: {}/{count}", report.join_latencies.len());
+    for reason in &report.failures {
+        info!("Failure: {reason}");
+    }
+    info!(
+        "Sent {} packets ({:.1} KiB/s), recieved {} packets ({:.1} KiB/s)",
+        report.sent,
+        report.bytes_sent as f32 / 1024.0 / elapsed,
+        report.recieved,
+        report.bytes_recieved as f32 / 1024.0 / elapsed,
+    );
+    info!("Join latency: {}", summarize(&report.join_latencies));
+    info!("Chunk latency: {}", summarize(&report.chunk_latencies));
+    std::process::exit(0);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Stage {
+    Handshake,
+    CreatingUser,
+    CreatingWorld,
+    WaitingForWorld,
+    Joining(Instant),
+    /// The first bot raises the world's player limit before letting the others
+    /// join
+    RaisingLimit,
+    Playing,
+}
+
+struct Bot<'a> {
+    index: usize,
+    client: Client<Packet>,
+    shared: &'a Shared,
+    stage: Stage,
+    /// The world this bot made, if it's the first bot
+    world: Option<usize>,
+    position: Transform,
+    /// Direction the bot is walking in, in radians
+    heading: f32,
+    chunk_requests: HashMap<(isize, isize), Instant>,
+    last_move: Instant,
+    last_chunk: Instant,
+    last_chat: Instant,
+}
+
+impl<'a> Bot<'a> {
+    fn run(
+        index: usize,
+        address: [u8; 4],
+        tcp_port: u16,
+        ws_port: u16,
+        shared: &'a Shared,
+    ) -> Result<(), String> {
+        let client = Client::launch(ClientConfig {
+            address,
+            tcp_port,
+            ws_port,
+            connection_timeout: TIMEOUT_DURATION,
+            ..default()
+        })
+        .ok_or_else(|| String::from("unable to connect"))?;
+        let mut bot = Bot {
+            index,
+            client,
+            shared,
+            stage: Stage::Handshake,
+            world: None,
+            position: Transform::from_xyz(0.0, 0.0, 0.0),
+            heading: rand::random::<f32>() * std::f32::consts::TAU,
+            chunk_requests: default(),
+            last_move: Instant::now(),
+            last_chunk: Instant::now(),
+            // Spread chat out between bots
+            last_chat: Instant::now()
+                .checked_sub(Duration::from_millis(index as u64 * 500))
+                .unwrap_or_else(Instant::now),
+        };
+        bot.send(Packet::NettyVersion(ProtocolVersion::current()));
+        while !shared.stop.load(Ordering::Relaxed) {
+            for packet in bot.client.get_packets() {
+                bot.recieve(packet)?;
+            }
+            bot.act();
+            std::thread::sleep(FRAME);
+        }
+        if bot.stage == Stage::Playing {
+            bot.send(Packet::LeaveWorld);
+        }
+        Ok(())
+    }
+    fn send(&mut self, packet: Packet) {
+        let mut report = self.shared.report.lock().unwrap();
+        report.sent += 1;
+        report.bytes_sent += packet.encode().map(|frame| frame.len()).unwrap_or(0) as u64;
+        drop(report);
+        self.client.send(packet);
+    }
+    fn recieve(&mut self, packet: Packet) -> Result<(), String> {
+        let mut report = self.shared.report.lock().unwrap();
+        report.recieved += 1;
+        report.bytes_recieved += packet.encode().map(|frame| frame.len()).unwrap_or(0) as u64;
+        drop(report);
+        match packet {
+            Packet::AllSet(_) if self.stage == Stage::Handshake => {
+                self.stage = Stage::CreatingUser;
+                self.send(Packet::CreateUser(User {
+                    username: format!("Bot{}", self.index),
+                    tag: 0,
+                }));
+            }
+            Packet::WrongVersion(version) => {
+                return Err(format!("the server is running protocol version {version}"));
+            }
+            Packet::OverusedName => {
+                return Err(String::from("too many bots have been made with this name"));
+            }
+            Packet::CreatedUser(_) => {
+                if self.index == 0 {
+                    self.stage = Stage::CreatingWorld;
+                    self.send(Packet::CreateWorld(String::from("Bot World")));
+                }
+                else {
+                    self.stage = Stage::WaitingForWorld;
+                }
+            }
+            Packet::CreatedWorld(world) => {
+                // The world is only shared once its player limit is raised
+                self.world = Some(world);
+                self.stage = Stage::Joining(Instant::now());
+                self.send(Packet::JoinWorld(world));
+            }
+            Packet::JoinedGame(position, _) => {
+                if let Stage::Joining(sent) = self.stage {
+                    let mut report = self.shared.report.lock().unwrap();
+                    report.join_latencies.push(sent.elapsed());
+                    report.playing += 1;
+                }
+                self.position = position;
+                if self.index == 0 {
+                    self.stage = Stage::RaisingLimit;
+                    let count = self.shared.count;
+                    self.chat(format!("/maxplayers {count}"));
+                }
+                else {
+                    self.stage = Stage::Playing;
+                }
+            }
+            Packet::ChatMessage(message) if self.stage == Stage::RaisingLimit => {
+                // Other messages, like players joining, can arrive first
+                if message.text == max_players_reply(self.shared.count) {
+                    *self.shared.world.lock().unwrap() = self.world;
+                    self.stage = Stage::Playing;
+                }
+                else if message.text.starts_with("Usage:") {
+                    return Err(format!("unable to raise the player limit: {}", message.text));
+                }
+            }
+            Packet::ChunkData(chunk, _) => {
+                if let Some(sent) = self.chunk_requests.remove(&chunk) {
+                    self.shared
+                        .report
+                        .lock()
+                        .unwrap()
+                        .chunk_latencies
+                        .push(sent.elapsed());
+                }
+            }
+            Packet::PlayerPositionUpdate(user, position) => {
+                if user.username == format!("Bot{}", self.index) {
+                    self.position = position;
+                }
+            }
+            Packet::Kicked(reason) => {
+                return Err(format!("kicked: {reason}"));
+            }
+            Packet::JoinRefused(reason) => {
+                return Err(format!("unable to join: {reason}"));
+            }
+            Packet::FailedDeserialize => {
+                return Err(String::from("lost connection to the server"));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+    fn act(&mut self) {
+        match self.stage {
+            Stage::WaitingForWorld => {
+                let world = *self.shared.world.lock().unwrap();
+                if let Some(world) = world {
+                    self.stage = Stage::Joining(Instant::now());
+                    self.send(Packet::JoinWorld(world));
+                }
+            }
+            Stage::Playing => {
+                if self.last_move.elapsed() >= MOVE_INTERVAL {
+                    self.last_move = Instant::now();
+                    // Random walk, turning a little every step
+                    self.heading += (rand::random::<f32>() - 0.5) * 0.5;
+                    self.position.translation.x += self.heading.cos() * STEP;
+                    self.position.translation.y += self.heading.sin() * STEP;
+                    self.send(Packet::RequestMove(self.position));
+                }
+                if self.last_chunk.elapsed() >= CHUNK_INTERVAL {
+                    self.last_chunk = Instant::now();
+                    // The same chunk math as `Reality::system_mark_chunks`
+                    let chunk = (
+                        (self.position.translation.x / 1920.0).round() as isize,
+                        (self.position.translation.y / 1088.0).round() as isize,
+                    );
+                    self.chunk_requests.insert(chunk, Instant::now());
+                    self.send(Packet::RequestChunk(chunk));
+                }
+                if self.last_chat.elapsed() >= CHAT_INTERVAL {
+                    self.last_chat = Instant::now();
+                    let text = format!("Hello from Bot{}!", self.index);
+                    self.chat(text);
+                }
+            }
+            _ => {}
+        }
+    }
+    fn chat(&mut self, text: String) {
+        self.send(Packet::SendChatMessage(ChatMessage {
+            text,
+            color: Color::BLACK,
+            channel: ChatChannel::World,
+            sender: None,
+            sent_at: Instant::now(),
+        }));
+    }
+}
@@ -21,6 +21,39 @@ pub struct TitleScreenUser;
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Component)]
 pub struct UILocked;
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Component)]
+/// Indicates a [Text2dBundle] that is part of the server list. These are
+/// redrawn whenever the list is sorted, filtered or paged.
+pub struct ServerListEntry;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Component)]
+/// Indicates a [Text2dBundle] that is part of the friends panel on the
+/// titlescreen.
+pub struct FriendsPanel {
+    /// Is this where the player types in a new friend?
+    pub input: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Component)]
+/// Indicates a [Text2dBundle] that is part of the crafting menu.
+pub struct CraftingMenu {
+    /// Where on screen this is drawn
+    pub location: (f32, f32),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Component)]
+/// Part of the inventory screen.
+pub struct InventorySlot {
+    /// The inventory slot this shows
+    pub slot: usize,
+    /// Represents the type of inventory object this is.
+    /// 1 = slot background
+    /// 2 = slot contents
+    /// 3 = stack count
+    /// 4 = title
+    pub type_: usize,
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Component)]
 pub struct DialougeText;
 
@@ -44,7 +77,30 @@ pub struct HotbarMarker {
     /// 1 = slot background
     /// 2 = selected slot
     /// 3 = slot contents
+    /// 4 = stack count
+    pub type_: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Component)]
+pub struct ClockMarker {
+    /// Represents the type of clock object this is.
+    /// 1 = night tint over the world
+    /// 2 = time of day text
+    pub type_: usize,
+}
+
+/// The float on the end of the player's fishing line.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Component)]
+pub struct BobberMarker;
+
+#[derive(Clone, Copy, Debug, Component)]
+pub struct WeatherMarker {
+    /// Represents the type of weather object this is.
+    /// 1 = tint over the world
+    /// 2 = rain drop
     pub type_: usize,
+    /// Where a rain drop is on the screen
+    pub offset: Vec2,
 }
 
 #[derive(Clone, Copy, Debug, Component)]
@@ -55,6 +111,7 @@ pub struct SettingsPageComp {
     /// 2 | video settings increase scaling text
     /// 3 | video settings decrease scaling text
     /// 4 | video settings scaling text
+    /// 5 | online settings address being typed
     pub type_: u8,
 }
 
@@ -30,14 +30,38 @@ pub const EMBED_ASSETS: bool = true;
 #[cfg(target_arch = "wasm32")]
 pub const EMBED_ASSETS: bool = false;
 
-/// The current version tag for netty. If this is different from whoever you're
-/// talking to, they're likely using an incompatible protocol.
-pub const NETTY_VERSION: &str = "closed-alpha-iteration-24";
+/// Major version of the network protocol. Clients and servers with different
+/// major versions can't play together. Bump this whenever a packet changes
+/// layout, or packets are framed differently.
+pub const PROTOCOL_MAJOR: u16 = 32;
+/// Minor version of the network protocol, bumped for additions that older
+/// clients and servers can do without.
+pub const PROTOCOL_MINOR: u16 = 2;
+/// Optional protocol features this build supports. Packets for a feature are
+/// only sent to peers that list it.
+pub const PROTOCOL_CAPABILITIES: &[&str] = &[
+    "chat_history",
+    "friends",
+    "world_clock",
+    "weather",
+    "keepalive",
+    "join_refused",
+    "selected_slot",
+    "player_actions",
+];
+/// Largest packet that will be sent or accepted, in bytes. Connections that
+/// send anything bigger are dropped.
+pub const MAX_PACKET_SIZE: u64 = 16 * 1024 * 1024;
+/// Version of the layout worlds and profiles are saved in. Bump this and add
+/// an upgrade to `save_format` whenever anything saved changes.
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+/// Highest tag a user can have. Each username can be used this many times.
+pub const MAX_USER_TAG: u16 = 9999;
 /// Port for tcp network connections
 pub const TCP_PORT: u16 = 11111;
 /// Port for ws network connections
 pub const WS_PORT: u16 = 11112;
-/// Global game server address
+/// Default game server address, used when the player hasn't picked one
 #[cfg(debug_assertions)]
 pub const GGS: [u8; 4] = [127, 0, 0, 1];
 #[cfg(not(debug_assertions))]
@@ -46,18 +70,82 @@ pub const GGS: [u8; 4] = [127, 0, 0, 1];
 pub const GGS: [u8; 4] = [127, 0, 0, 1];
 /// Time in seconds before a connection is considered unable to connect
 pub const TIMEOUT_DURATION: std::time::Duration = std::time::Duration::from_secs(3);
+/// Time between clients telling the server they're still connected
+pub const KEEPALIVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+/// Time without hearing from a client that sends keepalives before the server
+/// treats it as disconnected
+pub const CONNECTION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
 /// Time between game saves on the server
 pub const AUTOSAVE_FREQUENCY: std::time::Duration = std::time::Duration::from_secs(60 * 5);
+/// Most simulation steps the server will run at once to catch up. Time beyond
+/// this is skipped rather than simulated.
+pub const MAX_CATCH_UP_STEPS: u32 = 100;
+/// Length of a day in new worlds, in seconds
+pub const DEFAULT_DAY_LENGTH: f64 = 20.0 * 60.0;
+/// Time of day new worlds start at, as a fraction of a day (0.25 is 6:00)
+pub const DAY_START: f64 = 0.25;
+/// Game time between the server sending each world's clock to its players, in
+/// seconds
+pub const CLOCK_SYNC_INTERVAL: f64 = 10.0;
+/// Shortest time weather lasts before changing, in seconds of game time
+pub const WEATHER_MIN_LENGTH: usize = 2 * 60;
+/// Longest time weather lasts before changing, in seconds of game time
+pub const WEATHER_MAX_LENGTH: usize = 8 * 60;
+/// Amount of rain drops drawn during a storm. Rain draws half as many.
+pub const RAIN_DROPS: usize = 240;
+/// Colour the world is tinted at midnight
+pub const NIGHT_TINT: bevy::prelude::Color = bevy::prelude::Color::rgba(0.02, 0.03, 0.15, 0.6);
 
 /// Size of the player hitbox in pixels
 pub const PLAYER_HITBOX: (f32, f32) = (64.0, 64.0);
+/// Slots in the hotbar
+pub const HOTBAR_SIZE: usize = 10;
+/// Slots in the backpack, shown in rows as wide as the hotbar
+pub const BACKPACK_SIZE: usize = 30;
 /// The distance before an item on the ground is drawn to players
 pub const ITEM_MAGNET_DISTANCE: f32 = 256.0;
+/// How strongly items are drawn to players. An item `d` pixels away moves
+/// `ITEM_MAGNET_STRENGTH / √(d² + 100)` pixels per second.
+pub const ITEM_MAGNET_STRENGTH: f32 = 1280.0;
 /// The distance before an item on the ground is picked up by players
 pub const ITEM_PICKUP_DISTANCE: f32 = 16.0;
+/// Seconds before a dropped item can be picked up or drawn to players
+pub const ITEM_DROP_COOLDOWN: f64 = 2.0;
+/// The furthest a player can throw an item
+pub const ITEM_THROW_DISTANCE: f32 = 192.0;
 /// The distance a player can be from a tree and still successfully hit it when
 /// chopping with an axe
 pub const TREE_CHOP_DISTANCE: f32 = (PLAYER_HITBOX.0 / 2.0) + 64.0;
+/// The distance a player can be from a tile and still dig, water or plant in it
+pub const FARM_REACH: f32 = PLAYER_HITBOX.0 + 64.0;
+/// Chance of finding seeds when tilling grass
+pub const SEED_FIND_CHANCE: f32 = 0.25;
+/// The distance a player can be from a rock and still mine it
+pub const MINE_REACH: f32 = (PLAYER_HITBOX.0 / 2.0) + 64.0;
+/// Chance of an extra drop from a broken rock for every mining level after the
+/// first
+pub const MINING_BONUS_CHANCE: f32 = 0.1;
+/// Chance of a rock being generated on each stone tile
+pub const ROCK_CHANCE: f32 = 0.08;
+/// The distance a player can be from a station and still craft with it
+pub const STATION_REACH: f32 = PLAYER_HITBOX.0 + 64.0;
+/// Things a player has to craft to raise their crafting level
+pub const CRAFTS_PER_LEVEL: usize = 10;
+/// The distance a player can cast a fishing line
+pub const FISH_REACH: f32 = 4.0 * 64.0;
+/// Shortest and longest time, in seconds, before a fish bites a cast line.
+/// Divided by the rod's power and the weather's bite multiplier.
+pub const FISH_BITE_TIME: (f32, f32) = (4.0, 16.0);
+/// Seconds a player has to reel in after a bite, before counting fishing skill
+pub const FISH_BITE_WINDOW: f32 = 1.5;
+/// Extra seconds to reel in for every fishing level after the first
+pub const FISH_WINDOW_PER_LEVEL: f32 = 0.25;
+/// Index of water in `metadata/terrain.tjson`
+pub const TERRAIN_WATER: usize = 0;
+/// Index of grass in `metadata/terrain.tjson`
+pub const TERRAIN_GRASS: usize = 2;
+/// Index of stone in `metadata/terrain.tjson`
+pub const TERRAIN_STONE: usize = 3;
 /// The offset between the cursor's render location and actual location
 pub const CURSOR_OFFSET: [f32; 2] = [-25.0, 31.0];
 /// Width of a chunk in tiles
@@ -68,6 +156,28 @@ pub const CHUNK_HEIGHT: usize = 17;
 pub const CHUNK_SIZE: usize = CHUNK_WIDTH * CHUNK_HEIGHT;
 /// The distance at which a player can interact with an NPC
 pub const NPC_INTERACTION_DISTANCE: f32 = 100.0;
+/// Amount of chat messages saved with each world
+pub const CHAT_LOG_LENGTH: usize = 100;
+/// Amount of logged chat messages sent to a player when they join a world
+pub const CHAT_HISTORY_ON_JOIN: usize = 20;
+/// Amount of chat messages the client keeps for scrolling back through
+pub const CHAT_HISTORY_LENGTH: usize = 100;
+/// Amount of chat messages shown on screen at once
+pub const CHAT_VISIBLE_LINES: usize = 9;
+/// Amount of your own sent messages that can be recalled in the chat box
+pub const CHAT_RECALL_LENGTH: usize = 50;
+/// UDP port LAN discovery announcements are sent to
+pub const LAN_DISCOVERY_PORT: u16 = 11113;
+/// Time between LAN discovery announcements
+pub const LAN_ANNOUNCE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+/// Time after a LAN server's last announcement before it's removed from the server list
+pub const LAN_SERVER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(7);
+/// Time the metrics endpoint waits on a scraper before dropping its connection
+pub const METRICS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+/// Amount of players allowed in a new world at once
+pub const DEFAULT_MAX_PLAYERS: usize = 8;
+/// Amount of worlds shown on each page of the server list
+pub const SERVER_LIST_PAGE_SIZE: usize = 5;
 
 /// The message used for panic!s when a non-recoverable error occurs
 pub const FATAL_ERROR: &str = "A fatal error occured and socktile cannot continue";
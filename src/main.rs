@@ -8,6 +8,8 @@ use iyes_progress::ProgressPlugin;
 use tracing_subscriber::{prelude::*, EnvFilter};
 
 mod assets;
+#[cfg(not(target_arch = "wasm32"))]
+mod bots;
 mod components;
 mod consts;
 mod matrix;
@@ -32,6 +34,8 @@ pub enum GameState {
     NetworkCheck,
     /// Offline mode title screen
     OfflineTitle,
+    /// The server runs a version of the game this one can't play with
+    VersionMismatch,
     /// Online mode title screen
     TitleScreen,
     /// User creation screen
@@ -73,6 +77,10 @@ fn main() {
             args.next();
             // Collect the rest of the arguments
             let arguments: Vec<String> = args.collect();
+            // If the first argument is `replay`, replay a packet capture
+            if arguments.first() == Some(&String::from("replay")) {
+                server::replay::replay(arguments);
+            }
             // If one of the arguments is `server`...
             if arguments.contains(&String::from("server")) {
                 // Run a server.This returns a never type and should never 
@@ -81,6 +89,39 @@ fn main() {
                 server::startup(arguments);
             }
         }
+        // If the first argument is `bots`, load test a server
+        let arguments: Vec<String> = std::env::args().skip(1).collect();
+        if arguments.first() == Some(&String::from("bots")) {
+            bots::run(arguments);
+        }
+    }
+
+    // Load settings, and connect to the server given with `--server host:port`
+    // instead of the one picked in the address book
+    #[allow(unused_mut)]
+    let mut disk = resources::Disk::init();
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let arguments: Vec<String> = std::env::args().skip(1).collect();
+        if let Some(index) = arguments.iter().position(|argument| argument == "--server") {
+            let server = arguments
+                .get(index + 1)
+                .map(|address| resources::ServerAddress::parse(address));
+            match server {
+                Some(Ok(server)) => disk.set_server_override(server),
+                Some(Err(e)) => warn!("Invalid --server address: {e}"),
+                None => warn!("--server needs an address, like `--server example.com:11111`"),
+            }
+        }
+        // Record traffic with the server for `socktile replay`
+        if let Some(index) = arguments.iter().position(|argument| argument == "--capture") {
+            if let Some(path) = arguments.get(index + 1) {
+                disk.set_capture_path(std::path::PathBuf::from(path));
+            }
+            else {
+                warn!("--capture needs a file to write to");
+            }
+        }
     }
 
     // Create our Bevy app!
@@ -142,6 +183,12 @@ fn main() {
             systems::visual::clear_old,
             systems::visual::title_screen,
             systems::audio::title_screen_loop,
+            resources::Friends::system_queue_redraw,
+        ).chain())
+        // [ORDERED] Explain why we can't play on this server
+        .add_systems(OnEnter(GameState::VersionMismatch), (
+            systems::visual::clear_old,
+            systems::visual::version_mismatch,
         ).chain())
         // [ORDERED] Spawn the user creation textures/text and clear any old stuff
         .add_systems(OnEnter(GameState::MakeUser), (
@@ -159,7 +206,10 @@ fn main() {
         ))
         // Update the settings menu UI
         .add_systems(Update, (
+            systems::visual::settings_video,
+            systems::visual::settings_online,
             resources::ui::ui_settings_tab,
+            resources::ui::ui_online_settings,
             resources::ui::ui_toggle_fullscreen,
             resources::ui::ui_increase_scaling,
             resources::ui::ui_decrease_scaling,
@@ -179,22 +229,33 @@ fn main() {
             systems::visual::clear_old,
             systems::visual::join_world,
             resources::network::system_server_list,
+            resources::LanDiscovery::system_start_listening,
         ).chain())
         .add_systems(Update, (
             resources::Reality::system_server_list_renderer,
+            resources::Reality::system_server_list_controls,
+            resources::LanDiscovery::system_merge_servers,
+            resources::LanDiscovery::system_join_local_server,
             resources::ui::ui_game,
             resources::ui::ui_return_titlescreen,
         ).run_if(in_state(GameState::ServerList)))
         .add_systems(OnEnter(GameState::Play), (
             systems::visual::clear_old,
             resources::Reality::system_spawn_hotbar,
+            resources::Reality::system_spawn_clock,
+            systems::visual::spawn_weather,
             resources::Chat::system_init,
+            resources::Crafting::system_reset,
+            resources::InventoryScreen::system_reset,
         ).chain())
         .add_systems(Update, (
             systems::visual::update_title_screen_user,
             systems::visual::update_title_screen_camera,
             resources::ui::ui_return_create_world,
             resources::ui::ui_view_worlds,
+            resources::ui::ui_game,
+            resources::Friends::system_render_panel,
+            resources::Friends::system_panel_actions,
         ).run_if(in_state(GameState::TitleScreen)))
         .add_systems(Update, (
             resources::ui::ui_resume_game_settings,
@@ -207,8 +268,10 @@ fn main() {
         ).chain())
         .add_systems(Update, (
             window_setup::window_update,
+            systems::audio::weather_ambience.run_if(resource_exists::<CoreAssets>()),
             systems::text_box::text_input,
             resources::network::system_step,
+            resources::network::system_keep_alive,
             resources::ui::ui_open_settings,
             resources::ui::ui_quick_exit,
             resources::ui::ui_close_pause_menu,
@@ -225,8 +288,12 @@ fn main() {
         .insert_resource(resources::Animator::init())
         .insert_resource(resources::TextBox::init())
         .insert_resource(resources::ui::UIManager::init())
-        .insert_resource(resources::Disk::init())
+        .insert_resource(disk)
         .insert_resource(resources::Chat::init())
+        .insert_resource(resources::Friends::init())
+        .insert_resource(resources::Crafting::init())
+        .insert_resource(resources::InventoryScreen::init())
+        .insert_resource(resources::LanDiscovery::init())
         .insert_resource(resources::LastState::init())
         .add_systems(Update, (
             resources::Reality::system_spawn_objects,
@@ -256,9 +323,23 @@ fn main() {
         .add_systems(Update, (
             resources::Chat::system_type_chat,
             resources::Chat::system_send_chat,
+            resources::Chat::system_chat_history_keys,
             resources::ui::ui_forward,
             resources::ui::ui_disconnect_game,
             systems::visual::animate_sprites,
+            resources::Reality::system_update_clock,
+            resources::Reality::system_action_farm,
+            resources::Reality::system_action_fish,
+            resources::Reality::system_update_bobber,
+            resources::Reality::system_action_mine,
+            resources::Crafting::system_toggle_menu,
+            resources::Crafting::system_render_menu,
+            resources::Crafting::system_menu_actions,
+            resources::InventoryScreen::system_toggle_screen,
+            resources::InventoryScreen::system_render_screen,
+            resources::InventoryScreen::system_update_screen,
+            resources::InventoryScreen::system_drag_stacks,
+            resources::InventoryScreen::system_drop_key,
         ).run_if(in_state(GameState::Play)))
         .add_systems(Update, (
             resources::Reality::system_action_blueprint,
@@ -271,6 +352,10 @@ fn main() {
             resources::Reality::system_player_controls,
             resources::Reality::system_pause_renderer,
             resources::Reality::system_position_hotbar,
+            resources::Reality::system_position_clock,
+            resources::Crafting::system_position_menu,
+            resources::InventoryScreen::system_position_screen,
+            systems::visual::update_weather,
             resources::Reality::system_player_locator,
             resources::Reality::system_display_blueprint,
             resources::Reality::system_camera_updater,
@@ -13,7 +13,7 @@ pub use crate::utils::*;
 // Group imports
 pub use crate::resources::ui::{UIClickAction, UIClickable, UIManager};
 pub use crate::resources::Disk;
-pub use crate::shared::player::{Item, ItemAction, PlayerData};
+pub use crate::shared::player::{Item, ItemAction, ItemStack, PlayerData};
 pub use serde::{Deserialize, Serialize};
 
 // Individual imports
@@ -7,9 +7,17 @@ mod animator;
 pub mod ui;
 pub use animator::Animator;
 mod disk;
-pub use disk::Disk;
+pub use disk::{AddressBook, Disk, ServerAddress};
 mod chat;
 pub use chat::Chat;
-pub use chat::ChatMessage;
+pub use chat::{ChatChannel, ChatMessage};
+mod friends;
+pub use friends::Friends;
+mod crafting;
+pub use crafting::Crafting;
+mod inventory;
+pub use inventory::InventoryScreen;
+mod lan;
+pub use lan::LanDiscovery;
 pub mod last_state;
 pub use last_state::LastState;
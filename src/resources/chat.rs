@@ -3,8 +3,17 @@ use crate::prelude::*;
 
 #[derive(Resource)]
 pub struct Chat {
+    /// Recieved messages, newest first
     pub history: Vec<ChatMessage>,
     pub is_chat_open: MenuState,
+    /// How many messages back through `history` the chat is scrolled
+    scroll: usize,
+    /// Messages this player has sent, oldest first
+    sent: Vec<String>,
+    /// Which of `sent` is in the chat box, counting back from the newest
+    recall: Option<usize>,
+    /// The selected chat tab
+    tab: ChatTab,
 }
 
 impl Chat {
@@ -12,6 +21,10 @@ impl Chat {
         Chat {
             history: vec![],
             is_chat_open: MenuState::Closed,
+            scroll: 0,
+            sent: vec![],
+            recall: None,
+            tab: ChatTab::All,
         }
     }
     pub fn is_open(&self) -> bool {
@@ -23,14 +36,35 @@ impl Chat {
     pub fn escape_close(&mut self) {
         // Close chat immediately, without sending message.
         self.is_chat_open = MenuState::Closed;
+        self.scroll = 0;
+        self.recall = None;
     }
     fn add_message(&mut self, msg: ChatMessage) {
-        self.history.push(msg);
+        self.history.insert(0, msg);
+        self.history.truncate(CHAT_HISTORY_LENGTH);
+        // Keep the same messages on screen if scrolled back
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.max_scroll());
+        }
+    }
+    fn max_scroll(&self) -> usize {
+        self.visible_history()
+            .len()
+            .saturating_sub(CHAT_VISIBLE_LINES)
+    }
+    /// Messages shown in the selected tab, newest first.
+    fn visible_history(&self) -> Vec<&ChatMessage> {
         self.history
-            .sort_by(|a, b| a.sent_at.elapsed().cmp(&b.sent_at.elapsed()));
-        while self.history.len() > 9 {
-            self.history.pop();
+            .iter()
+            .filter(|message| self.tab.shows(message))
+            .collect()
+    }
+    fn remember_sent(&mut self, text: String) {
+        self.sent.push(text);
+        if self.sent.len() > CHAT_RECALL_LENGTH {
+            self.sent.remove(0);
         }
+        self.recall = None;
     }
     pub fn system_open_chat(mut selfs: ResMut<Chat>, mut tb: ResMut<TextBox>) {
         if selfs.is_chat_open == MenuState::Queued {
@@ -46,7 +80,8 @@ impl Chat {
         if selfs.is_chat_open == MenuState::Open {
             boxes.for_each_mut(|(mut text, box_)| {
                 if box_.location == 0 {
-                    text.sections[0].value = tb.grab_buffer();
+                    text.sections[0].value = String::from(selfs.tab.label());
+                    text.sections[1].value = tb.grab_buffer();
                 }
             });
         }
@@ -56,7 +91,7 @@ impl Chat {
         mut tb: ResMut<TextBox>,
         mut netty: ResMut<Netty>,
         mut boxes: Query<(&mut Text, &ChatBox)>,
-        disk: Res<Disk>,
+        mut disk: ResMut<Disk>,
         keys: Res<Input<KeyCode>>,
     ) {
         if selfs.is_chat_open == MenuState::Open
@@ -65,34 +100,100 @@ impl Chat {
             boxes.for_each_mut(|(mut text, box_)| {
                 if box_.location == 0 {
                     text.sections[0].value = String::new();
-                    netty.send(Packet::SendChatMessage(ChatMessage {
-                        text: tb
-                            .grab_buffer()
-                            .trim_end_matches('\n')
-                            .trim_end_matches('\r')
-                            .to_string(),
-                        color: Color::BLACK,
-                        sent_at: std::time::Instant::now(),
-                    }));
+                    text.sections[1].value = String::new();
+                    let message = tb
+                        .grab_buffer()
+                        .trim_end_matches('\n')
+                        .trim_end_matches('\r')
+                        .to_string();
+                    if !message.is_empty() {
+                        selfs.remember_sent(message.clone());
+                    }
+                    if !selfs.client_command(&mut disk, &message) {
+                        netty.send(Packet::SendChatMessage(ChatMessage {
+                            text: message,
+                            color: Color::BLACK,
+                            channel: selfs.tab.channel(),
+                            sender: None,
+                            sent_at: std::time::Instant::now(),
+                        }));
+                    }
                     tb.clear_buffer();
                     selfs.is_chat_open = MenuState::Closed;
+                    selfs.scroll = 0;
                 }
             });
         }
     }
+    /// Switches tabs, scrolls through recieved messages and recalls sent ones
+    /// while the chat is open.
+    pub fn system_chat_history_keys(
+        mut selfs: ResMut<Chat>,
+        mut tb: ResMut<TextBox>,
+        disk: Res<Disk>,
+        keys: Res<Input<KeyCode>>,
+    ) {
+        if selfs.is_chat_open != MenuState::Open {
+            return;
+        }
+        let ctrls = disk.control_config();
+        if keys.just_pressed(ctrls.chat_cycle_tab) {
+            selfs.tab = selfs.tab.next();
+            selfs.scroll = 0;
+        }
+        if keys.just_pressed(ctrls.chat_scroll_up) {
+            selfs.scroll = (selfs.scroll + 1).min(selfs.max_scroll());
+        }
+        if keys.just_pressed(ctrls.chat_scroll_down) {
+            selfs.scroll = selfs.scroll.saturating_sub(1);
+        }
+        if keys.just_pressed(ctrls.chat_recall_previous) && !selfs.sent.is_empty() {
+            let recall = match selfs.recall {
+                Some(index) => (index + 1).min(selfs.sent.len() - 1),
+                None => 0,
+            };
+            selfs.recall = Some(recall);
+            tb.set_buffer(selfs.sent[selfs.sent.len() - 1 - recall].clone());
+        }
+        if keys.just_pressed(ctrls.chat_recall_next) {
+            match selfs.recall {
+                Some(0) => {
+                    selfs.recall = None;
+                    tb.clear_buffer();
+                }
+                Some(index) => {
+                    selfs.recall = Some(index - 1);
+                    tb.set_buffer(selfs.sent[selfs.sent.len() - index].clone());
+                }
+                None => {}
+            }
+        }
+    }
     pub fn system_init(mut commands: Commands, fonts: ResMut<FontAssets>) {
-        for index in 0..10 {
+        for index in 0..(CHAT_VISIBLE_LINES + 1) {
             commands.spawn((
                 Text2dBundle {
                     text: Text {
-                        sections: vec![TextSection {
-                            value: String::new(),
-                            style: TextStyle {
-                                font: fonts.apple_tea.clone(),
-                                font_size: 32.0,
-                                color: Color::BLACK,
+                        sections: vec![
+                            // Channel prefix
+                            TextSection {
+                                value: String::new(),
+                                style: TextStyle {
+                                    font: fonts.apple_tea.clone(),
+                                    font_size: 32.0,
+                                    color: Color::BLACK,
+                                },
+                            },
+                            // Message
+                            TextSection {
+                                value: String::new(),
+                                style: TextStyle {
+                                    font: fonts.apple_tea.clone(),
+                                    font_size: 32.0,
+                                    color: Color::BLACK,
+                                },
                             },
-                        }],
+                        ],
                         alignment: TextAlignment::Left,
                         linebreak_behavior: bevy::text::BreakLineOn::AnyCharacter
                     },
@@ -115,29 +216,102 @@ impl Chat {
         boxes.for_each_mut(|(mut text, thisbox, mut loc)| {
             loc.translation.x = -(1920.0 / 2.0);
             loc.translation.y = -(1080.0 / 2.0) + 12.0 + (40.0 * thisbox.location as f32);
-            if thisbox.location < selfs.history.len() + 1 && thisbox.location != 0 {
-                let thismsg = &selfs.history[thisbox.location - 1];
+            if thisbox.location == 0 {
+                return;
+            }
+            let visible = selfs.visible_history();
+            let index = thisbox.location - 1 + selfs.scroll;
+            if index >= visible.len() {
+                text.sections[0].value = String::new();
+                text.sections[1].value = String::new();
+            }
+            else {
+                let thismsg = visible[index];
+                let fade = 0.01 * thismsg.sent_at.elapsed().as_secs_f32();
+                let (prefix, mut prefix_color) = thismsg.channel.prefix();
                 let mut iso_color = thismsg.color;
-                let midalpha = iso_color.a() - (0.01 * thismsg.sent_at.elapsed().as_secs_f32());
-                if midalpha < 0.0 {
-                    iso_color.set_a(0.0);
+                for color in [&mut iso_color, &mut prefix_color] {
+                    let midalpha = color.a() - fade;
+                    if selfs.is_chat_open == MenuState::Open {
+                        color.set_a(1.0);
+                    }
+                    else if midalpha < 0.0 {
+                        color.set_a(0.0);
+                    }
+                    else {
+                        color.set_a(midalpha);
+                    }
                 }
-                else {
-                    iso_color.set_a(midalpha);
-                }
-                if selfs.is_chat_open == MenuState::Open {
-                    iso_color.set_a(1.0);
-                }
-                text.sections[0].value = thismsg.text.clone();
-                text.sections[0].style.color = iso_color;
+                text.sections[0].value = prefix;
+                text.sections[0].style.color = prefix_color;
+                text.sections[1].value = thismsg.text.clone();
+                text.sections[1].style.color = iso_color;
             }
         });
     }
-    pub fn system_pull_messages(mut selfs: ResMut<Chat>, mut reality: ResMut<Reality>) {
+    pub fn system_pull_messages(
+        mut selfs: ResMut<Chat>,
+        mut reality: ResMut<Reality>,
+        disk: Res<Disk>,
+    ) {
         for message in reality.pull_messages() {
+            if let Some(sender) = &message.sender {
+                if disk.is_ignored(sender) {
+                    continue;
+                }
+            }
             selfs.add_message(message);
         }
     }
+    /// Runs commands that only affect this client, like managing the ignore
+    /// list. Returns false if `text` isn't one of them and should be sent to
+    /// the server.
+    fn client_command(&mut self, disk: &mut Disk, text: &str) -> bool {
+        let mut args = text.split_whitespace();
+        let command = args.next().unwrap_or_default();
+        if !matches!(command, "/ignore" | "/unignore" | "/ignored") {
+            return false;
+        }
+        let mut ignored = disk.ignored();
+        if command == "/ignored" {
+            let names: Vec<String> = ignored.iter().map(|user| user.to_string()).collect();
+            self.add_message(ChatMessage::system(
+                format!("Ignored ({}): {}", names.len(), names.join(", ")),
+                Color::DARK_GRAY,
+            ));
+            return true;
+        }
+        let user = if let Some(user) = args.next().and_then(User::parse) {
+            user
+        }
+        else {
+            self.add_message(ChatMessage::system(
+                format!("Usage: {command} <user#tag>"),
+                Color::RED,
+            ));
+            return true;
+        };
+        let reply = if command == "/ignore" {
+            if !ignored.contains(&user) {
+                ignored.push(user.clone());
+            }
+            format!("Ignoring {user}. (/unignore to undo)")
+        }
+        else {
+            ignored.retain(|ignored_user| ignored_user != &user);
+            format!("No longer ignoring {user}.")
+        };
+        if disk.update_ignored(ignored) {
+            self.add_message(ChatMessage::system(reply, Color::DARK_GRAY));
+        }
+        else {
+            self.add_message(ChatMessage::system(
+                "Unable to save the ignore list.",
+                Color::RED,
+            ));
+        }
+        true
+    }
 }
 
 use std::time::Instant;
@@ -146,7 +320,97 @@ use std::time::Instant;
 pub struct ChatMessage {
     pub text: String,
     pub color: Color,
+    pub channel: ChatChannel,
+    /// The player who sent this message, if any
+    pub sender: Option<User>,
     #[serde(skip)]
     #[serde(default = "Instant::now")]
     pub sent_at: Instant,
 }
+
+impl ChatMessage {
+    /// Creates a message that didn't come from a player.
+    pub fn system(text: impl Into<String>, color: Color) -> ChatMessage {
+        ChatMessage {
+            text: text.into(),
+            color,
+            channel: ChatChannel::System,
+            sender: None,
+            sent_at: Instant::now(),
+        }
+    }
+}
+
+/// Who a chat message is sent to.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum ChatChannel {
+    /// Everyone in the sender's world
+    World,
+    /// One other player, in any world. When sending this is the recipient, and
+    /// when recieving it's the other player in the conversation.
+    Whisper(User),
+    /// Everyone in the sender's party
+    Party,
+    /// Everyone on the server, in any world
+    Global,
+    /// Messages from the server or game itself
+    System,
+}
+
+impl ChatChannel {
+    /// The text and color shown before messages in this channel.
+    fn prefix(&self) -> (String, Color) {
+        match self {
+            ChatChannel::World => (String::new(), Color::BLACK),
+            ChatChannel::Whisper(_) => (String::from("[Whisper] "), Color::PURPLE),
+            ChatChannel::Party => (String::from("[Party] "), Color::TEAL),
+            ChatChannel::Global => (String::from("[Global] "), Color::ORANGE_RED),
+            ChatChannel::System => (String::new(), Color::DARK_GRAY),
+        }
+    }
+}
+
+/// Which messages are shown in the chat, and where sent messages go.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChatTab {
+    All,
+    World,
+    Party,
+    Global,
+}
+
+impl ChatTab {
+    fn next(self) -> ChatTab {
+        match self {
+            ChatTab::All => ChatTab::World,
+            ChatTab::World => ChatTab::Party,
+            ChatTab::Party => ChatTab::Global,
+            ChatTab::Global => ChatTab::All,
+        }
+    }
+    fn label(self) -> &'static str {
+        match self {
+            ChatTab::All => "[All] ",
+            ChatTab::World => "[World] ",
+            ChatTab::Party => "[Party] ",
+            ChatTab::Global => "[Global] ",
+        }
+    }
+    /// The channel messages typed in this tab are sent to.
+    fn channel(self) -> ChatChannel {
+        match self {
+            ChatTab::All | ChatTab::World => ChatChannel::World,
+            ChatTab::Party => ChatChannel::Party,
+            ChatTab::Global => ChatChannel::Global,
+        }
+    }
+    /// Is this message shown in this tab? System messages and whispers are
+    /// always shown.
+    fn shows(self, message: &ChatMessage) -> bool {
+        match (self, &message.channel) {
+            (ChatTab::All, _) => true,
+            (_, ChatChannel::System) | (_, ChatChannel::Whisper(_)) => true,
+            (tab, channel) => &tab.channel() == channel,
+        }
+    }
+}
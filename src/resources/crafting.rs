@@ -0,0 +1,159 @@
+use super::{Chat, Reality};
+use crate::prelude::*;
+use crate::shared::crafting::RecipeBook;
+use crate::shared::player::Recipe;
+
+/// Left edge of the crafting menu
+const MENU_X: f32 = 300.0;
+/// Top of the first row of the crafting menu
+const MENU_Y: f32 = 400.0;
+const ROW_HEIGHT: f32 = 44.0;
+
+#[derive(Resource)]
+pub struct Crafting {
+    book: RecipeBook,
+    /// Recipes the server says this player can craft
+    unlocked: Vec<Recipe>,
+    open: bool,
+    /// Does the menu need to be redrawn?
+    changed: bool,
+}
+
+impl Crafting {
+    pub fn init() -> Crafting {
+        Crafting {
+            book: RecipeBook::load(),
+            unlocked: vec![],
+            open: false,
+            changed: true,
+        }
+    }
+    pub fn set_unlocked(&mut self, unlocked: Vec<Recipe>) {
+        self.unlocked = unlocked;
+        self.changed = true;
+    }
+    /// Redraws the menu next frame, such as when the player's inventory
+    /// changes what they can afford.
+    pub fn queue_redraw(&mut self) {
+        self.changed = true;
+    }
+    /// Closes the menu when joining a world.
+    pub fn system_reset(mut selfs: ResMut<Crafting>) {
+        selfs.open = false;
+        selfs.changed = true;
+    }
+    pub fn system_toggle_menu(
+        mut selfs: ResMut<Crafting>,
+        reality: Res<Reality>,
+        chat: Res<Chat>,
+        disk: Res<Disk>,
+        keyboard: Res<Input<KeyCode>>,
+    ) {
+        if chat.is_open() || reality.paused() {
+            return;
+        }
+        if keyboard.just_pressed(disk.control_config().open_crafting) {
+            selfs.open = !selfs.open;
+            selfs.changed = true;
+        }
+    }
+    /// Draws every unlocked recipe, greyed out if the player can't afford it.
+    pub fn system_render_menu(
+        mut commands: Commands,
+        mut selfs: ResMut<Crafting>,
+        mut uiman: ResMut<UIManager>,
+        reality: Res<Reality>,
+        fonts: Res<FontAssets>,
+        old: Query<Entity, With<CraftingMenu>>,
+    ) {
+        if !selfs.changed {
+            return;
+        }
+        selfs.changed = false;
+        old.for_each(|e| {
+            commands.entity(e).despawn();
+        });
+        uiman.remove_tag("crafting");
+        if !selfs.open {
+            return;
+        }
+
+        let mut spawn_text = |text: String, x: f32, y: f32, color: Color| {
+            commands.spawn((
+                Text2dBundle {
+                    text: Text {
+                        sections: vec![TextSection {
+                            value: text,
+                            style: TextStyle {
+                                font: fonts.simvoni.clone(),
+                                font_size: 35.0,
+                                color,
+                            },
+                        }],
+                        alignment: TextAlignment::Left,
+                        linebreak_behavior: bevy::text::BreakLineOn::AnyCharacter
+                    },
+                    text_anchor: bevy::sprite::Anchor::CenterLeft,
+                    transform: Transform::from_xyz(x, y, UI_TEXT),
+                    ..default()
+                },
+                CraftingMenu { location: (x, y) },
+                UILocked {},
+                RemoveOnStateChange {},
+            ));
+        };
+
+        spawn_text(String::from("Crafting"), MENU_X, MENU_Y, Color::BLACK);
+        let mut row = 1;
+        for (index, info) in selfs.book.recipes.iter().enumerate() {
+            if !selfs.unlocked.contains(&info.name) {
+                continue;
+            }
+            let y = MENU_Y - (row as f32 * ROW_HEIGHT);
+            let color = if info.affordable(reality.inventory()) {
+                Color::BLACK
+            }
+            else {
+                Color::GRAY
+            };
+            let mut text = format!("{} ({})", info.label(), info.cost());
+            if let Some(station) = info.station {
+                text.push_str(&format!(" at a {}", station.name()));
+            }
+            spawn_text(String::from("[Craft]"), MENU_X, y, Color::BLUE);
+            spawn_text(text, MENU_X + 110.0, y, color);
+            uiman.add_ui(UIClickable {
+                action: UIClickAction::Craft(index),
+                location: (MENU_X, y + (ROW_HEIGHT / 2.0)),
+                size: (100.0, ROW_HEIGHT),
+                removed_on_use: false,
+                tag: Some(String::from("crafting")),
+            });
+            row += 1;
+        }
+        if row == 1 {
+            let y = MENU_Y - ROW_HEIGHT;
+            spawn_text(String::from("No recipes yet"), MENU_X, y, Color::GRAY);
+        }
+    }
+    /// Keeps the menu in place on screen. Runs before `system_camera_updater`.
+    pub fn system_position_menu(mut query: Query<(&mut Transform, &CraftingMenu)>) {
+        query.for_each_mut(|(mut location, menu)| {
+            location.translation.x = menu.location.0;
+            location.translation.y = menu.location.1;
+        });
+    }
+    /// Asks the server to craft recipes clicked in the menu.
+    pub fn system_menu_actions(
+        selfs: Res<Crafting>,
+        mut uiman: ResMut<UIManager>,
+        mut netty: ResMut<Netty>,
+    ) {
+        if let Some(UIClickAction::Craft(index)) = uiman.queued_action {
+            uiman.queued_action = None;
+            if let Some(info) = selfs.book.recipes.get(index) {
+                netty.send(Packet::Craft(info.name.clone()));
+            }
+        }
+    }
+}
@@ -7,6 +7,14 @@ pub struct Disk {
     control_config: ControlConfig,
     audio_config: AudioConfig,
     user: Option<User>,
+    /// Players whose chat messages are hidden
+    ignored: Vec<User>,
+    address_book: AddressBook,
+    /// Server given on the command line, used instead of the address book's
+    /// selection
+    server_override: Option<ServerAddress>,
+    /// File to capture packets to, given with `--capture`
+    capture_path: Option<PathBuf>,
 }
 
 impl Disk {
@@ -45,11 +53,31 @@ impl Disk {
             else {
                 user = None;
             }
+            let p_ignored = wasm_cookies::get("ignorelist");
+            let ignored;
+            if let Some(Ok(ign)) = p_ignored {
+                ignored = serde_json::from_str(&ign).unwrap_or_default();
+            }
+            else {
+                ignored = vec![];
+            }
+            let p_address_book = wasm_cookies::get("addressbook");
+            let address_book;
+            if let Some(Ok(ab)) = p_address_book {
+                address_book = serde_json::from_str(&ab).unwrap_or_default();
+            }
+            else {
+                address_book = AddressBook::default();
+            }
             return Disk {
                 window_config,
                 control_config,
                 audio_config,
                 user,
+                ignored,
+                address_book,
+                server_override: None,
+                capture_path: None,
             };
         }
         #[cfg(not(target_arch = "wasm32"))]
@@ -91,8 +119,7 @@ impl Disk {
             control_config_path.push("control_config.bic");
             let control_config_data = std::fs::read(control_config_path);
             let control_config = if let Ok(data) = control_config_data {
-                bincode::deserialize(&data)
-                    .expect("Encountered corrupted control configuration data.")
+                ControlConfig::from_saved(&data)
             }
             else {
                 ControlConfig::default()
@@ -109,11 +136,35 @@ impl Disk {
                 AudioConfig::default()
             };
 
+            let mut ignored_path = files_dir();
+            ignored_path.push("ignore_list.bic");
+            let ignored_data = std::fs::read(ignored_path);
+            let ignored = if let Ok(data) = ignored_data {
+                bincode::deserialize(&data).expect("Encountered corrupted ignore list data.")
+            }
+            else {
+                vec![]
+            };
+
+            let mut address_book_path = files_dir();
+            address_book_path.push("address_book.bic");
+            let address_book_data = std::fs::read(address_book_path);
+            let address_book = if let Ok(data) = address_book_data {
+                bincode::deserialize(&data).expect("Encountered corrupted address book data.")
+            }
+            else {
+                AddressBook::default()
+            };
+
             Disk {
                 window_config,
                 control_config,
                 user,
                 audio_config,
+                ignored,
+                address_book,
+                server_override: None,
+                capture_path: None,
             }
         }
     }
@@ -171,6 +222,98 @@ impl Disk {
             false
         }
     }
+    pub fn is_ignored(&self, user: &User) -> bool {
+        self.ignored.contains(user)
+    }
+    pub fn ignored(&self) -> Vec<User> {
+        self.ignored.clone()
+    }
+    pub fn update_ignored(&mut self, new: Vec<User>) -> bool {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let warm_cookies = wasm_cookies::CookieOptions::default()
+                .expires_after(std::time::Duration::from_secs(60 * 24 * 30 * 12 * 5));
+            wasm_cookies::set(
+                "ignorelist",
+                &serde_json::to_string(&new).unwrap(),
+                &warm_cookies
+            );
+            self.ignored = new;
+            true
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut ignored_path = files_dir();
+            ignored_path.push("ignore_list.bic");
+            let ignored_data = bincode::serialize(&new);
+            if let Ok(bytes) = ignored_data {
+                if std::fs::write(ignored_path, bytes).is_ok() {
+                    self.ignored = new;
+                    return true;
+                }
+                false
+            }
+            else {
+                false
+            }
+        }
+    }
+    pub fn address_book(&self) -> AddressBook {
+        self.address_book.clone()
+    }
+    pub fn update_address_book(&mut self, new: AddressBook) -> bool {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let warm_cookies = wasm_cookies::CookieOptions::default()
+                .expires_after(std::time::Duration::from_secs(60 * 24 * 30 * 12 * 5));
+            wasm_cookies::set(
+                "addressbook",
+                &serde_json::to_string(&new).unwrap(),
+                &warm_cookies
+            );
+            self.address_book = new;
+            true
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut address_book_path = files_dir();
+            address_book_path.push("address_book.bic");
+            let address_book_data = bincode::serialize(&new);
+            if let Ok(bytes) = address_book_data {
+                if std::fs::write(address_book_path, bytes).is_ok() {
+                    self.address_book = new;
+                    return true;
+                }
+                false
+            }
+            else {
+                false
+            }
+        }
+    }
+    /// Uses this server instead of the address book's selection until the game
+    /// is closed.
+    pub fn set_server_override(&mut self, server: ServerAddress) {
+        self.server_override = Some(server);
+    }
+    pub fn set_capture_path(&mut self, path: PathBuf) {
+        self.capture_path = Some(path);
+    }
+    pub fn capture_path(&self) -> Option<&std::path::Path> {
+        self.capture_path.as_deref()
+    }
+    pub fn has_server_override(&self) -> bool {
+        self.server_override.is_some()
+    }
+    /// Returns the server the game should connect to.
+    pub fn server(&self) -> ServerAddress {
+        if let Some(server) = &self.server_override {
+            server.clone()
+        }
+        else {
+            self.address_book.selected()
+        }
+    }
     pub fn user(&self) -> Option<User> {
         self.user.clone()
     }
@@ -225,7 +368,10 @@ impl Default for WindowConfig {
     }
 }
 
+/// Key bindings. New bindings go at the end, so configs saved before they
+/// existed can still be read.
 #[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ControlConfig {
     pub move_up: KeyCode,
     pub move_down: KeyCode,
@@ -235,6 +381,14 @@ pub struct ControlConfig {
     pub close_menu: KeyCode,
     pub send_chat: KeyCode,
     pub interact: KeyCode,
+    pub chat_scroll_up: KeyCode,
+    pub chat_scroll_down: KeyCode,
+    pub chat_recall_previous: KeyCode,
+    pub chat_recall_next: KeyCode,
+    pub chat_cycle_tab: KeyCode,
+    pub open_crafting: KeyCode,
+    pub open_inventory: KeyCode,
+    pub drop_item: KeyCode,
 }
 
 impl Default for ControlConfig {
@@ -248,10 +402,180 @@ impl Default for ControlConfig {
             close_menu: KeyCode::Escape,
             send_chat: KeyCode::Return,
             interact: KeyCode::E,
+            chat_scroll_up: KeyCode::PageUp,
+            chat_scroll_down: KeyCode::PageDown,
+            chat_recall_previous: KeyCode::Up,
+            chat_recall_next: KeyCode::Down,
+            chat_cycle_tab: KeyCode::Tab,
+            open_crafting: KeyCode::C,
+            open_inventory: KeyCode::I,
+            drop_item: KeyCode::Q,
+        }
+    }
+}
+
+impl ControlConfig {
+    /// Every binding, in the order they're saved.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn bindings_mut(&mut self) -> [&mut KeyCode; 16] {
+        [
+            &mut self.move_up,
+            &mut self.move_down,
+            &mut self.move_right,
+            &mut self.move_left,
+            &mut self.open_chat,
+            &mut self.close_menu,
+            &mut self.send_chat,
+            &mut self.interact,
+            &mut self.chat_scroll_up,
+            &mut self.chat_scroll_down,
+            &mut self.chat_recall_previous,
+            &mut self.chat_recall_next,
+            &mut self.chat_cycle_tab,
+            &mut self.open_crafting,
+            &mut self.open_inventory,
+            &mut self.drop_item,
+        ]
+    }
+    /// Reads a saved config. Bindings are saved one after another, so every
+    /// binding that can be read is kept and the rest are left as defaults.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_saved(data: &[u8]) -> ControlConfig {
+        let mut config = ControlConfig::default();
+        let mut reader = data;
+        let mut read = 0;
+        for binding in config.bindings_mut() {
+            match bincode::deserialize_from(&mut reader) {
+                Ok(key) => *binding = key,
+                Err(_) => break,
+            }
+            read += 1;
+        }
+        if read < config.bindings_mut().len() || !reader.is_empty() {
+            warn!("Some saved key bindings couldn't be read, and were reset to their defaults");
+        }
+        config
+    }
+}
+
+/// A game server the client can connect to.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct ServerAddress {
+    /// A hostname or IP address
+    pub host: String,
+    pub tcp_port: u16,
+    pub ws_port: u16,
+}
+
+impl ServerAddress {
+    /// Parses `host` or `host:port`. The websocket port is assumed to be the
+    /// one after the tcp port, as it is for default servers. IPv6 addresses
+    /// (`[host]:port`) are recognized, but refused since netty only connects
+    /// over IPv4.
+    pub fn parse(from: &str) -> Result<ServerAddress, &'static str> {
+        let from = from.trim();
+        let (host, port) = if let Some(rest) = from.strip_prefix('[') {
+            let (host, rest) = rest.split_once(']').ok_or("Missing a closing ]")?;
+            if rest.is_empty() {
+                (host, None)
+            }
+            else {
+                (host, Some(rest.strip_prefix(':').ok_or("Expected :port after ]")?))
+            }
+        }
+        else if from.matches(':').count() > 1 {
+            // A bare IPv6 address, which can't have a port
+            (from, None)
+        }
+        else {
+            match from.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (from, None),
+            }
+        };
+        if host.parse::<std::net::Ipv6Addr>().is_ok() {
+            return Err("IPv6 servers aren't supported, use an IPv4 address or hostname");
+        }
+        if host.is_empty() || host.contains(|c: char| c.is_whitespace() || "[]:".contains(c)) {
+            return Err("Invalid host");
+        }
+        let tcp_port = match port {
+            Some(port) => port.parse::<u16>().map_err(|_| "Invalid port")?,
+            None => TCP_PORT,
+        };
+        Ok(ServerAddress {
+            host: host.to_string(),
+            tcp_port,
+            ws_port: tcp_port.checked_add(1).ok_or("Invalid port")?,
+        })
+    }
+    /// Looks up the IPv4 address of this server's host.
+    pub fn resolve(&self) -> Option<[u8; 4]> {
+        if let Ok(ip) = self.host.parse::<std::net::Ipv4Addr>() {
+            return Some(ip.octets());
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            use std::net::ToSocketAddrs;
+            let addresses = match (self.host.as_str(), self.tcp_port).to_socket_addrs() {
+                Ok(addresses) => addresses,
+                Err(e) => {
+                    warn!("Unable to look up {}: {e}", self.host);
+                    return None;
+                }
+            };
+            for address in addresses {
+                if let std::net::SocketAddr::V4(address) = address {
+                    return Some(address.ip().octets());
+                }
+            }
+            warn!("{} has no IPv4 address", self.host);
+        }
+        None
+    }
+}
+
+impl std::fmt::Display for ServerAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.host, self.tcp_port)
+    }
+}
+
+/// Servers the player has saved, and which one to connect to.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct AddressBook {
+    pub servers: Vec<ServerAddress>,
+    pub selected: usize,
+}
+
+impl AddressBook {
+    /// Returns the selected server, or the default one if the book is empty.
+    pub fn selected(&self) -> ServerAddress {
+        self.servers
+            .get(self.selected)
+            .cloned()
+            .unwrap_or_else(default_server)
+    }
+}
+
+impl Default for AddressBook {
+    fn default() -> Self {
+        AddressBook {
+            servers: vec![default_server()],
+            selected: 0,
         }
     }
 }
 
+fn default_server() -> ServerAddress {
+    let ip = std::net::Ipv4Addr::from(GGS);
+    ServerAddress {
+        host: ip.to_string(),
+        tcp_port: TCP_PORT,
+        ws_port: WS_PORT,
+    }
+}
+
 fn files_dir() -> PathBuf {
     let mut dir = std::env::current_exe().expect("Unable to get the executable's path.");
     dir.pop();
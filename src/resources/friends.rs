@@ -0,0 +1,240 @@
+use super::TextBox;
+use crate::prelude::*;
+use crate::shared::friends::{FriendStatus, Presence};
+
+/// Most rows the title screen friends panel shows at once.
+const PANEL_ROWS: usize = 8;
+/// Left edge of the friends panel
+const PANEL_X: f32 = -260.0;
+/// Top of the first row of the friends panel
+const PANEL_Y: f32 = 60.0;
+const ROW_HEIGHT: f32 = 44.0;
+
+#[derive(Resource)]
+pub struct Friends {
+    friends: Vec<FriendStatus>,
+    /// Users who want to be friends with this player
+    requests: Vec<User>,
+    /// Shown at the bottom of the panel, such as why a request failed
+    status: Option<String>,
+    /// Is the player typing in a user to send a request to?
+    typing: bool,
+    /// Does the panel need to be redrawn?
+    changed: bool,
+}
+
+impl Friends {
+    pub fn init() -> Friends {
+        Friends {
+            friends: vec![],
+            requests: vec![],
+            status: None,
+            typing: false,
+            changed: true,
+        }
+    }
+    pub fn set_list(&mut self, friends: Vec<FriendStatus>, requests: Vec<User>) {
+        self.friends = friends;
+        self.requests = requests;
+        self.changed = true;
+    }
+    pub fn update_presence(&mut self, status: FriendStatus) {
+        if let Some(friend) = self.friends.iter_mut().find(|friend| friend.user == status.user) {
+            *friend = status;
+            self.changed = true;
+        }
+    }
+    pub fn set_status(&mut self, status: String) {
+        self.status = Some(status);
+        self.changed = true;
+    }
+    /// Redraws the panel next frame. Used when entering the title screen.
+    pub fn system_queue_redraw(mut selfs: ResMut<Friends>) {
+        selfs.typing = false;
+        selfs.changed = true;
+    }
+    /// Draws the friends panel on the title screen.
+    pub fn system_render_panel(
+        mut commands: Commands,
+        mut selfs: ResMut<Friends>,
+        mut uiman: ResMut<UIManager>,
+        fonts: Res<FontAssets>,
+        netty: Res<Netty>,
+        old: Query<Entity, With<FriendsPanel>>,
+    ) {
+        // Servers without friends lists don't get a panel
+        if !selfs.changed || !netty.server_has("friends") {
+            return;
+        }
+        selfs.changed = false;
+        old.for_each(|e| {
+            commands.entity(e).despawn();
+        });
+        uiman.remove_tag("friends");
+
+        let mut spawn_text = |text: String, x: f32, y: f32, color: Color, input: bool| {
+            commands.spawn((
+                Text2dBundle {
+                    text: Text {
+                        sections: vec![TextSection {
+                            value: text,
+                            style: TextStyle {
+                                font: fonts.simvoni.clone(),
+                                font_size: 35.0,
+                                color,
+                            },
+                        }],
+                        alignment: TextAlignment::Left,
+                        linebreak_behavior: bevy::text::BreakLineOn::AnyCharacter
+                    },
+                    text_anchor: bevy::sprite::Anchor::CenterLeft,
+                    transform: Transform::from_xyz(x, y, UI_TEXT),
+                    ..default()
+                },
+                FriendsPanel { input },
+                RemoveOnStateChange {},
+            ));
+        };
+        let mut button = |action: UIClickAction, x: f32, y: f32, width: f32| {
+            uiman.add_ui(UIClickable {
+                action,
+                location: (x, y + (ROW_HEIGHT / 2.0)),
+                size: (width, ROW_HEIGHT),
+                removed_on_use: false,
+                tag: Some(String::from("friends")),
+            });
+        };
+
+        let online = selfs
+            .friends
+            .iter()
+            .filter(|friend| friend.presence != Presence::Offline)
+            .count();
+        spawn_text(
+            format!("Friends ({online}/{})", selfs.friends.len()),
+            PANEL_X,
+            PANEL_Y,
+            Color::BLACK,
+            false,
+        );
+        spawn_text(String::from("[Add]"), PANEL_X + 420.0, PANEL_Y, Color::BLUE, false);
+        button(UIClickAction::AddFriend, PANEL_X + 420.0, PANEL_Y, 100.0);
+
+        let mut row = 1;
+        for (index, request) in selfs.requests.iter().enumerate() {
+            if row > PANEL_ROWS {
+                break;
+            }
+            let y = PANEL_Y - (row as f32 * ROW_HEIGHT);
+            spawn_text(
+                format!("{request} wants to be friends"),
+                PANEL_X,
+                y,
+                Color::DARK_GRAY,
+                false,
+            );
+            spawn_text(String::from("[Yes]"), PANEL_X + 420.0, y, Color::DARK_GREEN, false);
+            button(UIClickAction::AcceptFriend(index), PANEL_X + 420.0, y, 70.0);
+            spawn_text(String::from("[No]"), PANEL_X + 500.0, y, Color::RED, false);
+            button(UIClickAction::DeclineFriend(index), PANEL_X + 500.0, y, 60.0);
+            row += 1;
+        }
+        // Online friends first
+        let mut friends: Vec<(usize, &FriendStatus)> = selfs.friends.iter().enumerate().collect();
+        friends.sort_by_key(|(_, friend)| friend.presence == Presence::Offline);
+        for (index, friend) in friends {
+            if row > PANEL_ROWS {
+                break;
+            }
+            let y = PANEL_Y - (row as f32 * ROW_HEIGHT);
+            let (presence, color) = match &friend.presence {
+                Presence::Offline => (String::from("Offline"), Color::GRAY),
+                Presence::TitleScreen => (String::from("Online"), Color::BLACK),
+                Presence::InWorld(_, name) => (format!("In {name}"), Color::DARK_GREEN),
+            };
+            spawn_text(format!("{} - {presence}", friend.user), PANEL_X, y, color, false);
+            if let (Presence::InWorld(world, _), true) = (&friend.presence, friend.can_join) {
+                spawn_text(String::from("[Join]"), PANEL_X + 420.0, y, Color::BLUE, false);
+                button(UIClickAction::JoinWorld(*world), PANEL_X + 420.0, y, 80.0);
+            }
+            spawn_text(String::from("[x]"), PANEL_X + 510.0, y, Color::RED, false);
+            button(UIClickAction::RemoveFriend(index), PANEL_X + 510.0, y, 50.0);
+            row += 1;
+        }
+
+        let y = PANEL_Y - (row as f32 * ROW_HEIGHT);
+        if selfs.typing {
+            spawn_text(String::new(), PANEL_X, y, Color::BLACK, true);
+        }
+        else if let Some(status) = &selfs.status {
+            spawn_text(status.clone(), PANEL_X, y, Color::RED, false);
+        }
+    }
+    /// Handles clicks on the friends panel and typing in new friends.
+    pub fn system_panel_actions(
+        mut selfs: ResMut<Friends>,
+        mut uiman: ResMut<UIManager>,
+        mut tb: ResMut<TextBox>,
+        mut netty: ResMut<Netty>,
+        mut panel: Query<(&mut Text, &FriendsPanel)>,
+        disk: Res<Disk>,
+        keys: Res<Input<KeyCode>>,
+    ) {
+        match uiman.queued_action {
+            Some(UIClickAction::AddFriend) => {
+                uiman.queued_action = None;
+                tb.clear_buffer();
+                selfs.typing = true;
+                selfs.status = None;
+                selfs.changed = true;
+            }
+            Some(UIClickAction::AcceptFriend(index)) => {
+                uiman.queued_action = None;
+                if let Some(user) = selfs.requests.get(index) {
+                    netty.send(Packet::RespondFriendRequest(user.clone(), true));
+                }
+            }
+            Some(UIClickAction::DeclineFriend(index)) => {
+                uiman.queued_action = None;
+                if let Some(user) = selfs.requests.get(index) {
+                    netty.send(Packet::RespondFriendRequest(user.clone(), false));
+                }
+            }
+            Some(UIClickAction::RemoveFriend(index)) => {
+                uiman.queued_action = None;
+                if let Some(friend) = selfs.friends.get(index) {
+                    netty.send(Packet::RemoveFriend(friend.user.clone()));
+                }
+            }
+            _ => {}
+        }
+        if !selfs.typing {
+            return;
+        }
+        let ctrls = disk.control_config();
+        if keys.just_pressed(ctrls.close_menu) {
+            tb.clear_buffer();
+            selfs.typing = false;
+            selfs.changed = true;
+        }
+        else if keys.just_pressed(ctrls.send_chat) {
+            let typed = tb.grab_buffer().trim().to_string();
+            tb.clear_buffer();
+            selfs.typing = false;
+            if let Some(user) = User::parse(&typed) {
+                netty.send(Packet::SendFriendRequest(user.clone()));
+                selfs.set_status(format!("Sent a friend request to {user}."));
+            }
+            else {
+                selfs.set_status(String::from("Invalid user. (ex PlayerName#1234)"));
+            }
+        }
+        else {
+            panel.for_each_mut(|(mut text, part)| {
+                if part.input {
+                    text.sections[0].value = format!("Add: {}", tb.grab_buffer());
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use super::{Chat, Reality};
+use crate::prelude::*;
+use bevy::window::PrimaryWindow;
+
+/// Centre of the top row of the backpack
+const BACKPACK_Y: f32 = 64.0;
+
+#[derive(Resource)]
+pub struct InventoryScreen {
+    open: bool,
+    /// Does the screen need to be spawned or despawned?
+    changed: bool,
+    /// The slot being dragged, if any
+    held: Option<usize>,
+}
+
+impl InventoryScreen {
+    pub fn init() -> InventoryScreen {
+        InventoryScreen {
+            open: false,
+            changed: false,
+            held: None,
+        }
+    }
+    /// Closes the screen when joining a world.
+    pub fn system_reset(mut selfs: ResMut<InventoryScreen>) {
+        selfs.open = false;
+        selfs.changed = false;
+        selfs.held = None;
+    }
+    pub fn system_toggle_screen(
+        mut selfs: ResMut<InventoryScreen>,
+        reality: Res<Reality>,
+        chat: Res<Chat>,
+        disk: Res<Disk>,
+        keyboard: Res<Input<KeyCode>>,
+    ) {
+        if chat.is_open() || reality.paused() {
+            return;
+        }
+        if keyboard.just_pressed(disk.control_config().open_inventory) {
+            selfs.open = !selfs.open;
+            selfs.changed = true;
+            selfs.held = None;
+        }
+    }
+    /// Spawns the backpack's slots when opened, and removes them when closed.
+    /// Hotbar slots can be dragged to and from while it's open.
+    pub fn system_render_screen(
+        mut commands: Commands,
+        mut selfs: ResMut<InventoryScreen>,
+        mut uiman: ResMut<UIManager>,
+        textures: Res<UIAssets>,
+        items: Res<ItemAssets>,
+        fonts: Res<FontAssets>,
+        old: Query<Entity, With<InventorySlot>>,
+    ) {
+        if !selfs.changed {
+            return;
+        }
+        selfs.changed = false;
+        old.for_each(|e| {
+            commands.entity(e).despawn();
+        });
+        uiman.remove_tag("inventory");
+        if !selfs.open {
+            return;
+        }
+
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    "Backpack",
+                    TextStyle {
+                        font: fonts.simvoni.clone(),
+                        font_size: 35.0,
+                        color: Color::BLACK,
+                    },
+                ),
+                text_anchor: bevy::sprite::Anchor::BottomLeft,
+                transform: Transform::from_xyz(0.0, 0.0, UI_TEXT),
+                ..default()
+            },
+            InventorySlot {
+                slot: HOTBAR_SIZE,
+                type_: 4,
+            },
+            UILocked {},
+            RemoveOnStateChange {},
+        ));
+        for slot in 0..(HOTBAR_SIZE + BACKPACK_SIZE) {
+            let (x, y) = slot_centre(slot);
+            if slot >= HOTBAR_SIZE {
+                commands.spawn((
+                    SpriteBundle {
+                        texture: textures.slot.clone(),
+                        transform: Transform::from_xyz(x, y, UI_IMG),
+                        ..default()
+                    },
+                    InventorySlot { slot, type_: 1 },
+                    UILocked {},
+                    RemoveOnStateChange {},
+                ));
+                commands.spawn((
+                    SpriteBundle {
+                        texture: items.none.clone(),
+                        transform: Transform::from_xyz(x, y, UI_IMG + 0.01),
+                        ..default()
+                    },
+                    InventorySlot { slot, type_: 2 },
+                    UILocked {},
+                    RemoveOnStateChange {},
+                ));
+                commands.spawn((
+                    Text2dBundle {
+                        text: Text::from_section(
+                            "",
+                            TextStyle {
+                                font: fonts.simvoni.clone(),
+                                font_size: 28.0,
+                                color: Color::BLACK,
+                            },
+                        ),
+                        text_anchor: bevy::sprite::Anchor::BottomRight,
+                        transform: Transform::from_xyz(x, y, UI_TEXT),
+                        ..default()
+                    },
+                    InventorySlot { slot, type_: 3 },
+                    UILocked {},
+                    RemoveOnStateChange {},
+                ));
+            }
+            uiman.add_ui(UIClickable {
+                action: UIClickAction::HoldStack(slot),
+                location: (x - 32.0, y + 32.0),
+                size: (64.0, 64.0),
+                removed_on_use: false,
+                tag: Some(String::from("inventory")),
+            });
+        }
+    }
+    /// Shows what's in each backpack slot.
+    pub fn system_update_screen(
+        reality: Res<Reality>,
+        textures: Res<ItemAssets>,
+        mut sprites: Query<(&InventorySlot, &mut Handle<Image>)>,
+        mut counts: Query<(&InventorySlot, &mut Text)>,
+    ) {
+        let inventory = reality.inventory();
+        sprites.for_each_mut(|(marker, mut texture)| {
+            if marker.type_ == 2 {
+                let slot = inventory.slot(marker.slot).copied().flatten();
+                let item = slot.map(|stack| stack.item);
+                texture.set(Box::new(textures.pick_from_item(item))).unwrap();
+            }
+        });
+        counts.for_each_mut(|(marker, mut text)| {
+            if marker.type_ == 3 {
+                let slot = inventory.slot(marker.slot).copied().flatten();
+                text.sections[0].value = ItemStack::count_label(&slot);
+            }
+        });
+    }
+    /// Keeps the screen in place, with any stack being dragged under the
+    /// cursor. Runs before `system_camera_updater`.
+    pub fn system_position_screen(
+        selfs: Res<InventoryScreen>,
+        windows: Query<&Window, With<PrimaryWindow>>,
+        mut query: Query<(&mut Transform, &InventorySlot)>,
+    ) {
+        let cursor = windows.get_single().ok().and_then(cursor_location);
+        query.for_each_mut(|(mut location, marker)| {
+            let (mut x, mut y) = slot_centre(marker.slot);
+            match (marker.type_, cursor) {
+                (2 | 3, Some(cursor)) if selfs.held == Some(marker.slot) => {
+                    (x, y) = cursor;
+                }
+                _ => {}
+            }
+            match marker.type_ {
+                3 => {
+                    // Bottom right corner of the slot
+                    x += 28.0;
+                    y -= 28.0;
+                }
+                4 => {
+                    // Above the top left slot
+                    x -= 32.0;
+                    y += 40.0;
+                }
+                _ => {}
+            }
+            location.translation.x = x;
+            location.translation.y = y;
+        });
+    }
+    /// Drops an item from the selected slot towards the cursor, or the whole
+    /// stack while holding control.
+    pub fn system_drop_key(
+        mut netty: ResMut<Netty>,
+        reality: Res<Reality>,
+        chat: Res<Chat>,
+        disk: Res<Disk>,
+        keyboard: Res<Input<KeyCode>>,
+        windows: Query<&Window, With<PrimaryWindow>>,
+    ) {
+        if chat.is_open() || reality.paused() {
+            return;
+        }
+        if !keyboard.just_pressed(disk.control_config().drop_item) {
+            return;
+        }
+        let inventory = reality.inventory();
+        let stack = if let Some(stack) = inventory.hotbar[inventory.selected_slot] {
+            stack
+        }
+        else {
+            return;
+        };
+        let count = if keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+            stack.count
+        }
+        else {
+            1
+        };
+        let towards = throw_target(&reality, windows.get_single().ok());
+        netty.send(Packet::DropItems(inventory.selected_slot, count, towards));
+    }
+    /// Picks up stacks clicked on, and asks the server to move them where
+    /// they're dropped. Holding shift splits the stack instead, and letting go
+    /// outside of every slot throws the stack out of the inventory.
+    pub fn system_drag_stacks(
+        mut selfs: ResMut<InventoryScreen>,
+        mut uiman: ResMut<UIManager>,
+        mut netty: ResMut<Netty>,
+        reality: Res<Reality>,
+        buttons: Res<Input<MouseButton>>,
+        keyboard: Res<Input<KeyCode>>,
+        windows: Query<&Window, With<PrimaryWindow>>,
+    ) {
+        if let Some(UIClickAction::HoldStack(slot)) = uiman.queued_action {
+            uiman.queued_action = None;
+            if let Some(Some(_stack)) = reality.inventory().slot(slot) {
+                selfs.held = Some(slot);
+            }
+        }
+        if !buttons.just_released(MouseButton::Left) {
+            return;
+        }
+        let from = if let Some(from) = selfs.held.take() {
+            from
+        }
+        else {
+            return;
+        };
+        let window = windows.get_single().ok();
+        let to = if let Some(to) = window.and_then(cursor_location).and_then(slot_at) {
+            to
+        }
+        else {
+            if let Some(Some(stack)) = reality.inventory().slot(from) {
+                let towards = throw_target(&reality, window);
+                netty.send(Packet::DropItems(from, stack.count, towards));
+            }
+            return;
+        };
+        if to == from {
+            return;
+        }
+        if keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            netty.send(Packet::SplitStack(from, to));
+        }
+        else {
+            netty.send(Packet::MoveStack(from, to));
+        }
+    }
+}
+
+/// Where a slot is drawn on screen. The hotbar is along the bottom, and the
+/// backpack is in rows above it.
+fn slot_centre(slot: usize) -> (f32, f32) {
+    if slot < HOTBAR_SIZE {
+        return (slot as f32 * 64.0 - (64.0 * 5.0), -(1080.0 / 2.0) + 32.0);
+    }
+    let (row, column) = ((slot - HOTBAR_SIZE) / HOTBAR_SIZE, (slot - HOTBAR_SIZE) % HOTBAR_SIZE);
+    (column as f32 * 64.0 - (64.0 * 5.0), BACKPACK_Y - (row as f32 * 64.0))
+}
+
+/// The slot drawn at a point on screen, if any.
+fn slot_at(point: (f32, f32)) -> Option<usize> {
+    (0..(HOTBAR_SIZE + BACKPACK_SIZE)).find(|slot| {
+        let (x, y) = slot_centre(*slot);
+        (point.0 - x).abs() < 32.0 && (point.1 - y).abs() < 32.0
+    })
+}
+
+/// Where in the world items are thrown towards: the cursor, or the player's
+/// feet if it's outside the window.
+fn throw_target(reality: &Reality, window: Option<&Window>) -> Transform {
+    let mut target = reality.player_position();
+    if let Some((x, y)) = window.and_then(cursor_location) {
+        target.translation.x += x;
+        target.translation.y += y;
+    }
+    target
+}
+
+/// Where the cursor is pointing on screen, the same way `ui_manager` sees it.
+fn cursor_location(window: &Window) -> Option<(f32, f32)> {
+    let position = window.cursor_position()?;
+    Some((
+        position.x - (window.width() / 2.0) + CURSOR_OFFSET[0],
+        (window.height() - position.y) - (window.height() / 2.0) + CURSOR_OFFSET[1],
+    ))
+}
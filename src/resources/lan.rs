@@ -0,0 +1,159 @@
+use super::Reality;
+use crate::prelude::*;
+use crate::shared::{lan::LanAnnouncement, listing::GameListing, version::ProtocolVersion};
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Listens for servers announcing themselves on the local network. Browsers
+/// can't use UDP, so nothing is ever found on the web.
+#[derive(Resource)]
+pub struct LanDiscovery {
+    /// Every server heard from, and when it was last heard from
+    found: Arc<Mutex<Vec<(Ipv4Addr, LanAnnouncement, Instant)>>>,
+    /// Servers currently shown in the server list. `GameListing.internal_id`
+    /// of local entries is an index into this.
+    listed: Vec<(Ipv4Addr, LanAnnouncement)>,
+    started: bool,
+    last_merge: Instant,
+}
+
+impl LanDiscovery {
+    pub fn init() -> LanDiscovery {
+        LanDiscovery {
+            found: default(),
+            listed: vec![],
+            started: false,
+            last_merge: Instant::now(),
+        }
+    }
+    /// Returns the address and ports of a listed server.
+    pub fn address_of(&self, index: usize) -> Option<([u8; 4], u16, u16)> {
+        let (ip, announcement) = self.listed.get(index)?;
+        Some((ip.octets(), announcement.tcp_port, announcement.ws_port))
+    }
+    /// Starts listening for announcements, if this isn't already.
+    pub fn system_start_listening(mut selfs: ResMut<LanDiscovery>) {
+        if selfs.started {
+            return;
+        }
+        selfs.started = true;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let bind = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, LAN_DISCOVERY_PORT));
+            let socket = match bind {
+                Ok(socket) => socket,
+                Err(e) => {
+                    warn!("Unable to listen for servers on the local network: {e}");
+                    return;
+                }
+            };
+            let found = selfs.found.clone();
+            std::thread::spawn(move || {
+                let mut buffer = [0; 1024];
+                loop {
+                    let (length, from) = match socket.recv_from(&mut buffer) {
+                        Ok(recieved) => recieved,
+                        Err(e) => {
+                            warn!("Stopped listening for servers on the local network: {e}");
+                            return;
+                        }
+                    };
+                    let ip = match from.ip() {
+                        std::net::IpAddr::V4(ip) => ip,
+                        std::net::IpAddr::V6(_) => continue,
+                    };
+                    let announcement: LanAnnouncement =
+                        if let Ok(announcement) = serde_json::from_slice(&buffer[..length]) {
+                            announcement
+                        }
+                        else {
+                            continue;
+                        };
+                    let mut found = found.lock().unwrap();
+                    found.retain(|(other, other_announcement, _)| {
+                        (*other, other_announcement.tcp_port) != (ip, announcement.tcp_port)
+                    });
+                    found.push((ip, announcement, Instant::now()));
+                }
+            });
+        }
+    }
+    /// Adds servers found on the local network to the server list.
+    pub fn system_merge_servers(mut selfs: ResMut<LanDiscovery>, mut reality: ResMut<Reality>) {
+        if selfs.last_merge.elapsed() < LAN_ANNOUNCE_INTERVAL / 2 {
+            return;
+        }
+        selfs.last_merge = Instant::now();
+        let mut found = selfs.found.lock().unwrap();
+        found.retain(|(_, _, heard)| heard.elapsed() < LAN_SERVER_TIMEOUT);
+        let listed: Vec<(Ipv4Addr, LanAnnouncement)> = found
+            .iter()
+            .filter(|(_, announcement, _)| {
+                ProtocolVersion::current().compatible_with(&announcement.version)
+            })
+            .map(|(ip, announcement, _)| (*ip, announcement.clone()))
+            .collect();
+        drop(found);
+        if listed == selfs.listed {
+            return;
+        }
+        let listings = listed
+            .iter()
+            .enumerate()
+            .map(|(index, (ip, announcement))| GameListing {
+                public_name: announcement.name.clone(),
+                description: format!("{} worlds", announcement.worlds),
+                internal_id: index,
+                local: true,
+                address: format!("{ip}:{}", announcement.tcp_port),
+                password: false,
+                played: false,
+                owner: User {
+                    username: String::new(),
+                    tag: 0,
+                },
+                online: announcement.online,
+                max_players: 0,
+                last_played: None,
+                age: 0,
+            })
+            .collect();
+        selfs.listed = listed;
+        reality.set_local_servers(listings);
+    }
+    /// Connects to a server on the local network when it's clicked in the
+    /// server list.
+    pub fn system_join_local_server(
+        mut commands: Commands,
+        selfs: Res<LanDiscovery>,
+        mut reality: ResMut<Reality>,
+        mut uiman: ResMut<UIManager>,
+        disk: Res<Disk>,
+    ) {
+        let index = if let Some(UIClickAction::JoinLocalServer(index)) = uiman.queued_action {
+            index
+        }
+        else {
+            return;
+        };
+        uiman.queued_action = None;
+        let (address, tcp_port, ws_port) = if let Some(address) = selfs.address_of(index) {
+            address
+        }
+        else {
+            return;
+        };
+        if let Some(mut netty) = super::network::connect(address, tcp_port, ws_port, disk.capture_path()) {
+            if let Some(user) = disk.user() {
+                netty.send(Packet::UserPresence(user));
+            }
+            netty.send(Packet::AvalableServers);
+            reality.set_avalable_servers(vec![]);
+            commands.insert_resource(netty);
+        }
+        else {
+            warn!("Unable to connect to the server on the local network");
+        }
+    }
+}
@@ -1,7 +1,12 @@
 use netty::client::{Client, ClientConfig};
 
-use super::{chat::ChatMessage, Reality};
+use super::{chat::ChatMessage, Animator, Crafting, Friends, Reality, ServerAddress, TextBox};
 use crate::prelude::*;
+use crate::shared::version::ProtocolVersion;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::shared::capture::{Direction, Recorder, Side};
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
 
 #[cfg(target_arch = "wasm32")]
 use std::sync::{Arc, Mutex};
@@ -14,20 +19,40 @@ pub struct Netty {
     n: Arc<Mutex<Client<Packet>>>,
     #[cfg(target_arch = "wasm32")]
     buffer: Vec<Packet>,
+    /// The server's protocol version, once it has answered ours
+    server_version: Option<ProtocolVersion>,
+    /// Has the server sent something unreadable, or closed the connection?
+    lost: bool,
+    /// Time until the next `KeepAlive` is due
+    keepalive: Timer,
+    /// The server this connection was made to, if it was picked on the disk
+    server: Option<ServerAddress>,
+    /// Records packets when the game is run with `--capture`
+    #[cfg(not(target_arch = "wasm32"))]
+    recorder: Option<(Recorder, SocketAddr)>,
 }
 
 impl Netty {
     #[cfg(not(target_arch = "wasm32"))]
     pub fn new(n: Client<Packet>) -> Netty {
         Netty {
-            n
+            n,
+            server_version: None,
+            lost: false,
+            keepalive: Timer::new(KEEPALIVE_INTERVAL, TimerMode::Repeating),
+            server: None,
+            recorder: None,
         }
     }
     #[cfg(target_arch = "wasm32")]
     pub fn new(n: Arc<Mutex<Client<Packet>>>) -> Netty {
         Netty {
             n,
-            buffer: vec![]
+            buffer: vec![],
+            server_version: None,
+            lost: false,
+            keepalive: Timer::new(KEEPALIVE_INTERVAL, TimerMode::Repeating),
+            server: None,
         }
     }
     #[cfg(target_arch = "wasm32")]
@@ -42,8 +67,33 @@ impl Netty {
     }
     #[cfg(not(target_arch = "wasm32"))]
     pub fn send(&mut self, p: Packet) {
+        self.capture(Direction::Sent, &p);
         self.n.send(p);
     }
+    /// Starts recording every packet sent to and recieved from `server`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start_capture(&mut self, path: &std::path::Path, server: SocketAddr) {
+        match Recorder::create(path, Side::Client, None) {
+            Ok(recorder) => self.recorder = Some((recorder, server)),
+            Err(e) => warn!("Unable to capture packets to {}: {e}", path.display()),
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    fn capture(&mut self, direction: Direction, packet: &Packet) {
+        if let Some((recorder, server)) = &mut self.recorder {
+            recorder.record(direction, *server, packet);
+        }
+    }
+    pub fn server_version(&self) -> Option<ProtocolVersion> {
+        self.server_version.clone()
+    }
+    /// Does the server support an optional protocol feature?
+    pub fn server_has(&self, capability: &str) -> bool {
+        self.server_version
+            .as_ref()
+            .map(|version| version.has(capability))
+            .unwrap_or(false)
+    }
     pub fn update(&mut self) {
         #[cfg(target_arch = "wasm32")]
         {
@@ -56,21 +106,41 @@ impl Netty {
     }
 }
 
+/// Connects to the server picked in the address book (or on the command line).
+fn init(disk: &Disk) -> Option<Netty> {
+    let server = disk.server();
+    info!("Connecting to {server}");
+    let address = server.resolve()?;
+    let mut netty = connect(address, server.tcp_port, server.ws_port, disk.capture_path())?;
+    netty.server = Some(server);
+    Some(netty)
+}
+
+/// Connects to a game server, and announces our version. Packets are captured
+/// to `capture` if given.
 #[cfg(not(target_arch = "wasm32"))]
-fn init() -> Option<Netty> {
+pub fn connect(
+    address: [u8; 4],
+    tcp_port: u16,
+    ws_port: u16,
+    capture: Option<&std::path::Path>,
+) -> Option<Netty> {
     info!("Netty initalizing");
 
     let client_attempt = Client::launch(ClientConfig {
-        address: GGS,
-        tcp_port: TCP_PORT,
-        ws_port: WS_PORT,
+        address,
+        tcp_port,
+        ws_port,
         connection_timeout: TIMEOUT_DURATION,
         ..default()
     });
     if let Some(client) = client_attempt {
-        info!("Good connection to GGS, Netty constructed");
+        info!("Good connection to server, Netty constructed");
         let mut n = Netty::new(client);
-        n.send(Packet::NettyVersion(String::from(NETTY_VERSION)));
+        if let Some(path) = capture {
+            n.start_capture(path, SocketAddr::from((address, tcp_port)));
+        }
+        n.send(Packet::NettyVersion(ProtocolVersion::current()));
         Some(n)
     }
     else {
@@ -79,19 +149,26 @@ fn init() -> Option<Netty> {
     }
 }
 
+/// Connects to a game server, and announces our version. Packets can't be
+/// captured on the web.
 #[cfg(target_arch = "wasm32")]
-fn init() -> Option<Netty> {
+pub fn connect(
+    address: [u8; 4],
+    tcp_port: u16,
+    ws_port: u16,
+    _capture: Option<&std::path::Path>,
+) -> Option<Netty> {
     info!("Netty initalizing");
 
     let client = Client::launch(ClientConfig {
-        address: GGS,
-        tcp_port: TCP_PORT,
-        ws_port: WS_PORT,
+        address,
+        tcp_port,
+        ws_port,
         connection_timeout: TIMEOUT_DURATION,
         ..default()
     });
     let mut n = Netty::new(client);
-    n.send(Packet::NettyVersion(String::from(NETTY_VERSION)));
+    n.send(Packet::NettyVersion(ProtocolVersion::current()));
     Some(n)
 }
 
@@ -100,7 +177,32 @@ pub fn system_startup_checks(
     mut state: ResMut<NextState<GameState>>,
     disk: Res<Disk>,
 ) {
-    let pot_client = init();
+    start(&mut commands, &mut state, &disk);
+}
+
+/// Connects again if a different server has been picked since connecting, or
+/// if there's no working connection. Returns true if it did, and has picked
+/// the screen to show next.
+pub fn reconnect_if_needed(
+    commands: &mut Commands,
+    state: &mut NextState<GameState>,
+    netty: Option<&Netty>,
+    disk: &Disk,
+) -> bool {
+    let working = netty.filter(|netty| !netty.lost);
+    if working.and_then(|netty| netty.server.as_ref()) == Some(&disk.server()) {
+        return false;
+    }
+    info!("Reconnecting to {}", disk.server());
+    commands.remove_resource::<Netty>();
+    start(commands, state, disk);
+    true
+}
+
+/// Connects to the server picked on the disk and logs in, then picks the
+/// screen to show.
+fn start(commands: &mut Commands, state: &mut NextState<GameState>, disk: &Disk) {
+    let pot_client = init(disk);
     if let Some(mut client) = pot_client {
         if disk.user().is_some() {
             info!("Logging in user");
@@ -123,6 +225,13 @@ pub fn system_step(
     netty: Option<ResMut<Netty>>,
     mut reality: ResMut<Reality>,
     mut disk: ResMut<Disk>,
+    mut man: ResMut<UIManager>,
+    mut friends: ResMut<Friends>,
+    mut crafting: ResMut<Crafting>,
+    mut animator: ResMut<Animator>,
+    mut tb: ResMut<TextBox>,
+    mut state: ResMut<NextState<GameState>>,
+    current: Res<State<GameState>>,
 ) {
     if let Some(mut netty) = netty {
         netty.update();
@@ -138,13 +247,27 @@ pub fn system_step(
             drop(ax);
         }
         for packet in pkts {
+            #[cfg(not(target_arch = "wasm32"))]
+            netty.capture(Direction::Recieved, &packet);
             match packet {
                 Packet::CreatedUser(user) => {
                     while !disk.update_user(user.clone()) {}
                     info!("Saved new user information.");
                 }
-                Packet::AllSet => {
-                    // do nothing
+                Packet::OverusedName => {
+                    warn!("Too many users already have this username");
+                    // Bring the name back to be changed, where it's shown as taken
+                    if let Some(user) = disk.user() {
+                        tb.set_buffer(user.username);
+                    }
+                    state.set(GameState::MakeUser);
+                }
+                Packet::AllSet(version) => {
+                    if !ProtocolVersion::current().compatible_with(&version) {
+                        warn!("Server is running protocol version {version}, which is incompatible");
+                        state.set(GameState::VersionMismatch);
+                    }
+                    netty.server_version = Some(version);
                 }
                 Packet::CreatedWorld(id) => {
                     netty.send(Packet::JoinWorld(id));
@@ -159,9 +282,13 @@ pub fn system_step(
                 Packet::ServerList(servers) => {
                     reality.set_avalable_servers(servers);
                 }
-                Packet::WrongVersion(prefered_version) => {
-                    error!("Server is running {}, and you're using {} (You're most likely out of date, update!)", prefered_version, NETTY_VERSION);
-                    panic!("{FATAL_ERROR}");
+                Packet::WrongVersion(server_version) => {
+                    warn!(
+                        "Server is running protocol version {server_version}, and you're using {}",
+                        ProtocolVersion::current()
+                    );
+                    netty.server_version = Some(server_version);
+                    state.set(GameState::VersionMismatch);
                 }
                 Packet::OnlinePlayers(players) => {
                     reality.add_online_players(players);
@@ -173,33 +300,35 @@ pub fn system_step(
                     reality.disconnect_player(user);
                 }
                 Packet::PlayerPositionUpdate(p, l) => {
-                    reality.queue_player_move(p, l);
+                    if Some(&p) == disk.user().as_ref() {
+                        // The server moved us (by a command or similar)
+                        reality.set_player_position(l);
+                    }
+                    else {
+                        reality.queue_player_move(p, l);
+                    }
                 }
                 Packet::Whitelisted => {
-                    reality.queue_chat(ChatMessage {
-                        text: String::from("User added to whitelist!"),
-                        color: Color::BLACK,
-                        sent_at: std::time::Instant::now(),
-                    });
+                    reality.queue_chat(ChatMessage::system("User added to whitelist!", Color::BLACK));
                 }
                 Packet::NoWhitelistPermission => {
-                    reality.queue_chat(ChatMessage {
-                        text: String::from("You don't have permission to whitelist other users."),
-                        color: Color::RED,
-                        sent_at: std::time::Instant::now(),
-                    });
+                    reality.queue_chat(ChatMessage::system(
+                        "You don't have permission to whitelist other users.",
+                        Color::RED,
+                    ));
                 }
                 Packet::UnwhitelistableUser => {
-                    reality.queue_chat(ChatMessage {
-                        text: String::from(
-                            "Unable to whitelist user. (Did you spell everything right?)",
-                        ),
-                        color: Color::RED,
-                        sent_at: std::time::Instant::now(),
-                    });
+                    reality.queue_chat(ChatMessage::system(
+                        "Unable to whitelist user. (Did you spell everything right?)",
+                        Color::RED,
+                    ));
                 }
                 Packet::InventoryState(inventory) => {
                     reality.set_inventory(inventory);
+                    crafting.queue_redraw();
+                }
+                Packet::UnlockedRecipes(unlocked) => {
+                    crafting.set_unlocked(unlocked);
                 }
                 Packet::CreateObject(object) => {
                     reality.spawn_object(object);
@@ -218,17 +347,98 @@ pub fn system_step(
                 Packet::ChatMessage(message) => {
                     reality.queue_chat(message);
                 }
+                Packet::ChatHistory(messages) => {
+                    for message in messages {
+                        reality.queue_chat(message);
+                    }
+                }
                 Packet::TileUpdate(chunk, tile, state) => {
                     reality.update_tile(chunk, tile, state)
                 }
-                p => {
-                    panic!("Unhandled client packet failed netty! ({:?})", p);
+                Packet::PlayerAction(user, action) => {
+                    animator.mark_action(user, action);
+                }
+                Packet::ActionAnimation(_) => {
+                    // Servers without "player_actions" don't say who used the item, so there's
+                    // no one to animate
+                    debug!("Ignoring an item animation that doesn't say whose it is");
+                }
+                Packet::Kicked(reason) => {
+                    warn!("Kicked from the world: {reason}");
+                    friends.set_status(reason);
+                    man.queued_action = Some(UIClickAction::LeftWorld);
+                }
+                Packet::JoinRefused(reason) => {
+                    warn!("Unable to join the world: {reason}");
+                    friends.set_status(reason);
+                    man.queued_action = Some(UIClickAction::LeftWorld);
+                }
+                Packet::FriendList(list, requests) => {
+                    friends.set_list(list, requests);
+                }
+                Packet::FriendPresence(status) => {
+                    friends.update_presence(status);
+                }
+                Packet::FriendRequestFailed(reason) => {
+                    friends.set_status(reason);
+                }
+                Packet::WorldClock(clock) => {
+                    reality.set_clock(clock);
+                }
+                Packet::Weather(weather) => {
+                    reality.set_weather(weather);
+                }
+                Packet::Bobber(target) => {
+                    reality.set_bobber(Some(target));
+                }
+                Packet::Bite => {
+                    reality.fish_bite();
+                }
+                Packet::Caught(caught) => {
+                    reality.set_bobber(None);
+                    let message = if let Some(item) = caught {
+                        let name = item.name();
+                        let article = if name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+                            "an"
+                        }
+                        else {
+                            "a"
+                        };
+                        ChatMessage::system(format!("You caught {article} {name}!"), Color::BLACK)
+                    }
+                    else {
+                        ChatMessage::system("It got away...", Color::DARK_GRAY)
+                    };
+                    reality.queue_chat(message);
+                }
+                Packet::FailedDeserialize => {
+                    if !netty.lost {
+                        error!("Lost connection to the server");
+                        netty.lost = true;
+                        friends.set_status(String::from("Lost connection to the server"));
+                        // Leaving the world reconnects, now that this one is lost
+                        if *current.get() == GameState::Play {
+                            man.queued_action = Some(UIClickAction::LeftWorld);
+                        }
+                    }
+                }
+                packet => {
+                    warn!("The server sent {}, which servers don't send", packet.variant_name());
                 }
             }
         }
     }
 }
 
+/// Lets the server know we're still here, if it keeps track.
+pub fn system_keep_alive(netty: Option<ResMut<Netty>>, time: Res<Time>) {
+    if let Some(mut netty) = netty {
+        if netty.keepalive.tick(time.delta()).just_finished() && netty.server_has("keepalive") {
+            netty.send(Packet::KeepAlive);
+        }
+    }
+}
+
 pub fn system_server_list(mut netty: ResMut<Netty>) {
     netty.send(Packet::AvalableServers)
 }
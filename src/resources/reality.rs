@@ -1,5 +1,13 @@
-use super::{chat::ChatMessage, Animator, Chat};
-use crate::shared::{listing::GameListing, player::Inventory};
+use super::{chat::ChatMessage, Animator, Chat, TextBox};
+use crate::shared::{
+    clock::WorldClock,
+    crafting::Station,
+    farming::{Crop, Farmland},
+    mining::Ore,
+    weather::Weather,
+    listing::{GameListing, ServerSort},
+    player::Inventory,
+};
 use crate::{
     modular_assets::{conjoin_styles, TransitionType},
     prelude::{tiles::TileTransitionConfig, *},
@@ -26,6 +34,16 @@ pub struct Reality {
     /// Servers that can be joined
     avalable_servers: Vec<GameListing>,
     push_servers: bool,
+    /// Servers found on the local network
+    local_servers: Vec<GameListing>,
+    /// How the server list is ordered
+    server_sort: ServerSort,
+    /// Only servers matching this are shown in the server list
+    server_filter: String,
+    /// Is the player typing in `server_filter`?
+    filtering_servers: bool,
+    /// Page of the server list being shown, starting at 0
+    server_page: usize,
     /// Players to spawn in and load
     players_to_spawn: Vec<(User, Transform)>,
     /// Players to unload
@@ -51,6 +69,14 @@ pub struct Reality {
     chunk_status: HashMap<(isize, isize), ChunkStatus>,
     blueprint_tile: (isize, isize),
     active_interaction: bool,
+    /// Time in the current world, if the server has sent it
+    clock: Option<WorldClock>,
+    /// Weather in the current world
+    weather: Weather,
+    /// Where the player's fishing line is in the water, if it is
+    bobber: Option<Transform>,
+    /// Is a fish biting the player's line?
+    bite: bool,
 }
 
 impl Reality {
@@ -62,6 +88,11 @@ impl Reality {
             chat_messages: vec![],
             avalable_servers: vec![],
             push_servers: false,
+            local_servers: vec![],
+            server_sort: ServerSort::LastPlayed,
+            server_filter: String::new(),
+            filtering_servers: false,
+            server_page: 0,
             players_to_spawn: vec![],
             players_to_despawn: vec![],
             owns_server: false,
@@ -74,7 +105,11 @@ impl Reality {
             chunk_data: default(),
             chunk_status: default(),
             blueprint_tile: (0, 0),
-            active_interaction: false
+            active_interaction: false,
+            clock: None,
+            weather: Weather::Clear,
+            bobber: None,
+            bite: false,
         }
     }
     /// Input tile coordinates are world aligned (+x right, +y up) starting in
@@ -103,9 +138,15 @@ impl Reality {
     pub fn pause_closed(&mut self) {
         self.pause_menu = MenuState::Closed;
     }
+    pub fn paused(&self) -> bool {
+        self.pause_menu != MenuState::Closed
+    }
     pub fn queue_player_move(&mut self, p: User, l: Transform) {
         self.players_to_move.insert(p, l);
     }
+    pub fn inventory(&self) -> &Inventory {
+        &self.player.inventory
+    }
     pub fn set_inventory(&mut self, inventory: Inventory) {
         self.player.inventory = inventory;
     }
@@ -117,11 +158,32 @@ impl Reality {
     pub fn queue_chat(&mut self, msg: ChatMessage) {
         self.chat_messages.push(msg);
     }
+    pub fn player_position(&self) -> Transform {
+        self.player_position
+    }
     /// Sets the player's position.
     pub fn set_player_position(&mut self, position: Transform) {
         // Set the player's position
         self.player_position = position;
     }
+    /// Syncs the time of day with the server.
+    pub fn set_clock(&mut self, clock: WorldClock) {
+        self.clock = Some(clock);
+    }
+    pub fn set_weather(&mut self, weather: Weather) {
+        self.weather = weather;
+    }
+    pub fn weather(&self) -> Weather {
+        self.weather
+    }
+    /// Sets where the player's fishing line is, or `None` once it's reeled in.
+    pub fn set_bobber(&mut self, bobber: Option<Transform>) {
+        self.bobber = bobber;
+        self.bite = false;
+    }
+    pub fn fish_bite(&mut self) {
+        self.bite = self.bobber.is_some();
+    }
     pub fn set_ownership(&mut self, ownership: bool) {
         info!("Setting ownership status to {ownership}");
         self.owns_server = ownership;
@@ -147,10 +209,39 @@ impl Reality {
         self.avalable_servers = servers;
         self.push_servers = true;
     }
-    pub fn display_servers(&mut self) -> Option<Vec<GameListing>> {
+    pub fn set_local_servers(&mut self, servers: Vec<GameListing>) {
+        self.local_servers = servers;
+        self.push_servers = true;
+    }
+    /// Returns the page of servers to show, and the amount of pages, if the
+    /// server list needs to be redrawn.
+    pub fn display_servers(&mut self) -> Option<(Vec<GameListing>, usize)> {
         if self.push_servers {
             self.push_servers = false;
-            return Some(self.avalable_servers.clone());
+            let mut servers: Vec<GameListing> = self
+                .avalable_servers
+                .iter()
+                .filter(|server| server.matches(&self.server_filter))
+                .cloned()
+                .collect();
+            self.server_sort.sort(&mut servers);
+            // Local servers are listed first, in the order they were found
+            let mut local: Vec<GameListing> = self
+                .local_servers
+                .iter()
+                .filter(|server| server.matches(&self.server_filter))
+                .cloned()
+                .collect();
+            local.append(&mut servers);
+            let servers = local;
+            let pages = servers.len().div_ceil(SERVER_LIST_PAGE_SIZE).max(1);
+            self.server_page = self.server_page.min(pages - 1);
+            let page = servers
+                .into_iter()
+                .skip(self.server_page * SERVER_LIST_PAGE_SIZE)
+                .take(SERVER_LIST_PAGE_SIZE)
+                .collect();
+            return Some((page, pages));
         }
         None
     }
@@ -218,7 +309,7 @@ impl Reality {
     /// Clears pending action if the held item has no action.
     pub fn system_action_none(mut selfs: ResMut<Reality>) {
         if selfs.waiting_for_action {
-            let slotted = selfs.player.inventory.hotbar[selfs.player.inventory.selected_slot];
+            let slotted = selfs.player.inventory.selected();
             if let Some(item) = slotted {
                 if item.action() == ItemAction::None {
                     selfs.waiting_for_action = false;
@@ -238,7 +329,7 @@ impl Reality {
             Query<(Entity, &mut Transform), With<BlueprintSelector>>,
         )>,
     ) {
-        let slotted = selfs.player.inventory.hotbar[selfs.player.inventory.selected_slot];
+        let slotted = selfs.player.inventory.selected();
         if qs.p1().is_empty() {
             if let Some(item) = slotted {
                 if item.action() == ItemAction::Blueprint {
@@ -291,7 +382,7 @@ impl Reality {
     }
     pub fn system_action_blueprint(mut selfs: ResMut<Reality>, mut netty: ResMut<Netty>) {
         if selfs.waiting_for_action {
-            let slotted = selfs.player.inventory.hotbar[selfs.player.inventory.selected_slot];
+            let slotted = selfs.player.inventory.selected();
             if let Some(item) = slotted {
                 if item.action() == ItemAction::Blueprint {
                     // do the thing!
@@ -329,7 +420,7 @@ impl Reality {
         mut objects: Query<(Entity, &mut Object)>,
     ) {
         if selfs.waiting_for_action {
-            let slotted = selfs.player.inventory.hotbar[selfs.player.inventory.selected_slot];
+            let slotted = selfs.player.inventory.selected();
             if let Some(item) = slotted {
                 let action = item.action();
                 if let ItemAction::Chop(power) = action {
@@ -365,6 +456,134 @@ impl Reality {
             }
         }
     }
+    /// Hits the closest rock in reach when holding a pickaxe. The server
+    /// decides whether it breaks.
+    pub fn system_action_mine(
+        mut selfs: ResMut<Reality>,
+        mut animator: ResMut<Animator>,
+        mut netty: ResMut<Netty>,
+        disk: Res<Disk>,
+        objects: Query<&Object>,
+    ) {
+        if !selfs.waiting_for_action {
+            return;
+        }
+        let slotted = selfs.player.inventory.selected();
+        let action = match slotted.map(|item| item.action()) {
+            Some(action @ ItemAction::Mine(_)) => action,
+            _ => return,
+        };
+        info!("Executing player action 'Mine'");
+        animator.mark_action(disk.user().unwrap(), action);
+        netty.send(Packet::ActionAnimation(action));
+        let closest = objects
+            .iter()
+            .filter(|object| matches!(object.rep, ObjectType::Rock(..)))
+            .map(|object| (object.uuid, distance(object.pos, selfs.player_position)))
+            .filter(|(_, distance)| *distance < MINE_REACH)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+        if let Some((uuid, _)) = closest {
+            netty.send(Packet::Mine(uuid));
+        }
+        selfs.waiting_for_action = false;
+    }
+    /// Digs, waters or plants at the cursor when holding a farming tool or
+    /// seeds. The server checks what can actually be done there.
+    pub fn system_action_farm(
+        mut selfs: ResMut<Reality>,
+        mut netty: ResMut<Netty>,
+        cursor: Query<&Transform, With<CursorMarker>>,
+    ) {
+        if !selfs.waiting_for_action {
+            return;
+        }
+        let slotted = selfs.player.inventory.selected();
+        let (item, cursor) = match (slotted, cursor.get_single()) {
+            (Some(item), Ok(cursor)) => (item, cursor),
+            _ => return,
+        };
+        let mut target = selfs.player_position;
+        target.translation.x += cursor.translation.x + CURSOR_OFFSET[0];
+        target.translation.y += cursor.translation.y + CURSOR_OFFSET[1];
+        match item.action() {
+            ItemAction::Dig(_) => netty.send(Packet::Dig(target)),
+            ItemAction::Water => netty.send(Packet::Water(target)),
+            ItemAction::Plant(crop) => netty.send(Packet::Plant(target, crop)),
+            _ => return,
+        }
+        selfs.waiting_for_action = false;
+    }
+    /// Casts a fishing line at the cursor, or reels it in if it's already
+    /// out. Whether anything is caught is up to the server.
+    pub fn system_action_fish(
+        mut selfs: ResMut<Reality>,
+        mut animator: ResMut<Animator>,
+        mut netty: ResMut<Netty>,
+        disk: Res<Disk>,
+        cursor: Query<&Transform, With<CursorMarker>>,
+    ) {
+        if !selfs.waiting_for_action {
+            return;
+        }
+        let slotted = selfs.player.inventory.selected();
+        let (action, cursor) = match (slotted.map(|item| item.action()), cursor.get_single()) {
+            (Some(action @ ItemAction::Fish(_)), Ok(cursor)) => (action, cursor),
+            _ => return,
+        };
+        if selfs.bobber.is_some() {
+            netty.send(Packet::Reel);
+        }
+        else {
+            let mut target = selfs.player_position;
+            target.translation.x += cursor.translation.x + CURSOR_OFFSET[0];
+            target.translation.y += cursor.translation.y + CURSOR_OFFSET[1];
+            netty.send(Packet::Cast(target));
+            animator.mark_action(disk.user().unwrap(), action);
+            netty.send(Packet::ActionAnimation(action));
+        }
+        selfs.waiting_for_action = false;
+    }
+    /// Shows the player's fishing line in the water, bobbing under when a
+    /// fish bites.
+    pub fn system_update_bobber(
+        mut commands: Commands,
+        selfs: Res<Reality>,
+        time: Res<Time>,
+        object_assets: Res<ObjectAssets>,
+        mut bobbers: Query<(Entity, &mut Transform), With<BobberMarker>>,
+    ) {
+        let target = if let Some(target) = selfs.bobber {
+            target
+        }
+        else {
+            for (entity, _) in &bobbers {
+                commands.entity(entity).despawn();
+            }
+            return;
+        };
+        let mut position = target;
+        position.translation.z = BACKGROUND + 1.0;
+        if selfs.bite {
+            position.translation.y -= 6.0 * (time.elapsed_seconds() * 20.0).sin().abs();
+        }
+        else {
+            position.translation.y += 2.0 * (time.elapsed_seconds() * 2.0).sin();
+        }
+        if let Ok((_, mut transform)) = bobbers.get_single_mut() {
+            *transform = position;
+        }
+        else {
+            commands.spawn((
+                SpriteBundle {
+                    texture: object_assets.bobber.clone(),
+                    transform: position,
+                    ..default()
+                },
+                BobberMarker,
+                RemoveOnStateChange {},
+            ));
+        }
+    }
     /// Marks chunks to be rendered, downloaded, and unrendered. This system is essential to
     /// the world loading and collision loading
     pub fn system_mark_chunks(mut selfs: ResMut<Reality>, mut netty: ResMut<Netty>) {
@@ -647,14 +866,19 @@ impl Reality {
     }
     pub fn system_update_objects(
         mut selfs: ResMut<Reality>,
-        mut objects: Query<(&mut Transform, &mut Object)>,
+        mut objects: Query<(&mut Transform, &mut Object, Option<&mut Sprite>)>,
     ) {
         for updateable in &selfs.objects_to_update {
-            objects.for_each_mut(|(mut transform, mut object)| {
+            objects.for_each_mut(|(mut transform, mut object, sprite)| {
                 if object.uuid == updateable.uuid {
                     object.update(updateable.clone());
                     transform.translation.x = object.pos.translation.x;
                     transform.translation.y = object.pos.translation.y;
+                    if let Some(mut sprite) = sprite {
+                        if let ObjectType::Farmland(farmland) = &object.rep {
+                            sprite.color = farmland_color(farmland);
+                        }
+                    }
                 }
             });
         }
@@ -687,10 +911,64 @@ impl Reality {
                         object.clone(),
                     ));
                 }
-                ObjectType::GroundItem(item) => {
+                ObjectType::GroundItem(stack) => {
+                    commands.spawn((
+                        SpriteBundle {
+                            texture: item_assets.pick_from_item(Some(stack.item)),
+                            transform: Transform::from_xyz(
+                                object.pos.translation.x,
+                                object.pos.translation.y,
+                                FRONT_OBJECTS,
+                            ),
+                            ..default()
+                        },
+                        object.clone(),
+                    ));
+                }
+                ObjectType::Farmland(farmland) => {
+                    commands.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: farmland_color(farmland),
+                                ..default()
+                            },
+                            texture: obj_assets.farmland.clone(),
+                            transform: Transform::from_xyz(
+                                object.pos.translation.x,
+                                object.pos.translation.y,
+                                BACKGROUND + 0.5,
+                            ),
+                            ..default()
+                        },
+                        object.clone(),
+                    ));
+                }
+                ObjectType::Rock(ore, _str) => {
+                    commands.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: ore_color(*ore),
+                                ..default()
+                            },
+                            texture: obj_assets.rock.clone(),
+                            transform: Transform::from_xyz(
+                                object.pos.translation.x,
+                                object.pos.translation.y,
+                                FRONT_OBJECTS,
+                            ),
+                            ..default()
+                        },
+                        object.clone(),
+                    ));
+                }
+                ObjectType::Station(station) => {
                     commands.spawn((
                         SpriteBundle {
-                            texture: item_assets.pick_from_item(Some(*item)),
+                            sprite: Sprite {
+                                color: station_color(*station),
+                                ..default()
+                            },
+                            texture: obj_assets.station.clone(),
                             transform: Transform::from_xyz(
                                 object.pos.translation.x,
                                 object.pos.translation.y,
@@ -723,6 +1001,7 @@ impl Reality {
         mut commands: Commands,
         textures: Res<UIAssets>,
         items: Res<ItemAssets>,
+        fonts: Res<FontAssets>,
         selfs: Res<Reality>,
     ) {
         for i in 0..10 {
@@ -758,6 +1037,26 @@ impl Reality {
                 },
                 UILocked {},
             ));
+            commands.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font: fonts.simvoni.clone(),
+                            font_size: 28.0,
+                            color: Color::BLACK,
+                        },
+                    ),
+                    text_anchor: bevy::sprite::Anchor::BottomRight,
+                    transform: Transform::from_xyz(0.0, 0.0, UI_TEXT),
+                    ..default()
+                },
+                HotbarMarker {
+                    location: i,
+                    type_: 4,
+                },
+                UILocked {},
+            ));
         }
         commands.spawn((
             SpriteBundle {
@@ -780,14 +1079,18 @@ impl Reality {
         selfs: Res<Reality>,
         textures: Res<ItemAssets>,
         mut query: Query<(&HotbarMarker, &mut Handle<Image>)>,
+        mut counts: Query<(&HotbarMarker, &mut Text)>,
     ) {
+        let hotbar = &selfs.player.inventory.hotbar;
         query.for_each_mut(|(marker, mut texture)| {
             if marker.type_ == 3 {
-                texture
-                    .set(Box::new(textures.pick_from_item(
-                        selfs.player.inventory.hotbar[marker.location],
-                    )))
-                    .unwrap();
+                let item = hotbar[marker.location].map(|stack| stack.item);
+                texture.set(Box::new(textures.pick_from_item(item))).unwrap();
+            }
+        });
+        counts.for_each_mut(|(marker, mut text)| {
+            if marker.type_ == 4 {
+                text.sections[0].value = ItemStack::count_label(&hotbar[marker.location]);
             }
         });
     }
@@ -795,13 +1098,99 @@ impl Reality {
         query.for_each_mut(|(mut location, spot)| {
             location.translation.x = spot.location as f32 * 64.0 - (64.0 * 5.0);
             location.translation.y = -(1080.0 / 2.0) + 32.0;
+            if spot.type_ == 4 {
+                // Bottom right corner of the slot
+                location.translation.x += 28.0;
+                location.translation.y -= 28.0;
+            }
+        });
+    }
+    pub fn system_spawn_clock(mut commands: Commands, fonts: Res<FontAssets>) {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::NONE,
+                    custom_size: Some(Vec2::new(1920.0, 1080.0)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, UI_IMG - 1.0),
+                ..default()
+            },
+            ClockMarker { type_: 1 },
+            UILocked {},
+            RemoveOnStateChange {},
+        ));
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    "",
+                    TextStyle {
+                        font: fonts.apple_tea.clone(),
+                        font_size: 48.0,
+                        color: Color::BLACK,
+                    },
+                ),
+                text_anchor: bevy::sprite::Anchor::CenterLeft,
+                transform: Transform::from_xyz(0.0, 0.0, UI_TEXT),
+                ..default()
+            },
+            ClockMarker { type_: 2 },
+            UILocked {},
+            RemoveOnStateChange {},
+        ));
+    }
+    /// Runs the clock between updates from the server, and shows it.
+    pub fn system_update_clock(
+        mut selfs: ResMut<Reality>,
+        time: Res<Time>,
+        mut tints: Query<(&ClockMarker, &mut Sprite)>,
+        mut texts: Query<(&ClockMarker, &mut Text)>,
+    ) {
+        let clock = if let Some(clock) = &mut selfs.clock {
+            clock.advance(time.delta_seconds_f64());
+            *clock
+        }
+        else {
+            return;
+        };
+        let darkness = 1.0 - clock.daylight();
+        tints.for_each_mut(|(marker, mut sprite)| {
+            if marker.type_ == 1 {
+                sprite.color = NIGHT_TINT.with_a(NIGHT_TINT.a() * darkness);
+            }
+        });
+        texts.for_each_mut(|(marker, mut text)| {
+            if marker.type_ == 2 {
+                text.sections[0].value = clock.to_string();
+                text.sections[0].style.color = if darkness > 0.5 {
+                    Color::WHITE
+                }
+                else {
+                    Color::BLACK
+                };
+            }
+        });
+    }
+    pub fn system_position_clock(mut query: Query<(&mut Transform, &ClockMarker)>) {
+        query.for_each_mut(|(mut location, marker)| {
+            if marker.type_ == 1 {
+                location.translation.x = 0.0;
+                location.translation.y = 0.0;
+            }
+            else {
+                // Just right of the hotbar
+                location.translation.x = 10.0 * 64.0 - (64.0 * 5.0) - 16.0;
+                location.translation.y = -(1080.0 / 2.0) + 32.0;
+            }
         });
     }
     pub fn system_scroll_hotbar(
         mut query: Query<&mut HotbarMarker>,
         mut scroll: EventReader<MouseWheel>,
         mut selfs: ResMut<Reality>,
+        mut netty: ResMut<Netty>,
     ) {
+        let selected = selfs.player.inventory.selected_slot;
         for event in scroll.iter() {
             match event.unit {
                 MouseScrollUnit::Line => {
@@ -837,6 +1226,11 @@ impl Reality {
                 }
             }
         }
+        // The server checks tools against the slot that's held
+        let slot = selfs.player.inventory.selected_slot;
+        if slot != selected && netty.server_has("selected_slot") {
+            netty.send(Packet::SelectSlot(slot));
+        }
     }
     pub fn system_player_loader(
         mut selfs: ResMut<Reality>,
@@ -1282,11 +1676,7 @@ impl Reality {
                 }));
             }
             else {
-                selfs.queue_chat(ChatMessage {
-                    text: String::from("Invalid user tag."),
-                    color: Color::RED,
-                    sent_at: std::time::Instant::now(),
-                });
+                selfs.queue_chat(ChatMessage::system("Invalid user tag.", Color::RED));
             }
             tb.clear_buffer();
             selfs.pause_closed();
@@ -1396,43 +1786,175 @@ impl Reality {
         mut selfs: ResMut<Reality>,
         mut uiman: ResMut<UIManager>,
         font_handles: Res<FontAssets>,
+        old: Query<Entity, With<ServerListEntry>>,
     ) {
-        if let Some(servers) = selfs.display_servers() {
-            for (index, server) in servers.iter().enumerate() {
+        if let Some((servers, pages)) = selfs.display_servers() {
+            old.for_each(|e| {
+                commands.entity(e).despawn();
+            });
+            uiman.remove_tag("server_list");
+            let mut spawn_text = |sections: Vec<(String, f32, Color)>, x: f32, y: f32| {
                 commands.spawn((
                     Text2dBundle {
                         text: Text {
-                            sections: vec![TextSection {
-                                value: server.public_name.clone(),
-                                style: TextStyle {
-                                    font: font_handles.simvoni.clone(),
-                                    font_size: 35.0,
-                                    color: Color::BLACK,
-                                },
-                            }],
+                            sections: sections
+                                .into_iter()
+                                .map(|(value, font_size, color)| TextSection {
+                                    value,
+                                    style: TextStyle {
+                                        font: font_handles.simvoni.clone(),
+                                        font_size,
+                                        color,
+                                    },
+                                })
+                                .collect(),
                             alignment: TextAlignment::Left,
                             linebreak_behavior: bevy::text::BreakLineOn::AnyCharacter
                         },
-                        transform: Transform::from_xyz(
-                            0.0,
-                            (1080.0 / 2.0) - 200.0 - (index as f32 * 128.0),
-                            UI_TEXT,
-                        ),
+                        transform: Transform::from_xyz(x, y, UI_TEXT),
                         ..Default::default()
                     },
+                    ServerListEntry {},
                     RemoveOnStateChange {},
                 ));
+            };
+            let mut button = |action: UIClickAction, x: f32, y: f32, size: (f32, f32)| {
                 uiman.add_ui(UIClickable {
-                    action: UIClickAction::JoinWorld(server.internal_id),
-                    location: (
-                        -200.0,
-                        ((1080.0 / 2.0) - 200.0 - (index as f32 * 128.0)) + 64.0,
-                    ),
-                    size: (400.0, 128.0),
+                    action,
+                    location: (x - (size.0 / 2.0), y + (size.1 / 2.0)),
+                    size,
                     removed_on_use: false,
-                    tag: None,
-                })
+                    tag: Some(String::from("server_list")),
+                });
+            };
+
+            // Controls along the top
+            let top = (1080.0 / 2.0) - 60.0;
+            let sort = format!("Sort: {}", selfs.server_sort.label());
+            spawn_text(vec![(sort, 35.0, Color::BLACK)], -500.0, top);
+            button(UIClickAction::CycleServerSort, -500.0, top, (360.0, 50.0));
+            let filter = if selfs.filtering_servers {
+                format!("Filter: {}_", selfs.server_filter)
+            }
+            else if selfs.server_filter.is_empty() {
+                String::from("Filter: (click to type)")
+            }
+            else {
+                format!("Filter: {}", selfs.server_filter)
+            };
+            spawn_text(vec![(filter, 35.0, Color::BLACK)], 0.0, top);
+            button(UIClickAction::FilterServers, 0.0, top, (500.0, 50.0));
+            if pages > 1 {
+                let page = format!("< {}/{pages} >", selfs.server_page + 1);
+                spawn_text(vec![(page, 35.0, Color::BLACK)], 550.0, top);
+                button(UIClickAction::PreviousServerPage, 490.0, top, (80.0, 50.0));
+                button(UIClickAction::NextServerPage, 610.0, top, (80.0, 50.0));
+            }
+
+            if servers.is_empty() {
+                let nothing_found =
+                    selfs.avalable_servers.is_empty() && selfs.local_servers.is_empty();
+                let text = if nothing_found {
+                    "No worlds yet. Create one, or ask a friend for an invite!"
+                }
+                else {
+                    "No worlds match this filter."
+                };
+                spawn_text(vec![(String::from(text), 35.0, Color::DARK_GRAY)], 0.0, 200.0);
+            }
+            for (index, server) in servers.iter().enumerate() {
+                let y = (1080.0 / 2.0) - 200.0 - (index as f32 * 150.0);
+                if server.local {
+                    let details = format!(
+                        "\nLocal network - {} - {} online - {}",
+                        server.address,
+                        server.online,
+                        server.description,
+                    );
+                    spawn_text(
+                        vec![
+                            (server.public_name.clone(), 40.0, Color::BLACK),
+                            (details, 25.0, Color::DARK_GREEN),
+                        ],
+                        0.0,
+                        y,
+                    );
+                    let action = UIClickAction::JoinLocalServer(server.internal_id);
+                    button(action, 0.0, y, (900.0, 140.0));
+                    continue;
+                }
+                let last_played = if let Some(seconds) = server.last_played {
+                    format!("played {} ago", format_duration(seconds))
+                }
+                else {
+                    String::from("never played")
+                };
+                let details = format!(
+                    "\nby {} - {}/{} online - {last_played} - {} old",
+                    server.owner,
+                    server.online,
+                    server.max_players,
+                    format_duration(server.age),
+                );
+                let mut sections = vec![
+                    (server.public_name.clone(), 40.0, Color::BLACK),
+                    (details, 25.0, Color::DARK_GRAY),
+                ];
+                if !server.description.is_empty() {
+                    sections.push((format!("\n{}", server.description), 25.0, Color::BLACK));
+                }
+                spawn_text(sections, 0.0, y);
+                button(UIClickAction::JoinWorld(server.internal_id), 0.0, y, (900.0, 140.0));
+            }
+        }
+    }
+    /// Handles sorting, filtering and paging the server list.
+    pub fn system_server_list_controls(
+        mut selfs: ResMut<Reality>,
+        mut uiman: ResMut<UIManager>,
+        mut tb: ResMut<TextBox>,
+        disk: Res<Disk>,
+        keys: Res<Input<KeyCode>>,
+    ) {
+        match uiman.queued_action {
+            Some(UIClickAction::CycleServerSort) => {
+                uiman.queued_action = None;
+                selfs.server_sort = selfs.server_sort.next();
+                selfs.push_servers = true;
+            }
+            Some(UIClickAction::FilterServers) => {
+                uiman.queued_action = None;
+                tb.set_buffer(selfs.server_filter.clone());
+                selfs.filtering_servers = true;
+                selfs.push_servers = true;
             }
+            Some(UIClickAction::NextServerPage) => {
+                uiman.queued_action = None;
+                selfs.server_page += 1;
+                selfs.push_servers = true;
+            }
+            Some(UIClickAction::PreviousServerPage) => {
+                uiman.queued_action = None;
+                selfs.server_page = selfs.server_page.saturating_sub(1);
+                selfs.push_servers = true;
+            }
+            _ => {}
+        }
+        if !selfs.filtering_servers {
+            return;
+        }
+        let ctrls = disk.control_config();
+        if keys.just_pressed(ctrls.send_chat) || keys.just_pressed(ctrls.close_menu) {
+            selfs.filtering_servers = false;
+            tb.clear_buffer();
+            selfs.push_servers = true;
+            return;
+        }
+        let typed = tb.grab_buffer().replace(['\n', '\r'], "");
+        if typed != selfs.server_filter {
+            selfs.server_filter = typed;
+            selfs.server_page = 0;
+            selfs.push_servers = true;
         }
     }
 }
@@ -1444,6 +1966,43 @@ pub enum MenuState {
     Open,
 }
 
+/// Tints farmland by how wet it is, and by how grown its crop is.
+fn farmland_color(farmland: &Farmland) -> Color {
+    let crop = if let Some(crop) = farmland.crop {
+        crop
+    }
+    else if farmland.watered {
+        return Color::rgb(0.35, 0.25, 0.15);
+    }
+    else {
+        return Color::rgb(0.55, 0.4, 0.25);
+    };
+    if farmland.ripe() {
+        return match crop {
+            Crop::Wheat => Color::rgb(0.9, 0.8, 0.3),
+            Crop::Carrot => Color::rgb(0.95, 0.55, 0.15),
+        };
+    }
+    let grown = farmland.stage as f32 / crop.stages() as f32;
+    Color::rgb(0.3, 0.45 + (0.4 * grown), 0.2)
+}
+
+/// Tints rocks by what they're made of.
+fn ore_color(ore: Ore) -> Color {
+    match ore {
+        Ore::Stone => Color::WHITE,
+        Ore::Copper => Color::rgb(1.0, 0.72, 0.5),
+        Ore::Iron => Color::rgb(0.9, 0.8, 0.78),
+    }
+}
+
+fn station_color(station: Station) -> Color {
+    match station {
+        Station::Workbench => Color::rgb(0.65, 0.45, 0.25),
+        Station::Furnace => Color::rgb(0.45, 0.45, 0.5),
+    }
+}
+
 /// true if collided, false otherwise
 fn calc_player_against_tiles(tiles: &[Tile], player: (f32, f32)) -> bool {
     for tile in tiles {
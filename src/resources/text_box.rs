@@ -14,6 +14,9 @@ impl TextBox {
     pub fn clear_buffer(&mut self) {
         self.buffer = String::new();
     }
+    pub fn set_buffer(&mut self, buffer: String) {
+        self.buffer = buffer;
+    }
     pub fn grab_buffer(&mut self) -> String {
         self.buffer.clone()
     }
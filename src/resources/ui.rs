@@ -3,7 +3,7 @@ use bevy::app::AppExit;
 use bevy_kira_audio::{Audio, AudioControl};
 use bevy_prototype_debug_lines::DebugLines;
 
-use super::{Reality, TextBox, LastState};
+use super::{network, Reality, TextBox, LastState};
 
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub enum SettingsPage {
@@ -20,6 +20,8 @@ pub struct UIManager {
     queue_player_action: bool,
     pub settings_page: SettingsPage,
     pub on_page: bool,
+    /// Is a server address being typed on the online settings page?
+    pub adding_server: bool,
 }
 
 impl UIManager {
@@ -30,6 +32,7 @@ impl UIManager {
             queue_player_action: false,
             settings_page: SettingsPage::Video,
             on_page: false,
+            adding_server: false,
         }
     }
     pub fn add_ui(&mut self, new: UIClickable) {
@@ -134,6 +137,8 @@ pub enum UIClickAction {
     DecreaseWindowScaling,
     InvitePlayer,
     DisconnectFromWorld,
+    /// The server removed us from the world, or never let us in
+    LeftWorld,
     GoToCreateWorld,
     GoToTitleScreen,
     CreateWorld,
@@ -145,6 +150,20 @@ pub enum UIClickAction {
     TabGameplaySettings,
     TabOnlineSettings,
     JoinWorld(usize),
+    AddFriend,
+    AcceptFriend(usize),
+    DeclineFriend(usize),
+    RemoveFriend(usize),
+    JoinLocalServer(usize),
+    CycleServerSort,
+    FilterServers,
+    NextServerPage,
+    PreviousServerPage,
+    SelectServer(usize),
+    RemoveServer(usize),
+    AddServer,
+    Craft(usize),
+    HoldStack(usize),
 }
 
 pub fn ui_debug_lines(man: Res<UIManager>, mut lines: ResMut<DebugLines>) {
@@ -341,26 +360,113 @@ pub fn ui_settings_camera(mut query: Query<&mut Transform, With<Camera>>) {
     });
 }
 
-pub fn ui_settings_tab(mut man: ResMut<UIManager>) {
-    if man.queued_action == Some(UIClickAction::TabVideoSettings) {
-        man.on_page = false;
-        man.settings_page = SettingsPage::Video;
-        man.remove_tag("Settings");
+pub fn ui_settings_tab(
+    mut commands: Commands,
+    mut man: ResMut<UIManager>,
+    query: Query<Entity, With<SettingsPageComp>>,
+) {
+    let page = match man.queued_action {
+        Some(UIClickAction::TabVideoSettings) => SettingsPage::Video,
+        Some(UIClickAction::TabSoundSettings) => SettingsPage::Sound,
+        Some(UIClickAction::TabGameplaySettings) => SettingsPage::Gameplay,
+        Some(UIClickAction::TabOnlineSettings) => SettingsPage::Online,
+        _ => return,
+    };
+    man.queued_action = None;
+    man.settings_page = page;
+    redraw_settings(&mut commands, &mut man, &query);
+}
+
+/// Clears the current settings page so it's drawn again next frame.
+fn redraw_settings(
+    commands: &mut Commands,
+    man: &mut UIManager,
+    query: &Query<Entity, With<SettingsPageComp>>,
+) {
+    query.for_each(|e| {
+        commands.entity(e).despawn();
+    });
+    man.on_page = false;
+    man.remove_tag("Settings");
+}
+
+/// Handles picking, removing and adding servers on the online settings page.
+pub fn ui_online_settings(
+    mut commands: Commands,
+    mut man: ResMut<UIManager>,
+    mut disk: ResMut<Disk>,
+    mut tb: ResMut<TextBox>,
+    mut texts: Query<(&mut Text, &SettingsPageComp)>,
+    query: Query<Entity, With<SettingsPageComp>>,
+    keys: Res<Input<KeyCode>>,
+) {
+    let mut book = disk.address_book();
+    match man.queued_action {
+        Some(UIClickAction::SelectServer(index)) => {
+            man.queued_action = None;
+            if index < book.servers.len() {
+                book.selected = index;
+                disk.update_address_book(book);
+            }
+            redraw_settings(&mut commands, &mut man, &query);
+            return;
+        }
+        Some(UIClickAction::RemoveServer(index)) => {
+            man.queued_action = None;
+            if index < book.servers.len() {
+                book.servers.remove(index);
+                if book.selected >= index && book.selected > 0 {
+                    book.selected -= 1;
+                }
+                disk.update_address_book(book);
+            }
+            redraw_settings(&mut commands, &mut man, &query);
+            return;
+        }
+        Some(UIClickAction::AddServer) => {
+            man.queued_action = None;
+            tb.clear_buffer();
+            man.adding_server = true;
+            redraw_settings(&mut commands, &mut man, &query);
+            return;
+        }
+        _ => {}
+    }
+    if !man.adding_server {
+        return;
     }
-    else if man.queued_action == Some(UIClickAction::TabSoundSettings) {
-        man.on_page = false;
-        man.settings_page = SettingsPage::Sound;
-        man.remove_tag("Settings");
+    let ctrls = disk.control_config();
+    if keys.just_pressed(ctrls.close_menu) {
+        tb.clear_buffer();
+        man.adding_server = false;
+        redraw_settings(&mut commands, &mut man, &query);
     }
-    else if man.queued_action == Some(UIClickAction::TabGameplaySettings) {
-        man.on_page = false;
-        man.settings_page = SettingsPage::Gameplay;
-        man.remove_tag("Settings");
+    else if keys.just_pressed(ctrls.send_chat) {
+        let typed = tb.grab_buffer();
+        if let Ok(server) = super::ServerAddress::parse(&typed) {
+            tb.clear_buffer();
+            man.adding_server = false;
+            if !book.servers.contains(&server) {
+                book.servers.push(server);
+                disk.update_address_book(book);
+            }
+            redraw_settings(&mut commands, &mut man, &query);
+        }
     }
-    else if man.queued_action == Some(UIClickAction::TabOnlineSettings) {
-        man.on_page = false;
-        man.settings_page = SettingsPage::Online;
-        man.remove_tag("Settings");
+    else {
+        let typed = tb.grab_buffer();
+        // Addresses that can't be parsed are shown in red, with why
+        let (shown, color) = match super::ServerAddress::parse(&typed) {
+            Ok(_) => (typed, Color::BLACK),
+            Err(_) if typed.is_empty() => (typed, Color::RED),
+            Err(e) => (format!("{typed} ({e})"), Color::RED),
+        };
+        texts.for_each_mut(|(mut text, component)| {
+            if component.type_ == 5 {
+                text.sections[1].value = shown.clone();
+                text.sections[1].style.color = color;
+            }
+        });
     }
 }
 
@@ -419,6 +525,8 @@ pub fn ui_close_settings(
     mut man: ResMut<UIManager>,
     mut state: ResMut<NextState<GameState>>,
     last_state: Res<LastState>,
+    netty: Option<Res<Netty>>,
+    disk: Res<Disk>,
     query: Query<Entity, With<RemoveOnStateChange>>,
 ) {
     if man.queued_action == Some(UIClickAction::CloseSettings) {
@@ -426,7 +534,13 @@ pub fn ui_close_settings(
             commands.entity(e).despawn();
         });
         man.reset_ui();
-        state.set(last_state.get());
+        // A newly picked server is used as soon as the player isn't in a world
+        let returning = last_state.get();
+        if returning == GameState::Play
+            || !network::reconnect_if_needed(&mut commands, &mut state, netty.as_deref(), &disk)
+        {
+            state.set(returning);
+        }
     }
 }
 
@@ -467,6 +581,7 @@ pub fn ui_disconnect_game(
     mut netty: ResMut<Netty>,
     mut state: ResMut<NextState<GameState>>,
     mut reality: ResMut<Reality>,
+    disk: Res<Disk>,
     audio: Res<Audio>,
     core: Res<CoreAssets>,
     audio_serve: Res<Assets<AudioSamples>>,
@@ -483,11 +598,18 @@ pub fn ui_disconnect_game(
         )>,
     >,
 ) {
-    if man.queued_action == Some(UIClickAction::DisconnectFromWorld) {
+    let action = man.queued_action;
+    if matches!(
+        action,
+        Some(UIClickAction::DisconnectFromWorld | UIClickAction::LeftWorld)
+    ) {
         let samples = audio_serve.get(&core.audio).unwrap();
         audio.play(samples.get("click"));
         man.reset_ui();
-        netty.send(Packet::LeaveWorld);
+        // The server already knows if it was the one to take us out
+        if action == Some(UIClickAction::DisconnectFromWorld) {
+            netty.send(Packet::LeaveWorld);
+        }
         query.for_each_mut(|e| {
             commands.entity(e).despawn();
         });
@@ -495,8 +617,10 @@ pub fn ui_disconnect_game(
         reality.set_player_position(Transform::from_xyz(0.0, 0.0, 0.0));
         // Fully reset because making things not conflict is hard :P
         reality.reset();
-        // Switch to titlescreen
-        state.set(GameState::TitleScreen);
+        // Switch to titlescreen, on a newly picked server if there is one
+        if !network::reconnect_if_needed(&mut commands, &mut state, Some(&*netty), &disk) {
+            state.set(GameState::TitleScreen);
+        }
     }
 }
 
@@ -1,25 +1,71 @@
 use std::{net::SocketAddr, path::PathBuf};
 
 use crate::prelude::*;
-use bevy::utils::HashMap;
+use crate::shared::capture::{Direction, Recorder, Side};
+use crate::shared::clock::WorldClock;
+use crate::shared::crafting::RecipeBook;
+use crate::shared::weather::Weather;
+use crate::shared::version::ProtocolVersion;
+use rand::Rng;
+use std::sync::{Arc, Mutex, OnceLock};
+use bevy::utils::{HashMap, HashSet};
 
 mod handler;
 use handler::handler;
 mod tick;
-use tick::tick;
+use tick::{advance, tick};
 
 mod globals;
 pub use globals::Globals;
 
-use self::tick::{profile_folder, save_folder};
+use self::tick::{profile_folder, save_folder, save_profile, save_world};
 
+mod admin;
+mod chat;
+mod commands;
+pub use commands::max_players_reply;
+mod config;
+mod crafting;
+mod farming;
+mod fishing;
+mod friends;
+mod inventory;
+mod lan;
+pub use config::Config;
+mod metrics;
+mod mining;
+mod moderation;
+pub use moderation::WordFilter;
+pub use metrics::Metrics;
+mod rate_limit;
+pub use rate_limit::RateLimiter;
+pub mod replay;
+mod save_format;
+#[cfg(test)]
+mod scenario;
 pub mod npc;
+mod party;
+pub use party::Parties;
 mod world;
 
+/// The globals netty created, shared with services that run on their own
+/// threads. Set by the first `tick`.
+static SHARED_GLOBALS: OnceLock<Arc<Mutex<Globals>>> = OnceLock::new();
+
+/// The server's globals, or None if it hasn't started ticking yet.
+fn shared_globals() -> Option<Arc<Mutex<Globals>>> {
+    SHARED_GLOBALS.get().cloned()
+}
+
 /// Starts the game server!
 #[cfg(not(target_arch = "wasm32"))]
 pub fn startup(_arguments: Vec<String>) -> ! {
     // TODO: add argument functionality back
+    let data_dir = std::env::current_dir().expect("Unable to access the current directory.");
+    let config = Config::load(&data_dir);
+    admin::launch(&config);
+    metrics::launch(&config);
+    lan::launch(&config);
     netty::server::launch_server::<Packet, Globals>(netty::server::ServerConfig {
         public_facing: true,
         tcp_port: TCP_PORT,
@@ -37,52 +83,61 @@ pub struct Profile {
     pub user: User,
     /// A list of all world ids for which this player can join
     pub avalable_games: Vec<usize>,
+    /// Users this player is friends with
+    pub friends: Vec<User>,
+    /// Users who have asked to be friends with this player
+    pub friend_requests: Vec<User>,
 }
 
-/// Returns all [Profile]s from the disk.
+/// Returns all [Profile]s from the disk, upgrading any saved by older versions.
 /// 
-/// # Panics
-/// This function can panic if there is a faliure reading the directory given by
-/// `profile_folder`.
+/// # Errors
+/// This function can return an error if there is a faliure reading the
+/// directory given by `profile_folder`.
 /// 
-/// This function can panic if a file in the above directory has no file
-/// extension, cannot be read, or cannot be deserialized.
-pub fn profiles() -> Vec<Profile> {
+/// This function can return an error if a profile cannot be read, cannot be
+/// deserialized, or was saved by a newer version.
+pub fn profiles(data_dir: &std::path::Path) -> Result<Vec<Profile>, anyhow::Error> {
+    use anyhow::Context;
+    use std::ffi::OsStr;
+
     let mut saved_users = vec![];
-    for file in std::fs::read_dir(profile_folder()).expect("Unable to access profiles.") {
-        let wrkabl = file.unwrap().path();
-        if wrkabl.extension().expect("File had no extension.") == "bic" {
-            saved_users.push(
-                bincode::deserialize(&std::fs::read(wrkabl).expect("Unable to read a profile."))
-                    .expect("Encountered a courrupted profile."),
-            );
+    for file in std::fs::read_dir(profile_folder(data_dir))? {
+        let path = file?.path();
+        if path.extension() == Some(OsStr::new("bic")) {
+            let profile = save_format::decode_profile(&std::fs::read(&path)?)
+                .with_context(|| format!("Unable to load the profile {}", path.display()))?;
+            saved_users.push(profile);
         }
     }
-    saved_users
+    Ok(saved_users)
 }
 
-/// Returns all [SaveGame]s from the disk.
+/// Returns all [SaveGame]s from the disk, upgrading any saved by older versions.
 /// 
 /// # Errors
 /// This function can return an error if there is a faliure reading the
 /// directory given by `save_folder`.
 /// 
-/// This function can return an error if a file in the above directory has no
-/// file extension, cannot be read, or cannot be deserialized.
-pub fn get_disk_savegames() -> Result<Vec<SaveGame>, anyhow::Error> {
+/// This function can return an error if a save cannot be read, cannot be
+/// deserialized, or was saved by a newer version.
+pub fn get_disk_savegames(data_dir: &std::path::Path) -> Result<Vec<SaveGame>, anyhow::Error> {
+    use anyhow::Context;
     use std::ffi::OsStr;
 
     // A list that will be returned with all loaded games on completion
     let mut loaded_saves = vec![];
     // Read the directory containing save files
-    let directory = std::fs::read_dir(save_folder())?;
+    let directory = std::fs::read_dir(save_folder(data_dir))?;
     // For each file (save) in this directory...
     for pfile in directory {
         let file = pfile?;
         // If the file ends with a .bic extension, it's probably a save
         if file.path().extension() == Some(OsStr::new("bic")) {
             // Add the deserialized data into our list of loaded games
-            loaded_saves.push(bincode::deserialize(&std::fs::read(file.path())?)?);
+            let save = save_format::decode_world(&std::fs::read(file.path())?)
+                .with_context(|| format!("Unable to load the world {}", file.path().display()))?;
+            loaded_saves.push(save);
         }
     }
     // Return everything we've collected!
@@ -98,4 +153,84 @@ pub struct SaveGame {
     pub whitelist: Vec<User>,
     pub played_before: Vec<User>,
     pub owner: User,
+    /// Players who can mute others in this world
+    pub moderators: Vec<User>,
+    /// Muted players, and the unix time (in seconds) their mute ends
+    pub mutes: HashMap<User, u64>,
+    /// Shown in the server list, set by the owner
+    pub description: String,
+    /// Amount of players allowed in this world at once
+    pub max_players: usize,
+    /// Unix time (in seconds) this world was created
+    pub created_at: u64,
+    /// Unix time (in seconds) someone last joined this world, or 0 if no one
+    /// has
+    pub last_played: u64,
+    /// Seconds of game time this world has been simulated for
+    pub clock: f64,
+    /// Seconds in one day of this world
+    pub day_length: f64,
+    pub weather: Weather,
+    /// Value of `clock` when the weather will next change
+    pub next_weather: f64,
+    /// Dropped items, and the value of `clock` when they can be picked up
+    pub pickup_after: HashMap<uuid::Uuid, f64>,
+}
+
+impl SaveGame {
+    /// A world that has only just been created.
+    pub fn new(
+        public_name: String,
+        internal_id: usize,
+        path: PathBuf,
+        owner: User,
+        rng: &mut impl Rng,
+    ) -> SaveGame {
+        SaveGame {
+            public_name,
+            internal_id,
+            data: world::World::new(),
+            path,
+            whitelist: vec![owner.clone()],
+            played_before: vec![],
+            owner,
+            moderators: vec![],
+            mutes: default(),
+            description: String::new(),
+            max_players: DEFAULT_MAX_PLAYERS,
+            created_at: moderation::unix_now(),
+            last_played: 0,
+            clock: 0.0,
+            day_length: DEFAULT_DAY_LENGTH,
+            weather: Weather::Clear,
+            next_weather: weather_length(rng),
+            pickup_after: default(),
+        }
+    }
+    /// The time in this world, as sent to players.
+    pub fn world_clock(&self) -> WorldClock {
+        WorldClock {
+            clock: self.clock,
+            day_length: self.day_length,
+        }
+    }
+    /// Changes the weather if it's been long enough. Returns the new weather
+    /// if it changed.
+    pub fn update_weather(&mut self, rng: &mut impl Rng) -> Option<Weather> {
+        if self.clock < self.next_weather {
+            return None;
+        }
+        self.next_weather = self.clock + weather_length(rng);
+        let weather = self.weather.next(rng.gen());
+        if weather == self.weather {
+            return None;
+        }
+        self.weather = weather;
+        Some(weather)
+    }
+}
+
+/// How long a spell of weather lasts, in seconds.
+fn weather_length(rng: &mut impl Rng) -> f64 {
+    random(rng, WEATHER_MIN_LENGTH, WEATHER_MAX_LENGTH) as f64
 }
@@ -0,0 +1,221 @@
+//! A local control socket for managing a running server from scripts.
+//!
+//! The socket listens on localhost only. Each request is a single line of JSON
+//! containing the configured shared secret and a command, and each one gets a
+//! single line of JSON back. For example:
+//!
+//! ```text
+//! {"secret": "hunter2", "command": "ListPlayers"}
+//! {"secret": "hunter2", "command": {"Broadcast": "Restarting in 5 minutes!"}}
+//! ```
+
+use super::{shared_globals, Config};
+use crate::prelude::*;
+use crate::resources::ChatMessage;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+#[derive(Deserialize)]
+struct AdminRequest {
+    secret: String,
+    command: AdminCommand,
+}
+
+#[derive(Deserialize)]
+enum AdminCommand {
+    /// List every online player and the world they're in
+    ListPlayers,
+    /// Save every world and profile now
+    Save,
+    /// Send a chat message to every player in a world
+    Broadcast(String),
+    /// Remove a user from the world they're in
+    Kick(User),
+    /// Get information about a world
+    WorldInfo(usize),
+}
+
+#[derive(Serialize)]
+enum AdminResponse {
+    Players(Vec<OnlinePlayer>),
+    Saved,
+    /// (Amount of players the message was sent to)
+    Broadcasted(usize),
+    Kicked,
+    WorldInfo(WorldInfo),
+    Error(String),
+}
+
+#[derive(Serialize)]
+struct OnlinePlayer {
+    user: User,
+    world: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct WorldInfo {
+    public_name: String,
+    internal_id: usize,
+    owner: User,
+    whitelist: Vec<User>,
+    online_players: Vec<User>,
+    loaded_chunks: usize,
+    objects: usize,
+}
+
+/// Starts the admin socket on its own thread if it's enabled in `config`.
+/// Requests made before the server starts ticking are refused.
+pub fn launch(config: &Config) {
+    let port = if let Some(port) = config.admin_port {
+        port
+    }
+    else {
+        return;
+    };
+    if config.admin_secret.is_empty() {
+        warn!("The admin socket is enabled but has no secret set. It will not be started.");
+        return;
+    }
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Unable to start the admin socket on port {port}: {e}");
+            return;
+        }
+    };
+    info!("Admin socket listening on 127.0.0.1:{port}");
+    let secret = config.admin_secret.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let secret = secret.clone();
+                    std::thread::spawn(move || connection(stream, secret));
+                }
+                Err(e) => warn!("Admin socket connection failed: {e}"),
+            }
+        }
+    });
+}
+
+/// Handles requests from one admin connection until it closes.
+fn connection(stream: TcpStream, secret: String) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(e) => {
+            warn!("Unable to set up an admin connection: {e}");
+            return;
+        }
+    };
+    for line in BufReader::new(stream).lines() {
+        let line = if let Ok(line) = line {
+            line
+        }
+        else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<AdminRequest>(&line) {
+            Ok(request) => {
+                if !secrets_match(&request.secret, &secret) {
+                    warn!("An admin request was made with an incorrect secret");
+                    AdminResponse::Error(String::from("Incorrect secret"))
+                }
+                else if let Some(globals) = shared_globals() {
+                    run_command(request.command, &globals)
+                }
+                else {
+                    AdminResponse::Error(String::from("The server is still starting"))
+                }
+            }
+            Err(e) => AdminResponse::Error(format!("Malformed request: {e}")),
+        };
+        let mut enc = serde_json::to_vec(&response).expect("Unable to serialize an admin response.");
+        enc.push(b'\n');
+        if writer.write_all(&enc).is_err() {
+            return;
+        }
+    }
+}
+
+/// Compares a request's secret to the real one without stopping at the first
+/// difference, so response times don't give away how close a guess was.
+fn secrets_match(given: &str, secret: &str) -> bool {
+    let (given, secret) = (given.as_bytes(), secret.as_bytes());
+    let mut difference = given.len() ^ secret.len();
+    for (index, byte) in secret.iter().enumerate() {
+        difference |= (byte ^ given.get(index).copied().unwrap_or(0)) as usize;
+    }
+    difference == 0
+}
+
+fn run_command(command: AdminCommand, globals: &Arc<Mutex<Globals>>) -> AdminResponse {
+    let mut globals = globals.lock().unwrap();
+    match command {
+        AdminCommand::ListPlayers => {
+            let mut players = vec![];
+            for user in globals.user_to_addr.keys() {
+                players.push(OnlinePlayer {
+                    user: user.clone(),
+                    world: globals.user_to_world.get(user).copied(),
+                });
+            }
+            AdminResponse::Players(players)
+        }
+        AdminCommand::Save => {
+            globals.save_all();
+            AdminResponse::Saved
+        }
+        AdminCommand::Broadcast(text) => {
+            let mut recipients = vec![];
+            for world in &globals.worlds {
+                for (user, _, _) in &world.data.players {
+                    if let Some(addr) = globals.user_to_addr.get(user) {
+                        recipients.push(*addr);
+                    }
+                }
+            }
+            let amount = recipients.len();
+            for addr in recipients {
+                globals.queued_packets.push((
+                    Packet::ChatMessage(ChatMessage::system(
+                        format!("[Server] {text}"),
+                        Color::PURPLE,
+                    )),
+                    addr,
+                ));
+            }
+            AdminResponse::Broadcasted(amount)
+        }
+        AdminCommand::Kick(user) => {
+            if !globals.user_to_world.contains_key(&user) {
+                return AdminResponse::Error(String::from("That user is not in a world"));
+            }
+            let mut outgoing = globals.remove_from_world(&user);
+            if let Some(addr) = globals.user_to_addr.get(&user) {
+                outgoing.push((Packet::Kicked(String::from("Kicked by the server")), *addr));
+            }
+            globals.queued_packets.append(&mut outgoing);
+            AdminResponse::Kicked
+        }
+        AdminCommand::WorldInfo(id) => {
+            for world in &globals.worlds {
+                if world.internal_id == id {
+                    return AdminResponse::WorldInfo(WorldInfo {
+                        public_name: world.public_name.clone(),
+                        internal_id: world.internal_id,
+                        owner: world.owner.clone(),
+                        whitelist: world.whitelist.clone(),
+                        online_players: world.data.players.iter().map(|p| p.0.clone()).collect(),
+                        loaded_chunks: world.data.terrain.len(),
+                        objects: world.data.objects.len(),
+                    });
+                }
+            }
+            AdminResponse::Error(format!("No world with id {id}"))
+        }
+    }
+}
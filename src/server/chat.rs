@@ -0,0 +1,98 @@
+//! Sends player chat messages to everyone in their channel.
+
+use super::commands::system_message;
+use crate::prelude::*;
+use crate::resources::{ChatChannel, ChatMessage};
+use std::net::SocketAddr;
+
+/// Sends a chat message from a player in `world` to everyone in its channel.
+/// Returns the packets that need to be sent.
+pub fn route(
+    globals: &mut Globals,
+    sender: &User,
+    source_addr: SocketAddr,
+    world: usize,
+    message: ChatMessage,
+) -> Vec<(Packet, SocketAddr)> {
+    let mut outgoing = vec![];
+    let mut sendable_message = message.clone();
+    sendable_message.sender = Some(sender.clone());
+    sendable_message
+        .text
+        .insert_str(0, &format!("[{}] ", sender.username));
+    match &message.channel {
+        ChatChannel::World => {
+            for this_ip in globals.world_addrs(world) {
+                outgoing.push((Packet::ChatMessage(sendable_message.clone()), this_ip));
+            }
+            globals.worlds[world].data.log_chat(sendable_message);
+        }
+        ChatChannel::Global => {
+            for user in globals.user_to_world.keys() {
+                if let Some(this_ip) = globals.user_to_addr.get(user) {
+                    outgoing.push((Packet::ChatMessage(sendable_message.clone()), *this_ip));
+                }
+            }
+        }
+        ChatChannel::Party => {
+            if let Some(party) = globals.parties.party_of(sender) {
+                for member in &party.members {
+                    if let Some(this_ip) = globals.user_to_addr.get(member) {
+                        outgoing.push((Packet::ChatMessage(sendable_message.clone()), *this_ip));
+                    }
+                }
+            }
+            else {
+                outgoing.push((
+                    system_message("You aren't in a party. (Try /party invite)", Color::RED),
+                    source_addr,
+                ));
+            }
+        }
+        ChatChannel::Whisper(target) => {
+            outgoing.append(&mut whisper(
+                globals,
+                sender,
+                source_addr,
+                target,
+                message.text,
+            ));
+        }
+        ChatChannel::System => {
+            warn!("{sender} tried to send a system message");
+        }
+    }
+    outgoing
+}
+
+/// Sends a private message to one player, and echoes it back to the sender.
+pub fn whisper(
+    globals: &Globals,
+    sender: &User,
+    source_addr: SocketAddr,
+    target: &User,
+    text: String,
+) -> Vec<(Packet, SocketAddr)> {
+    let addr = if let Some(addr) = globals.user_to_addr.get(target) {
+        *addr
+    }
+    else {
+        return vec![(
+            system_message(format!("{target} isn't online."), Color::RED),
+            source_addr,
+        )];
+    };
+    let message = |text: String, other: &User| {
+        Packet::ChatMessage(ChatMessage {
+            text,
+            color: Color::BLACK,
+            channel: ChatChannel::Whisper(other.clone()),
+            sender: Some(sender.clone()),
+            sent_at: std::time::Instant::now(),
+        })
+    };
+    vec![
+        (message(format!("{sender} -> you: {text}"), sender), addr),
+        (message(format!("you -> {target}: {text}"), target), source_addr),
+    ]
+}
@@ -0,0 +1,635 @@
+//! Chat commands. Any chat message starting with `/` is run as a command
+//! instead of being sent to other players.
+
+use super::{chat, moderation};
+use crate::prelude::*;
+use crate::resources::ChatMessage;
+use crate::shared::weather::Weather;
+use std::net::SocketAddr;
+
+/// What a player is allowed to do in the world they're in.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Role {
+    Player,
+    Moderator,
+    Owner,
+}
+
+impl Role {
+    pub fn of(globals: &Globals, world: usize, user: &User) -> Role {
+        if &globals.worlds[world].owner == user {
+            Role::Owner
+        }
+        else if globals.worlds[world].moderators.contains(user) {
+            Role::Moderator
+        }
+        else {
+            Role::Player
+        }
+    }
+}
+
+/// Everything a command needs to run.
+pub struct Context<'a> {
+    pub globals: &'a mut Globals,
+    pub sender: User,
+    pub source_addr: SocketAddr,
+    /// Index of the sender's world in `Globals.worlds`
+    pub world: usize,
+    pub outgoing: Vec<(Packet, SocketAddr)>,
+}
+
+impl Context<'_> {
+    /// Sends a system message to whoever ran the command.
+    pub fn reply(&mut self, text: impl Into<String>) {
+        self.outgoing.push((system_message(text, Color::DARK_GRAY), self.source_addr));
+    }
+    /// Sends a packet to everyone in the sender's world.
+    pub fn send_world(&mut self, packet: Packet) {
+        for addr in self.globals.world_addrs(self.world) {
+            self.outgoing.push((packet.clone(), addr));
+        }
+    }
+    /// Checks that the sender is allowed to say something, returning the
+    /// censored text.
+    fn check_chat(&mut self, text: &str) -> Result<String, CommandError> {
+        self.globals
+            .check_chat(self.world, &self.sender, text)
+            .map_err(CommandError::Message)
+    }
+    /// Moves a player in the sender's world and tells everyone about it.
+    fn move_player(&mut self, user: &User, pos: Transform) {
+        for player in &mut self.globals.worlds[self.world].data.players {
+            if &player.0 == user {
+                player.1 = pos;
+            }
+        }
+        self.send_world(Packet::PlayerPositionUpdate(user.clone(), pos));
+    }
+}
+
+/// Creates a chat message that didn't come from a player.
+pub fn system_message(text: impl Into<String>, color: Color) -> Packet {
+    Packet::ChatMessage(ChatMessage::system(text, color))
+}
+
+enum CommandError {
+    /// The command was used incorrectly; show its usage.
+    Usage,
+    /// Something went wrong; show this message.
+    Message(String),
+}
+
+struct Command {
+    name: &'static str,
+    usage: &'static str,
+    description: &'static str,
+    /// The lowest role allowed to use this command
+    role: Role,
+    /// Is this command only avalable in dev builds?
+    dev_only: bool,
+    run: fn(&mut Context, &[&str]) -> Result<(), CommandError>,
+}
+
+impl Command {
+    fn allowed(&self, role: Role) -> bool {
+        role >= self.role && (DEV_BUILD || !self.dev_only)
+    }
+}
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        usage: "/help [command]",
+        description: "Lists commands, or explains one.",
+        role: Role::Player,
+        dev_only: false,
+        run: help,
+    },
+    Command {
+        name: "who",
+        usage: "/who",
+        description: "Lists the players in this world.",
+        role: Role::Player,
+        dev_only: false,
+        run: who,
+    },
+    Command {
+        name: "msg",
+        usage: "/msg <user#tag> <message>",
+        description: "Sends a private message.",
+        role: Role::Player,
+        dev_only: false,
+        run: msg,
+    },
+    Command {
+        name: "party",
+        usage: "/party invite <user#tag> | /party accept | /party leave | /party list",
+        description: "Manages your party. Party chat reaches members in any world.",
+        role: Role::Player,
+        dev_only: false,
+        run: party,
+    },
+    Command {
+        name: "me",
+        usage: "/me <action>",
+        description: "Describes something you're doing.",
+        role: Role::Player,
+        dev_only: false,
+        run: me,
+    },
+    Command {
+        name: "spawn",
+        usage: "/spawn",
+        description: "Takes you back to the world's spawn.",
+        role: Role::Player,
+        dev_only: false,
+        run: spawn,
+    },
+    Command {
+        name: "invite",
+        usage: "/invite <user#tag>",
+        description: "Lets a player join this world.",
+        role: Role::Owner,
+        dev_only: false,
+        run: invite,
+    },
+    Command {
+        name: "describe",
+        usage: "/describe <description>",
+        description: "Sets the description shown in the server list.",
+        role: Role::Owner,
+        dev_only: false,
+        run: describe,
+    },
+    Command {
+        name: "maxplayers",
+        usage: "/maxplayers <amount>",
+        description: "Sets how many players can be in this world at once.",
+        role: Role::Owner,
+        dev_only: false,
+        run: max_players,
+    },
+    Command {
+        name: "daylength",
+        usage: "/daylength <minutes>",
+        description: "Sets how long a day lasts in this world.",
+        role: Role::Owner,
+        dev_only: false,
+        run: day_length,
+    },
+    Command {
+        name: "weather",
+        usage: "/weather <clear|rain|storm|fog>",
+        description: "Changes the weather in this world.",
+        role: Role::Owner,
+        dev_only: false,
+        run: weather,
+    },
+    Command {
+        name: "mute",
+        usage: "/mute <user#tag> <duration> (ex 30s, 10m, 2h, 1d)",
+        description: "Stops a player from chatting in this world for a while.",
+        role: Role::Moderator,
+        dev_only: false,
+        run: mute,
+    },
+    Command {
+        name: "unmute",
+        usage: "/unmute <user#tag>",
+        description: "Lets a muted player chat again.",
+        role: Role::Moderator,
+        dev_only: false,
+        run: unmute,
+    },
+    Command {
+        name: "mod",
+        usage: "/mod <user#tag>",
+        description: "Lets a player mute others in this world.",
+        role: Role::Owner,
+        dev_only: false,
+        run: add_moderator,
+    },
+    Command {
+        name: "unmod",
+        usage: "/unmod <user#tag>",
+        description: "Takes away a player's moderator role.",
+        role: Role::Owner,
+        dev_only: false,
+        run: remove_moderator,
+    },
+    Command {
+        name: "kick",
+        usage: "/kick <user#tag>",
+        description: "Removes a player from this world.",
+        role: Role::Owner,
+        dev_only: false,
+        run: kick,
+    },
+    Command {
+        name: "give",
+        usage: "/give <item>",
+        description: "Gives you an item.",
+        role: Role::Owner,
+        dev_only: true,
+        run: give,
+    },
+    Command {
+        name: "tp",
+        usage: "/tp <user#tag> | /tp <x> <y>",
+        description: "Teleports you to a player or location.",
+        role: Role::Owner,
+        dev_only: true,
+        run: tp,
+    },
+];
+
+/// Runs a chat message starting with `/` as a command. Returns the packets
+/// that need to be sent.
+pub fn run(
+    globals: &mut Globals,
+    sender: User,
+    source_addr: SocketAddr,
+    world: usize,
+    text: &str,
+) -> Vec<(Packet, SocketAddr)> {
+    let mut args = text.trim_start_matches('/').split_whitespace();
+    let name = args.next().unwrap_or_default().to_lowercase();
+    let args: Vec<&str> = args.collect();
+    let role = Role::of(globals, world, &sender);
+    let mut ctx = Context {
+        globals,
+        sender,
+        source_addr,
+        world,
+        outgoing: vec![],
+    };
+    let command = COMMANDS.iter().find(|command| command.name == name);
+    match command {
+        Some(command) if command.allowed(role) => match (command.run)(&mut ctx, &args) {
+            Ok(()) => {}
+            Err(CommandError::Usage) => {
+                ctx.reply(format!("Usage: {}", command.usage));
+            }
+            Err(CommandError::Message(text)) => {
+                ctx.outgoing.push((system_message(text, Color::RED), source_addr));
+            }
+        },
+        Some(_) => {
+            ctx.outgoing.push((
+                system_message("You don't have permission to use that command.", Color::RED),
+                source_addr,
+            ));
+        }
+        None => {
+            ctx.outgoing.push((
+                system_message(format!("Unknown command /{name}. Try /help"), Color::RED),
+                source_addr,
+            ));
+        }
+    }
+    ctx.outgoing
+}
+
+fn parse_user(from: &str) -> Result<User, CommandError> {
+    User::parse(from).ok_or_else(|| {
+        CommandError::Message(format!("{from} isn't a valid user. (ex PlayerName#1234)"))
+    })
+}
+
+fn help(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    let role = Role::of(ctx.globals, ctx.world, &ctx.sender);
+    if let Some(name) = args.first() {
+        let name = name.trim_start_matches('/');
+        let command = COMMANDS
+            .iter()
+            .find(|command| command.name == name && command.allowed(role))
+            .ok_or_else(|| CommandError::Message(format!("Unknown command /{name}")))?;
+        ctx.reply(format!("{} - {}", command.usage, command.description));
+    }
+    else {
+        let names: Vec<String> = COMMANDS
+            .iter()
+            .filter(|command| command.allowed(role))
+            .map(|command| format!("/{}", command.name))
+            .collect();
+        ctx.reply(format!("Commands: {}", names.join(", ")));
+    }
+    Ok(())
+}
+
+fn who(ctx: &mut Context, _args: &[&str]) -> Result<(), CommandError> {
+    let names: Vec<String> = ctx.globals.worlds[ctx.world]
+        .data
+        .players
+        .iter()
+        .map(|(user, _, _)| user.to_string())
+        .collect();
+    ctx.reply(format!("Online ({}): {}", names.len(), names.join(", ")));
+    Ok(())
+}
+
+fn msg(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    if args.len() < 2 {
+        return Err(CommandError::Usage);
+    }
+    let target = parse_user(args[0])?;
+    let text = ctx.check_chat(&args[1..].join(" "))?;
+    let mut whisper = chat::whisper(ctx.globals, &ctx.sender, ctx.source_addr, &target, text);
+    ctx.outgoing.append(&mut whisper);
+    Ok(())
+}
+
+fn party(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    match args {
+        ["invite", target] => {
+            let target = parse_user(target)?;
+            if target == ctx.sender {
+                return Err(CommandError::Message(String::from("You can't invite yourself.")));
+            }
+            let addr = *ctx
+                .globals
+                .user_to_addr
+                .get(&target)
+                .ok_or_else(|| CommandError::Message(format!("{target} isn't online.")))?;
+            if !ctx.globals.parties.invite(&ctx.sender, &target) {
+                return Err(CommandError::Message(String::from(
+                    "Only the party leader can invite players.",
+                )));
+            }
+            ctx.outgoing.push((
+                system_message(
+                    format!("{} invited you to their party. (/party accept)", ctx.sender),
+                    Color::TEAL,
+                ),
+                addr,
+            ));
+            ctx.reply(format!("Invited {target} to your party."));
+        }
+        ["accept"] => {
+            let members = ctx
+                .globals
+                .parties
+                .accept(&ctx.sender)
+                .ok_or_else(|| {
+                    CommandError::Message(String::from("You don't have a party invite."))
+                })?
+                .members
+                .clone();
+            let text = format!("{} joined the party.", ctx.sender);
+            send_users(ctx, &members, system_message(text, Color::TEAL));
+        }
+        ["leave"] => {
+            if ctx.globals.parties.party_of(&ctx.sender).is_none() {
+                return Err(CommandError::Message(String::from("You aren't in a party.")));
+            }
+            let remaining = ctx.globals.parties.leave(&ctx.sender);
+            let text = format!("{} left the party.", ctx.sender);
+            send_users(ctx, &remaining, system_message(text, Color::TEAL));
+            ctx.reply("You left the party.");
+        }
+        ["list"] => {
+            let party = ctx
+                .globals
+                .parties
+                .party_of(&ctx.sender)
+                .ok_or_else(|| CommandError::Message(String::from("You aren't in a party.")))?;
+            let names: Vec<String> = party
+                .members
+                .iter()
+                .map(|member| {
+                    if member == &party.leader {
+                        format!("{member} (leader)")
+                    }
+                    else {
+                        member.to_string()
+                    }
+                })
+                .collect();
+            ctx.reply(format!("Party ({}): {}", names.len(), names.join(", ")));
+        }
+        _ => return Err(CommandError::Usage),
+    }
+    Ok(())
+}
+
+/// Sends a packet to every online user in a list.
+fn send_users(ctx: &mut Context, users: &[User], packet: Packet) {
+    for user in users {
+        if let Some(addr) = ctx.globals.user_to_addr.get(user) {
+            ctx.outgoing.push((packet.clone(), *addr));
+        }
+    }
+}
+
+fn me(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    if args.is_empty() {
+        return Err(CommandError::Usage);
+    }
+    let action = ctx.check_chat(&args.join(" "))?;
+    let text = format!("* {} {action}", ctx.sender.username);
+    let message = system_message(text, Color::DARK_GRAY);
+    if let Packet::ChatMessage(logged) = &message {
+        ctx.globals.worlds[ctx.world].data.log_chat(logged.clone());
+    }
+    ctx.send_world(message);
+    Ok(())
+}
+
+fn spawn(ctx: &mut Context, _args: &[&str]) -> Result<(), CommandError> {
+    let sender = ctx.sender.clone();
+    ctx.move_player(&sender, Transform::from_xyz(0.0, 0.0, 0.0));
+    Ok(())
+}
+
+fn invite(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    let target = parse_user(args.first().ok_or(CommandError::Usage)?)?;
+    if ctx.globals.whitelist_user(ctx.world, &target) {
+        ctx.reply(format!("{target} can now join this world."));
+        Ok(())
+    }
+    else {
+        Err(CommandError::Message(format!("{target} isn't a real user.")))
+    }
+}
+
+fn describe(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    if args.is_empty() {
+        return Err(CommandError::Usage);
+    }
+    let description = ctx.check_chat(&args.join(" "))?;
+    ctx.globals.worlds[ctx.world].description = description;
+    ctx.reply("Updated this world's description.");
+    Ok(())
+}
+
+fn max_players(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    let amount = args
+        .first()
+        .and_then(|amount| amount.parse::<usize>().ok())
+        .filter(|amount| *amount > 0)
+        .ok_or(CommandError::Usage)?;
+    ctx.globals.worlds[ctx.world].max_players = amount;
+    ctx.reply(max_players_reply(amount));
+    Ok(())
+}
+
+/// What `/maxplayers` replies with once the limit is changed. Bots wait for
+/// this before letting each other join.
+pub fn max_players_reply(amount: usize) -> String {
+    format!("Up to {amount} players can be in this world at once.")
+}
+
+fn day_length(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    let minutes = args
+        .first()
+        .and_then(|minutes| minutes.parse::<f64>().ok())
+        .filter(|minutes| minutes.is_finite() && *minutes >= 1.0)
+        .ok_or(CommandError::Usage)?;
+    ctx.globals.worlds[ctx.world].day_length = minutes * 60.0;
+    let mut packets = ctx.globals.clock_packets(ctx.world);
+    ctx.outgoing.append(&mut packets);
+    ctx.reply(format!("A day in this world now lasts {minutes} minutes."));
+    Ok(())
+}
+
+fn weather(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    let weather = args
+        .first()
+        .and_then(|name| Weather::parse(name))
+        .ok_or(CommandError::Usage)?;
+    let world = &mut ctx.globals.worlds[ctx.world];
+    world.weather = weather;
+    world.next_weather = world.clock + super::weather_length(&mut ctx.globals.rng);
+    let mut packets = ctx
+        .globals
+        .capable_world_packets(ctx.world, "weather", Packet::Weather(weather));
+    ctx.outgoing.append(&mut packets);
+    ctx.reply(format!("The weather is now {weather}."));
+    Ok(())
+}
+
+fn mute(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    let (target, duration) = match args {
+        [target, duration] => (parse_user(target)?, duration),
+        _ => return Err(CommandError::Usage),
+    };
+    let seconds = moderation::parse_duration(duration).ok_or(CommandError::Usage)?;
+    if target == ctx.sender {
+        return Err(CommandError::Message(String::from("You can't mute yourself.")));
+    }
+    if Role::of(ctx.globals, ctx.world, &target) >= Role::of(ctx.globals, ctx.world, &ctx.sender) {
+        return Err(CommandError::Message(format!("You can't mute {target}.")));
+    }
+    ctx.globals.worlds[ctx.world].mute(&target, seconds);
+    let duration = format_duration(seconds);
+    if ctx.globals.user_to_world.get(&target) == Some(&ctx.world) {
+        if let Some(addr) = ctx.globals.user_to_addr.get(&target) {
+            ctx.outgoing.push((
+                system_message(format!("You were muted for {duration}."), Color::RED),
+                *addr,
+            ));
+        }
+    }
+    ctx.reply(format!("Muted {target} for {duration}."));
+    Ok(())
+}
+
+fn unmute(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    let target = parse_user(args.first().ok_or(CommandError::Usage)?)?;
+    if !ctx.globals.worlds[ctx.world].unmute(&target) {
+        return Err(CommandError::Message(format!("{target} isn't muted.")));
+    }
+    ctx.reply(format!("Unmuted {target}."));
+    Ok(())
+}
+
+fn add_moderator(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    let target = parse_user(args.first().ok_or(CommandError::Usage)?)?;
+    let world = &mut ctx.globals.worlds[ctx.world];
+    if !world.whitelist.contains(&target) {
+        return Err(CommandError::Message(format!("{target} can't join this world.")));
+    }
+    if target == world.owner || world.moderators.contains(&target) {
+        return Err(CommandError::Message(format!("{target} is already a moderator.")));
+    }
+    world.moderators.push(target.clone());
+    ctx.reply(format!("{target} is now a moderator."));
+    Ok(())
+}
+
+fn remove_moderator(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    let target = parse_user(args.first().ok_or(CommandError::Usage)?)?;
+    let moderators = &mut ctx.globals.worlds[ctx.world].moderators;
+    if !moderators.contains(&target) {
+        return Err(CommandError::Message(format!("{target} isn't a moderator.")));
+    }
+    moderators.retain(|moderator| moderator != &target);
+    ctx.reply(format!("{target} is no longer a moderator."));
+    Ok(())
+}
+
+fn kick(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    let target = parse_user(args.first().ok_or(CommandError::Usage)?)?;
+    if target == ctx.sender {
+        return Err(CommandError::Message(String::from("You can't kick yourself.")));
+    }
+    if ctx.globals.user_to_world.get(&target) != Some(&ctx.world) {
+        return Err(CommandError::Message(format!("{target} isn't in this world.")));
+    }
+    let mut removal = ctx.globals.remove_from_world(&target);
+    ctx.outgoing.append(&mut removal);
+    if let Some(addr) = ctx.globals.user_to_addr.get(&target) {
+        ctx.outgoing.push((
+            Packet::Kicked(format!("Kicked by {}", ctx.sender)),
+            *addr,
+        ));
+    }
+    ctx.reply(format!("Kicked {target}."));
+    Ok(())
+}
+
+fn give(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    let name = args.first().ok_or(CommandError::Usage)?;
+    let item = Item::try_from_str(name)
+        .ok_or_else(|| CommandError::Message(format!("There's no item called {name}.")))?;
+    let players = &ctx.globals.worlds[ctx.world].data.players;
+    let index = players
+        .iter()
+        .position(|player| player.0 == ctx.sender)
+        .ok_or_else(|| CommandError::Message(String::from("You aren't in this world.")))?;
+    if !players[index].2.inventory.fits(item) {
+        return Err(CommandError::Message(String::from("Your inventory is full.")));
+    }
+    let mut given = ctx.globals.give_items(ctx.world, index, &[(item, 1)]);
+    ctx.outgoing.append(&mut given);
+    Ok(())
+}
+
+fn tp(ctx: &mut Context, args: &[&str]) -> Result<(), CommandError> {
+    let destination = match args {
+        [target] => {
+            let target = parse_user(target)?;
+            ctx.globals.worlds[ctx.world]
+                .data
+                .players
+                .iter()
+                .find(|player| player.0 == target)
+                .map(|player| player.1)
+                .ok_or_else(|| CommandError::Message(format!("{target} isn't in this world.")))?
+        }
+        [x, y] => {
+            let x = x.parse::<f32>().map_err(|_| CommandError::Usage)?;
+            let y = y.parse::<f32>().map_err(|_| CommandError::Usage)?;
+            // "NaN" and "inf" parse, but would be sent to everyone and saved
+            if !x.is_finite() || !y.is_finite() {
+                return Err(CommandError::Usage);
+            }
+            Transform::from_xyz(x, y, 0.0)
+        }
+        _ => return Err(CommandError::Usage),
+    };
+    let sender = ctx.sender.clone();
+    ctx.move_player(&sender, destination);
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use super::rate_limit::RateLimits;
+use crate::prelude::*;
+
+/// Settings for a game server, loaded from `server_config.json` in the data
+/// directory. Missing fields are filled in with their defaults, and a default
+/// file is written out if none exists so that it can be edited by hand.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Port for the local admin control socket. The socket is only started if
+    /// this is set and `admin_secret` is not empty.
+    pub admin_port: Option<u16>,
+    /// Shared secret every admin request must include.
+    pub admin_secret: String,
+    /// Port for the Prometheus metrics endpoint. Disabled if unset.
+    pub metrics_port: Option<u16>,
+    /// Seconds between metrics summaries in the log. 0 disables them.
+    pub metrics_log_interval: u64,
+    /// Flood protection settings for each connection.
+    pub rate_limits: RateLimits,
+    /// Longest chat message (in characters) players can send.
+    pub max_chat_length: usize,
+    /// Announce this server to clients on the local network.
+    pub lan_discovery: bool,
+    /// Name shown to clients that find this server on the local network.
+    pub server_name: String,
+    /// Gives users made on another server a profile here with the same name
+    /// and tag, so they can play on the local network. Anyone can claim any
+    /// name this way, so only enable it for trusted networks.
+    pub lan_profiles: bool,
+    /// Records every packet sent and recieved to this file, for replaying with
+    /// `socktile replay`. Disabled if unset.
+    pub capture_file: Option<String>,
+    /// Simulation steps per second for every world.
+    pub tick_rate: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            admin_port: None,
+            admin_secret: String::new(),
+            metrics_port: None,
+            metrics_log_interval: 60 * 5,
+            rate_limits: default(),
+            max_chat_length: 256,
+            lan_discovery: false,
+            server_name: String::from("Socktile Server"),
+            lan_profiles: false,
+            capture_file: None,
+            tick_rate: 20,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the server configuration from the disk, creating a default one if
+    /// none exists.
+    ///
+    /// # Panics
+    /// This function can panic if an existing configuration file cannot be
+    /// read or parsed.
+    pub fn load(data_dir: &std::path::Path) -> Config {
+        let path = config_path(data_dir);
+        if let Ok(data) = std::fs::read(&path) {
+            serde_json::from_slice(&data).expect("Encountered a courrupted server config.")
+        }
+        else {
+            let config = Config::default();
+            let enc = serde_json::to_vec_pretty(&config)
+                .expect("Unable to serialize the server config.");
+            if std::fs::write(&path, enc).is_err() {
+                warn!("Unable to write a default server config to {:?}", path);
+            }
+            config
+        }
+    }
+    /// Game time simulated by each step.
+    pub fn tick_length(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(1) / self.tick_rate.max(1)
+    }
+}
+
+/// Returns a `PathBuf` to the server's configuration file.
+pub fn config_path(data_dir: &std::path::Path) -> std::path::PathBuf {
+    let mut path = data_dir.to_path_buf();
+    path.push("server_config.json");
+    path
+}
@@ -0,0 +1,89 @@
+//! Crafting recipes, and unlocking them. Unlocks are checked in `tick`.
+
+use super::commands::system_message;
+use crate::prelude::*;
+use crate::shared::player::Recipe;
+use std::net::SocketAddr;
+
+impl Globals {
+    /// Unlocks every recipe a player now meets the conditions for. Returns
+    /// true if any were.
+    pub fn check_unlocks(&mut self, world: usize, index: usize) -> bool {
+        let data = &mut self.worlds[world].data.players[index].2;
+        let mut unlocked = false;
+        for info in &self.recipe_book.recipes {
+            if info.unlock.met(&data.stats, &data.inventory) {
+                unlocked |= data.recipes.unlock(info.name.clone());
+            }
+        }
+        unlocked
+    }
+    /// Tells a player which recipes they've unlocked.
+    pub fn recipe_packets(&self, world: usize, index: usize) -> Vec<(Packet, SocketAddr)> {
+        let (user, _, data) = &self.worlds[world].data.players[index];
+        let unlocked = data.recipes.unlocked().to_vec();
+        self.user_to_addr
+            .get(user)
+            .map(|addr| (Packet::UnlockedRecipes(unlocked), *addr))
+            .into_iter()
+            .collect()
+    }
+    /// Checks the unlocks of the players at `addrs`, such as after their items
+    /// changed, telling those who unlocked something.
+    pub fn update_unlocks(&mut self, addrs: &[SocketAddr]) -> Vec<(Packet, SocketAddr)> {
+        let mut outgoing = vec![];
+        for addr in addrs {
+            if let Some((world, index)) = self.locate_player(*addr) {
+                if self.check_unlocks(world, index) {
+                    outgoing.append(&mut self.recipe_packets(world, index));
+                }
+            }
+        }
+        outgoing
+    }
+    /// Crafts a recipe, if the player has unlocked it, is standing at its
+    /// station and has everything it uses up.
+    pub fn craft(&mut self, addr: SocketAddr, recipe: Recipe) -> Vec<(Packet, SocketAddr)> {
+        let (world, index) = if let Some(found) = self.locate_player(addr) {
+            found
+        }
+        else {
+            return vec![];
+        };
+        let info = if let Some(info) = self.recipe_book.get(&recipe) {
+            info.clone()
+        }
+        else {
+            return vec![];
+        };
+        let data = &self.worlds[world].data;
+        let (_, position, player) = &data.players[index];
+        if !player.recipes.is_unlocked(&recipe) || !info.affordable(&player.inventory) {
+            return vec![];
+        }
+        if let Some(station) = info.station {
+            let near = data.objects.iter().any(|object| {
+                object.rep == ObjectType::Station(station)
+                    && distance(object.pos, *position) <= STATION_REACH
+            });
+            if !near {
+                let message = format!("You need to be at a {} to craft that.", station.name());
+                return vec![(system_message(message, Color::DARK_GRAY), addr)];
+            }
+        }
+        let player = &mut self.worlds[world].data.players[index].2;
+        for (item, amount) in &info.inputs {
+            player.inventory.remove(*item, *amount);
+        }
+        player.stats.crafts += 1;
+        let mut outgoing = vec![];
+        if player.stats.crafts % CRAFTS_PER_LEVEL == 0 {
+            player.stats.crafting += 1;
+            let message = format!("Your crafting level is now {}!", player.stats.crafting);
+            outgoing.push((system_message(message, Color::GOLD), addr));
+        }
+        // This also unlocks anything the new items or crafting level allow
+        outgoing.append(&mut self.give_items(world, index, &info.outputs));
+        outgoing
+    }
+}
@@ -0,0 +1,249 @@
+//! Tilling, planting, watering and harvesting. Crops grow in `tick`.
+
+use crate::prelude::*;
+use crate::shared::farming::{Crop, Farmland};
+use rand::Rng;
+use std::net::SocketAddr;
+
+impl Globals {
+    /// Finds the world a connection's player is in and their index in it.
+    pub fn locate_player(&self, addr: SocketAddr) -> Option<(usize, usize)> {
+        let user = self.addr_to_user.get(&addr)?;
+        let world = *self.user_to_world.get(user)?;
+        let index = self.worlds[world]
+            .data
+            .players
+            .iter()
+            .position(|(player, _, _)| player == user)?;
+        Some((world, index))
+    }
+    /// What `pick` makes of the action of the item a player is holding. Clients
+    /// that don't say which slot they're holding get the best of their hotbar.
+    pub fn held<T: Ord>(
+        &self,
+        addr: SocketAddr,
+        world: usize,
+        index: usize,
+        pick: impl Fn(ItemAction) -> Option<T>,
+    ) -> Option<T> {
+        let inventory = &self.worlds[world].data.players[index].2.inventory;
+        if self.client_has(addr, "selected_slot") {
+            inventory.selected().and_then(|item| pick(item.action()))
+        }
+        else {
+            inventory.hotbar.iter().flatten().filter_map(|stack| pick(stack.item.action())).max()
+        }
+    }
+    /// Is a player holding an item with a matching action?
+    fn holds(
+        &self,
+        addr: SocketAddr,
+        world: usize,
+        index: usize,
+        matches: impl Fn(ItemAction) -> bool,
+    ) -> bool {
+        self.held(addr, world, index, |action| matches(action).then_some(())).is_some()
+    }
+    /// Finds the tile a player is farming, if it's within reach. Returns the
+    /// world, the player's index and the tile's centre.
+    fn farm_target(
+        &self,
+        addr: SocketAddr,
+        target: Transform,
+    ) -> Option<(usize, usize, Transform)> {
+        let (world, index) = self.locate_player(addr)?;
+        let (chunk, tile) = tile_at(target);
+        let centre = tile_centre(chunk, tile);
+        if distance(self.worlds[world].data.players[index].1, centre) > FARM_REACH {
+            return None;
+        }
+        Some((world, index, centre))
+    }
+    /// Gives a player items, and how many of each, dropping whatever doesn't
+    /// fit at their feet. Unlocks any recipes the player now qualifies for.
+    pub fn give_items(
+        &mut self,
+        world: usize,
+        index: usize,
+        items: &[(Item, usize)],
+    ) -> Vec<(Packet, SocketAddr)> {
+        let mut outgoing = vec![];
+        let (user, position, data) = &mut self.worlds[world].data.players[index];
+        let (user, position) = (user.clone(), *position);
+        let mut dropped = vec![];
+        for (item, count) in items {
+            let left = data.inventory.add(*item, *count);
+            if left > 0 {
+                dropped.push(Object {
+                    pos: position,
+                    rep: ObjectType::GroundItem(ItemStack::new(*item, left)),
+                    uuid: random_uuid(&mut self.rng),
+                });
+            }
+        }
+        let inventory = data.inventory.clone();
+        if let Some(addr) = self.user_to_addr.get(&user) {
+            outgoing.push((Packet::InventoryState(inventory), *addr));
+        }
+        for object in dropped {
+            for addr in self.world_addrs(world) {
+                outgoing.push((Packet::CreateObject(object.clone()), addr));
+            }
+            self.worlds[world].data.objects.push(object);
+        }
+        if self.check_unlocks(world, index) {
+            outgoing.append(&mut self.recipe_packets(world, index));
+        }
+        outgoing
+    }
+    /// Tills grass, or harvests a ripe crop.
+    pub fn dig(&mut self, addr: SocketAddr, target: Transform) -> Vec<(Packet, SocketAddr)> {
+        let (world, index, centre) = if let Some(found) = self.farm_target(addr, target) {
+            found
+        }
+        else {
+            return vec![];
+        };
+        if !self.holds(addr, world, index, |action| matches!(action, ItemAction::Dig(_))) {
+            return vec![];
+        }
+        let data = &mut self.worlds[world].data;
+        let existing = data
+            .objects
+            .iter()
+            .position(|object| distance(object.pos, centre) < 32.0);
+        if let Some(existing) = existing {
+            let object = &mut data.objects[existing];
+            let farmland = if let ObjectType::Farmland(farmland) = &mut object.rep {
+                farmland
+            }
+            else {
+                return vec![];
+            };
+            let crop = match farmland.crop {
+                Some(crop) if farmland.ripe() => crop,
+                _ => return vec![],
+            };
+            *farmland = Farmland::tilled();
+            let object = object.clone();
+            let harvest = [
+                (crop.produce(), random(&mut self.rng, 1, 3)),
+                (crop.seeds(), random(&mut self.rng, 1, 2)),
+            ];
+            let mut outgoing = self.object_updated(world, &object);
+            outgoing.append(&mut self.give_items(world, index, &harvest));
+            return outgoing;
+        }
+        let (chunk, tile) = tile_at(centre);
+        if data.get_or_gen(chunk)[tile.0 + (tile.1 * CHUNK_WIDTH)] != TERRAIN_GRASS {
+            return vec![];
+        }
+        let object = Object {
+            pos: centre,
+            rep: ObjectType::Farmland(Farmland::tilled()),
+            uuid: random_uuid(&mut self.rng),
+        };
+        data.objects.push(object.clone());
+        let mut outgoing: Vec<(Packet, SocketAddr)> = self
+            .world_addrs(world)
+            .into_iter()
+            .map(|addr| (Packet::CreateObject(object.clone()), addr))
+            .collect();
+        if self.rng.gen::<f32>() < SEED_FIND_CHANCE {
+            let crop = if self.rng.gen() {
+                Crop::Wheat
+            }
+            else {
+                Crop::Carrot
+            };
+            outgoing.append(&mut self.give_items(world, index, &[(crop.seeds(), 1)]));
+        }
+        outgoing
+    }
+    /// Waters farmland so its crop can grow.
+    pub fn water(&mut self, addr: SocketAddr, target: Transform) -> Vec<(Packet, SocketAddr)> {
+        let (world, index, centre) = if let Some(found) = self.farm_target(addr, target) {
+            found
+        }
+        else {
+            return vec![];
+        };
+        if !self.holds(addr, world, index, |action| action == ItemAction::Water) {
+            return vec![];
+        }
+        for object in &mut self.worlds[world].data.objects {
+            if distance(object.pos, centre) >= 32.0 {
+                continue;
+            }
+            if let ObjectType::Farmland(farmland) = &mut object.rep {
+                if farmland.watered {
+                    return vec![];
+                }
+                farmland.watered = true;
+                let object = object.clone();
+                return self.object_updated(world, &object);
+            }
+        }
+        vec![]
+    }
+    /// Plants a crop in empty farmland, using up one of the player's seeds.
+    pub fn plant(
+        &mut self,
+        addr: SocketAddr,
+        target: Transform,
+        crop: Crop,
+    ) -> Vec<(Packet, SocketAddr)> {
+        let (world, index, centre) = if let Some(found) = self.farm_target(addr, target) {
+            found
+        }
+        else {
+            return vec![];
+        };
+        if !self.worlds[world].data.players[index].2.inventory.contains(crop.seeds()) {
+            return vec![];
+        }
+        let data = &mut self.worlds[world].data;
+        let object = data.objects.iter_mut().find(|object| {
+            distance(object.pos, centre) < 32.0
+                && matches!(object.rep, ObjectType::Farmland(Farmland { crop: None, .. }))
+        });
+        let object = if let Some(object) = object {
+            object
+        }
+        else {
+            return vec![];
+        };
+        if let ObjectType::Farmland(farmland) = &mut object.rep {
+            farmland.crop = Some(crop);
+        }
+        let object = object.clone();
+        let player = &mut data.players[index];
+        player.2.inventory.remove(crop.seeds(), 1);
+        let inventory = player.2.inventory.clone();
+        let mut outgoing = self.object_updated(world, &object);
+        outgoing.push((Packet::InventoryState(inventory), addr));
+        outgoing
+    }
+}
+
+
+/// Grows a world's crops for `dt` seconds, returning the objects that changed.
+pub fn grow_crops(objects: &mut [Object], weather_waters: bool, dt: f32) -> Vec<Object> {
+    let mut changed = vec![];
+    for object in objects {
+        if let ObjectType::Farmland(farmland) = &mut object.rep {
+            let mut updated = false;
+            if weather_waters && !farmland.watered {
+                farmland.watered = true;
+                updated = true;
+            }
+            if farmland.grow(dt) {
+                updated = true;
+            }
+            if updated {
+                changed.push(object.clone());
+            }
+        }
+    }
+    changed
+}
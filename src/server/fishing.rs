@@ -0,0 +1,229 @@
+//! Casting and reeling in. Bites, and fish that get away, happen in `tick`.
+
+use crate::assets::tiles::TileTypeConfig;
+use crate::prelude::*;
+use crate::shared::clock::WorldClock;
+use rand::Rng;
+use std::net::SocketAddr;
+
+/// What can be caught where, loaded from `metadata/fishing.json`.
+#[derive(Clone, Deserialize, Debug)]
+pub struct LootTable {
+    catches: Vec<Catch>,
+    /// The name of every terrain state, by index
+    #[serde(skip)]
+    terrain: Vec<String>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+struct Catch {
+    item: Item,
+    /// Name of the terrain state this can be caught in
+    water: String,
+    /// Only caught at this time of day, if set
+    #[serde(default)]
+    time: Option<TimeOfDay>,
+    weight: f32,
+    /// Fishing skill needed to catch this
+    #[serde(default)]
+    skill: usize,
+    /// Weight added for every fishing level above `skill`
+    #[serde(default)]
+    per_level: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Debug)]
+pub enum TimeOfDay {
+    Day,
+    Night,
+}
+
+impl TimeOfDay {
+    pub fn of(clock: &WorldClock) -> TimeOfDay {
+        if clock.daylight() >= 0.5 {
+            TimeOfDay::Day
+        }
+        else {
+            TimeOfDay::Night
+        }
+    }
+}
+
+impl LootTable {
+    pub fn load() -> LootTable {
+        let mut table: LootTable =
+            serde_json::from_slice(include_bytes!("../../assets/metadata/fishing.json"))
+                .expect("Encountered a courrupted fishing loot table.");
+        let terrain: TileTypeConfig =
+            serde_json::from_slice(include_bytes!("../../assets/metadata/terrain.tjson"))
+                .expect("Encountered a courrupted terrain config.");
+        table.terrain = terrain.states.into_iter().map(|state| state.name).collect();
+        table
+    }
+    /// The name of a terrain state, if it's water anything can be caught in.
+    pub fn water(&self, state: usize) -> Option<&str> {
+        let name = self.terrain.get(state)?;
+        if self.catches.iter().any(|catch| &catch.water == name) {
+            Some(name.as_str())
+        }
+        else {
+            None
+        }
+    }
+    /// Picks what was caught, where `roll` is from 0 to 1. Returns `None` if
+    /// nothing can be caught.
+    pub fn roll(&self, water: &str, time: TimeOfDay, skill: usize, roll: f32) -> Option<Item> {
+        let weights: Vec<(Item, f32)> = self
+            .catches
+            .iter()
+            .filter(|catch| catch.water == water && skill >= catch.skill)
+            .filter(|catch| catch.time.map_or(true, |only| only == time))
+            .map(|catch| {
+                let bonus = catch.per_level * (skill - catch.skill) as f32;
+                (catch.item, (catch.weight + bonus).max(0.0))
+            })
+            .collect();
+        let total: f32 = weights.iter().map(|(_, weight)| weight).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut left = roll * total;
+        for (item, weight) in &weights {
+            if left < *weight {
+                return Some(*item);
+            }
+            left -= weight;
+        }
+        // Rounding can leave a sliver past the last catch
+        weights.iter().rev().find(|(_, weight)| *weight > 0.0).map(|(item, _)| *item)
+    }
+}
+
+/// A fishing line in the water.
+#[derive(Clone, Debug)]
+pub struct Cast {
+    world: usize,
+    target: Transform,
+    /// Name of the terrain state cast into
+    water: String,
+    /// Value of the world's clock when a fish bites
+    bite_at: f64,
+    /// Seconds after the bite the player has to reel in
+    window: f64,
+    /// Has the player been told about the bite?
+    bitten: bool,
+}
+
+impl Globals {
+    /// Casts a line into the water, replacing any line already out.
+    pub fn cast(&mut self, addr: SocketAddr, target: Transform) -> Vec<(Packet, SocketAddr)> {
+        let (world, index) = if let Some(found) = self.locate_player(addr) {
+            found
+        }
+        else {
+            return vec![];
+        };
+        let power = self.held(addr, world, index, |action| match action {
+            ItemAction::Fish(power) => Some(power),
+            _ => None,
+        });
+        let power = if let Some(power) = power {
+            power
+        }
+        else {
+            return vec![];
+        };
+        let (user, position, data) = &self.worlds[world].data.players[index];
+        if distance(*position, target) > FISH_REACH {
+            return vec![];
+        }
+        let (user, skill) = (user.clone(), data.stats.fishing);
+        let (chunk, tile) = tile_at(target);
+        let state = self.worlds[world].data.get_or_gen(chunk)[tile.0 + (tile.1 * CHUNK_WIDTH)];
+        let water = if let Some(water) = self.loot.water(state) {
+            water.to_string()
+        }
+        else {
+            return vec![];
+        };
+        let server = &self.worlds[world];
+        let (min, max) = FISH_BITE_TIME;
+        let wait = (min + (self.rng.gen::<f32>() * (max - min)))
+            / (server.weather.bite_multiplier() * power as f32);
+        let window = FISH_BITE_WINDOW + (FISH_WINDOW_PER_LEVEL * skill.saturating_sub(1) as f32);
+        let cast = Cast {
+            world,
+            target,
+            water,
+            bite_at: server.clock + wait as f64,
+            window: window as f64,
+            bitten: false,
+        };
+        self.casts.insert(user, cast);
+        vec![(Packet::Bobber(target), addr)]
+    }
+    /// Reels a line in. Catches something if a fish is biting, and nothing
+    /// otherwise.
+    pub fn reel(&mut self, addr: SocketAddr) -> Vec<(Packet, SocketAddr)> {
+        let cast = if let Some(cast) = self
+            .addr_to_user
+            .get(&addr)
+            .and_then(|user| self.casts.remove(user))
+        {
+            cast
+        }
+        else {
+            return vec![];
+        };
+        let server = &self.worlds[cast.world];
+        let hooked = cast.bitten && server.clock <= cast.bite_at + cast.window;
+        let located = self.locate_player(addr);
+        let (world, index) = match located {
+            Some(located) if hooked => located,
+            _ => return vec![(Packet::Caught(None), addr)],
+        };
+        let time = TimeOfDay::of(&server.world_clock());
+        let skill = self.worlds[world].data.players[index].2.stats.fishing;
+        let roll = self.rng.gen();
+        let caught = self.loot.roll(&cast.water, time, skill, roll);
+        let mut outgoing = vec![(Packet::Caught(caught), addr)];
+        if let Some(item) = caught {
+            outgoing.append(&mut self.give_items(world, index, &[(item, 1)]));
+        }
+        outgoing
+    }
+    /// Lets players know when a fish bites, and when one gets away. Lines are
+    /// also reeled in when their player walks away.
+    pub fn update_casts(&mut self) -> Vec<(Packet, SocketAddr)> {
+        let mut outgoing = vec![];
+        let mut ended = vec![];
+        for (user, cast) in &mut self.casts {
+            let addr = if let Some(addr) = self.user_to_addr.get(user) {
+                *addr
+            }
+            else {
+                ended.push(user.clone());
+                continue;
+            };
+            let server = &self.worlds[cast.world];
+            let in_reach = server
+                .data
+                .players
+                .iter()
+                .find(|(player, _, _)| player == user)
+                .map_or(false, |(_, position, _)| distance(*position, cast.target) <= FISH_REACH);
+            if !in_reach || server.clock > cast.bite_at + cast.window {
+                ended.push(user.clone());
+                outgoing.push((Packet::Caught(None), addr));
+            }
+            else if !cast.bitten && server.clock >= cast.bite_at {
+                cast.bitten = true;
+                outgoing.push((Packet::Bite, addr));
+            }
+        }
+        for user in ended {
+            self.casts.remove(&user);
+        }
+        outgoing
+    }
+}
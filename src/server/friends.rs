@@ -0,0 +1,162 @@
+//! Friends lists, friend requests and presence updates.
+
+use super::Profile;
+use crate::prelude::*;
+use crate::shared::friends::{FriendStatus, Presence};
+use std::net::SocketAddr;
+
+impl Globals {
+    fn profile(&self, user: &User) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| &profile.user == user)
+    }
+    fn profile_mut(&mut self, user: &User) -> Option<&mut Profile> {
+        self.profiles.iter_mut().find(|profile| &profile.user == user)
+    }
+    /// Returns what a user is currently doing.
+    pub fn presence_of(&self, user: &User) -> Presence {
+        if let Some(world) = self.user_to_world.get(user) {
+            let world = &self.worlds[*world];
+            Presence::InWorld(world.internal_id, world.public_name.clone())
+        }
+        else if self.user_to_addr.contains_key(user) {
+            Presence::TitleScreen
+        }
+        else {
+            Presence::Offline
+        }
+    }
+    /// Returns a friend's status as seen by `viewer`.
+    fn friend_status(&self, viewer: &User, friend: &User) -> FriendStatus {
+        let can_join = if let Some(world) = self.user_to_world.get(friend) {
+            self.worlds[*world].whitelist.contains(viewer)
+        }
+        else {
+            false
+        };
+        FriendStatus {
+            user: friend.clone(),
+            presence: self.presence_of(friend),
+            can_join,
+        }
+    }
+    /// Returns a user's friends list and friend requests, if they have a profile.
+    pub fn friend_list(&self, user: &User) -> Option<Packet> {
+        let profile = self.profile(user)?;
+        let friends = profile
+            .friends
+            .iter()
+            .map(|friend| self.friend_status(user, friend))
+            .collect();
+        Some(Packet::FriendList(friends, profile.friend_requests.clone()))
+    }
+    /// Tells every online friend of a user what they're doing now. Call this
+    /// whenever a user connects, disconnects, joins or leaves a world.
+    pub fn presence_updates(&self, user: &User) -> Vec<(Packet, SocketAddr)> {
+        let mut outgoing = vec![];
+        if let Some(profile) = self.profile(user) {
+            for friend in &profile.friends {
+                if let Some(addr) = self.user_to_addr.get(friend) {
+                    if !self.client_has(*addr, "friends") {
+                        continue;
+                    }
+                    outgoing.push((
+                        Packet::FriendPresence(self.friend_status(friend, user)),
+                        *addr,
+                    ));
+                }
+            }
+        }
+        outgoing
+    }
+    /// Sends each user their friends list, if they're online.
+    fn send_friend_lists(&self, users: &[&User]) -> Vec<(Packet, SocketAddr)> {
+        let mut outgoing = vec![];
+        for user in users {
+            if let (Some(addr), Some(list)) = (self.user_to_addr.get(user), self.friend_list(user)) {
+                if self.client_has(*addr, "friends") {
+                    outgoing.push((list, *addr));
+                }
+            }
+        }
+        outgoing
+    }
+    /// Asks `to` to become friends with `from`. If `to` has already asked
+    /// `from`, they become friends right away.
+    pub fn send_friend_request(&mut self, from: &User, to: &User) -> Vec<(Packet, SocketAddr)> {
+        let from_addr = if let Some(addr) = self.user_to_addr.get(from) {
+            *addr
+        }
+        else {
+            return vec![];
+        };
+        let failure = if from == to {
+            Some(String::from("You can't be friends with yourself."))
+        }
+        else if let Some(profile) = self.profile(to) {
+            if profile.friends.contains(from) {
+                Some(format!("You're already friends with {to}."))
+            }
+            else if profile.friend_requests.contains(from) {
+                Some(format!("You've already sent {to} a friend request."))
+            }
+            else {
+                None
+            }
+        }
+        else {
+            Some(format!("{to} isn't a real user."))
+        };
+        if let Some(reason) = failure {
+            return vec![(Packet::FriendRequestFailed(reason), from_addr)];
+        }
+        let requested_back = self
+            .profile(from)
+            .map(|profile| profile.friend_requests.contains(to))
+            .unwrap_or(false);
+        if requested_back {
+            return self.respond_friend_request(from, to, true);
+        }
+        if let Some(profile) = self.profile_mut(to) {
+            profile.friend_requests.push(from.clone());
+        }
+        self.send_friend_lists(&[to])
+    }
+    /// Accepts or declines the friend request `from` sent to `user`.
+    pub fn respond_friend_request(
+        &mut self,
+        user: &User,
+        from: &User,
+        accepted: bool,
+    ) -> Vec<(Packet, SocketAddr)> {
+        let had_request = if let Some(profile) = self.profile_mut(user) {
+            let before = profile.friend_requests.len();
+            profile.friend_requests.retain(|request| request != from);
+            profile.friend_requests.len() != before
+        }
+        else {
+            false
+        };
+        if !had_request {
+            return self.send_friend_lists(&[user]);
+        }
+        if accepted {
+            for (this, other) in [(user, from), (from, user)] {
+                if let Some(profile) = self.profile_mut(this) {
+                    if !profile.friends.contains(other) {
+                        profile.friends.push(other.clone());
+                    }
+                }
+            }
+        }
+        self.send_friend_lists(&[user, from])
+    }
+    /// Removes two users from each other's friends lists.
+    pub fn remove_friend(&mut self, user: &User, friend: &User) -> Vec<(Packet, SocketAddr)> {
+        for (this, other) in [(user, friend), (friend, user)] {
+            if let Some(profile) = self.profile_mut(this) {
+                profile.friends.retain(|existing| existing != other);
+            }
+        }
+        self.send_friend_lists(&[user, friend])
+    }
+}
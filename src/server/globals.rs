@@ -1,4 +1,5 @@
 use super::*;
+use rand::{rngs::StdRng, SeedableRng};
 
 #[derive(Clone)]
 pub struct Globals {
@@ -8,11 +9,68 @@ pub struct Globals {
     pub addr_to_user: HashMap<SocketAddr, User>,
     pub user_to_world: HashMap<User, usize>,
     pub last_autosave: std::time::Instant,
+    pub config: Config,
+    /// Packets produced outside of `handler` and `tick` (such as by the admin
+    /// socket) that are waiting to be sent on the next tick.
+    pub queued_packets: Vec<(Packet, SocketAddr)>,
+    pub metrics: Metrics,
+    pub rate_limiter: RateLimiter,
+    pub parties: Parties,
+    pub word_filter: WordFilter,
+    /// The protocol version each connected client announced
+    pub client_versions: HashMap<SocketAddr, ProtocolVersion>,
+    /// Connections that sent something unreadable or closed. Netty can't close
+    /// them for us, so anything else they send is ignored until a new
+    /// connection from the same address announces its version.
+    pub dropped_addrs: HashSet<SocketAddr>,
+    /// When each connection last sent a packet
+    pub last_heard: HashMap<SocketAddr, std::time::Instant>,
+    /// Records packets when `capture_file` is set in the config
+    pub recorder: Option<Arc<Mutex<Recorder>>>,
+    /// Where worlds, profiles and settings are stored
+    pub data_dir: PathBuf,
+    /// When `tick` was last called
+    pub last_tick: std::time::Instant,
+    /// Time that has passed but is less than a full simulation step
+    pub unsimulated: std::time::Duration,
+    /// All the time passed to `advance`, which captures are timestamped with
+    pub uptime: std::time::Duration,
+    /// What `rng` was seeded with, recorded in captures
+    pub seed: u64,
+    /// Every random roll the server makes, so a replay with the same seed
+    /// rolls the same things
+    pub rng: StdRng,
+    /// Fishing lines in the water, by who cast them
+    pub casts: HashMap<User, fishing::Cast>,
+    pub loot: fishing::LootTable,
+    pub recipe_book: RecipeBook,
 }
 
 impl Default for Globals {
+    /// Loads the server from the current directory.
     fn default() -> Self {
-        let saves = get_disk_savegames().expect("Savegames were unable to be loaded.");
+        let data_dir = std::env::current_dir().expect("Unable to access the current directory.");
+        Globals::load(data_dir)
+    }
+}
+
+impl Globals {
+    /// Loads the worlds, profiles and settings of a server stored in `data_dir`.
+    pub fn load(data_dir: PathBuf) -> Globals {
+        let saves = match get_disk_savegames(&data_dir) {
+            Ok(saves) => saves,
+            Err(e) => {
+                error!("Savegames were unable to be loaded: {e:#}");
+                panic!("{FATAL_ERROR}");
+            }
+        };
+        let profiles = match profiles(&data_dir) {
+            Ok(profiles) => profiles,
+            Err(e) => {
+                error!("Profiles were unable to be loaded: {e:#}");
+                panic!("{FATAL_ERROR}");
+            }
+        };
         let mut sorted = vec![];
         for i in 0..saves.len() {
             for save in saves.clone() {
@@ -21,13 +79,282 @@ impl Default for Globals {
                 }
             }
         }
+        let config = Config::load(&data_dir);
+        let seed = rand::random();
+        let recorder = config.capture_file.as_ref().and_then(|path| {
+            match Recorder::create(std::path::Path::new(path), Side::Server, Some(seed)) {
+                Ok(recorder) => Some(Arc::new(Mutex::new(recorder))),
+                Err(e) => {
+                    warn!("Unable to capture packets to {path}: {e}");
+                    None
+                }
+            }
+        });
         Self {
             worlds: sorted,
-            profiles: profiles(),
+            profiles,
             user_to_addr: default(),
             addr_to_user: default(),
             user_to_world: default(),
             last_autosave: std::time::Instant::now(),
+            config,
+            queued_packets: vec![],
+            metrics: default(),
+            rate_limiter: default(),
+            parties: default(),
+            word_filter: WordFilter::load(&data_dir),
+            client_versions: default(),
+            dropped_addrs: default(),
+            last_heard: default(),
+            recorder,
+            data_dir,
+            last_tick: std::time::Instant::now(),
+            unsimulated: std::time::Duration::ZERO,
+            uptime: std::time::Duration::ZERO,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            casts: default(),
+            loot: fishing::LootTable::load(),
+            recipe_book: RecipeBook::load(),
+        }
+    }
+    /// Records packets to the capture file, if capturing.
+    pub fn capture(&self, direction: Direction, packets: &[(Packet, SocketAddr)]) {
+        if let Some(recorder) = &self.recorder {
+            let mut recorder = recorder.lock().unwrap();
+            for (packet, peer) in packets {
+                recorder.record_at(self.uptime, direction, *peer, packet);
+            }
+        }
+    }
+    /// Starts the server's random rolls over from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+    /// Makes a profile for `username` with the next free tag. Returns `None` if
+    /// every tag for that name is taken.
+    pub fn create_profile(&mut self, username: String) -> Option<User> {
+        let tag = self
+            .profiles
+            .iter()
+            .filter(|profile| profile.user.username == username)
+            .map(|profile| profile.user.tag)
+            .max()
+            .unwrap_or(0);
+        let user = User {
+            username,
+            tag: tag.checked_add(1)?,
+        };
+        self.reserve_profile(user.clone()).then_some(user)
+    }
+    /// Makes a profile for a user whose tag was picked elsewhere, such as on
+    /// another server. Returns false if the tag is taken or out of range.
+    pub fn reserve_profile(&mut self, user: User) -> bool {
+        if user.tag == 0
+            || user.tag > MAX_USER_TAG
+            || self.profiles.iter().any(|profile| profile.user == user)
+        {
+            return false;
+        }
+        self.profiles.push(Profile {
+            user,
+            avalable_games: vec![],
+            friends: vec![],
+            friend_requests: vec![],
+        });
+        true
+    }
+    /// The user connected from `addr`. Warns and returns `None` if they sent
+    /// `packet` before announcing who they are.
+    pub fn sender(&self, addr: SocketAddr, packet: &str) -> Option<User> {
+        let user = self.addr_to_user.get(&addr).cloned();
+        if user.is_none() {
+            warn!("{addr} sent {packet} before announcing who they are");
+        }
+        user
+    }
+    /// The user connected from `addr` and the world they're in. Warns and
+    /// returns `None` if they sent `packet` outside of a world.
+    pub fn sender_in_world(&self, addr: SocketAddr, packet: &str) -> Option<(User, usize)> {
+        let user = self.sender(addr, packet)?;
+        if let Some(world) = self.user_to_world.get(&user) {
+            Some((user, *world))
+        }
+        else {
+            warn!("{user} sent {packet} outside of a world");
+            None
+        }
+    }
+    /// Does the client at `addr` support an optional protocol feature?
+    pub fn client_has(&self, addr: SocketAddr, capability: &str) -> bool {
+        self.client_versions
+            .get(&addr)
+            .map(|version| version.has(capability))
+            .unwrap_or(false)
+    }
+    /// Sends a packet to every player in a world whose client supports
+    /// `capability`.
+    pub fn capable_world_packets(
+        &self,
+        world: usize,
+        capability: &str,
+        packet: Packet,
+    ) -> Vec<(Packet, SocketAddr)> {
+        self.world_addrs(world)
+            .into_iter()
+            .filter(|addr| self.client_has(*addr, capability))
+            .map(|addr| (packet.clone(), addr))
+            .collect()
+    }
+    /// Sends a world's clock to every player in it who understands it.
+    pub fn clock_packets(&self, world: usize) -> Vec<(Packet, SocketAddr)> {
+        let clock = self.worlds[world].world_clock();
+        self.capable_world_packets(world, "world_clock", Packet::WorldClock(clock))
+    }
+    /// Returns the addresses of every player in a world.
+    pub fn world_addrs(&self, world: usize) -> Vec<SocketAddr> {
+        let mut addrs = vec![];
+        for (user, _, _) in &self.worlds[world].data.players {
+            if let Some(addr) = self.user_to_addr.get(user) {
+                addrs.push(*addr);
+            }
+        }
+        addrs
+    }
+    /// Tells everyone in a world that an object changed.
+    pub fn object_updated(&self, world: usize, object: &Object) -> Vec<(Packet, SocketAddr)> {
+        self.world_addrs(world)
+            .into_iter()
+            .map(|addr| (Packet::UpdateObject(object.clone()), addr))
+            .collect()
+    }
+    /// Drops items on the ground around a position, such as when something is
+    /// destroyed.
+    pub fn scatter_items(
+        &mut self,
+        world: usize,
+        position: Transform,
+        items: &[Item],
+    ) -> Vec<(Packet, SocketAddr)> {
+        let mut outgoing = vec![];
+        for item in items {
+            let x_offset = random(&mut self.rng, 0, 64) as f32;
+            let y_offset = random(&mut self.rng, 0, 64) as f32;
+            let object = Object {
+                pos: Transform::from_xyz(
+                    position.translation.x + x_offset - 32.0,
+                    position.translation.y + y_offset - 32.0,
+                    0.0,
+                ),
+                rep: ObjectType::GroundItem(ItemStack::one(*item)),
+                uuid: random_uuid(&mut self.rng),
+            };
+            for addr in self.world_addrs(world) {
+                outgoing.push((Packet::CreateObject(object.clone()), addr));
+            }
+            self.worlds[world].data.objects.push(object);
+        }
+        outgoing
+    }
+    /// Lets a user join a world. Returns false if the user has no profile.
+    pub fn whitelist_user(&mut self, world: usize, user: &User) -> bool {
+        for profile in &mut self.profiles {
+            if &profile.user == user {
+                if !profile.avalable_games.contains(&world) {
+                    profile.avalable_games.push(world);
+                }
+                if !self.worlds[world].whitelist.contains(user) {
+                    self.worlds[world].whitelist.push(user.clone());
+                }
+                return true;
+            }
+        }
+        false
+    }
+    /// Writes every world and profile to the disk.
+    pub fn save_all(&mut self) {
+        self.last_autosave = std::time::Instant::now();
+        info!("Saving worlds and profiles");
+        for world in self.worlds.clone() {
+            save_world(world);
+        }
+        for profile in self.profiles.clone() {
+            save_profile(profile, &self.data_dir);
+        }
+        info!("Done saving");
+    }
+    /// Removes a user from the world they are in, moving their data to the
+    /// world's offline players and notifying everyone else in it. Returns the
+    /// packets that need to be sent, or nothing if the user isn't in a world.
+    pub fn remove_from_world(&mut self, user: &User) -> Vec<(Packet, SocketAddr)> {
+        let mut outgoing = vec![];
+        let server = if let Some(server) = self.user_to_world.remove(user) {
+            server
+        }
+        else {
+            return outgoing;
+        };
+        self.casts.remove(user);
+        let mut self_index = None;
+        for (index, player) in self.worlds[server].data.players.iter().enumerate() {
+            if &player.0 == user {
+                self_index = Some(index);
+                continue;
+            }
+            if let Some(this_ip) = self.user_to_addr.get(&player.0) {
+                outgoing.push((Packet::PlayerDisconnected(user.clone()), *this_ip));
+            }
+        }
+        if let Some(index) = self_index {
+            let p = self.worlds[server].data.players.swap_remove(index);
+            self.worlds[server].data.offline_players.push(p);
+        }
+        else {
+            warn!("A user was marked as in a world they had no datablock in");
+        }
+        outgoing.append(&mut self.presence_updates(user));
+        outgoing
+    }
+    /// Disconnects clients that promised keepalives but haven't sent anything
+    /// for `CONNECTION_TIMEOUT`. Netty doesn't always report a closed
+    /// connection, so this is how players who vanish stop being shown online.
+    pub fn disconnect_timed_out(&mut self) -> Vec<(Packet, SocketAddr)> {
+        let timed_out: Vec<SocketAddr> = self
+            .last_heard
+            .iter()
+            .filter(|(addr, heard)| {
+                heard.elapsed() > CONNECTION_TIMEOUT && self.client_has(**addr, "keepalive")
+            })
+            .map(|(addr, _)| *addr)
+            .collect();
+        let mut outgoing = vec![];
+        for addr in timed_out {
+            info!("{addr} timed out");
+            outgoing.append(&mut self.disconnect(addr));
+        }
+        outgoing
+    }
+    /// Forgets a connection entirely, removing its user from their world and
+    /// telling their friends they went offline.
+    pub fn disconnect(&mut self, addr: SocketAddr) -> Vec<(Packet, SocketAddr)> {
+        self.dropped_addrs.remove(&addr);
+        self.rate_limiter.forget(addr);
+        self.last_heard.remove(&addr);
+        self.client_versions.remove(&addr);
+        let user = if let Some(user) = self.addr_to_user.remove(&addr) {
+            user
+        }
+        else {
+            return vec![];
+        };
+        let mut outgoing = self.remove_from_world(&user);
+        if self.user_to_addr.get(&user) == Some(&addr) {
+            self.user_to_addr.remove(&user);
         }
+        // Anything queued for the closed connection can't be delivered
+        outgoing.retain(|(_, to)| to != &addr);
+        outgoing.append(&mut self.presence_updates(&user));
+        outgoing
     }
 }
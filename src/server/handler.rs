@@ -1,60 +1,149 @@
-use crate::{prelude::*, shared::listing::GameListing};
+use crate::{
+    prelude::*,
+    resources::ChatMessage,
+    shared::{listing::GameListing, version::ProtocolVersion},
+};
 
-use super::{tick::save_folder, world, Profile, SaveGame};
+use super::{
+    chat, commands,
+    moderation::unix_now,
+    rate_limit::{PacketClass, Verdict},
+    tick::save_folder,
+    Direction, SaveGame,
+};
 
 pub fn handler(
     packet: Packet,
     globals: std::sync::Arc<std::sync::Mutex<Globals>>,
     source_addr: std::net::SocketAddr,
+) -> Vec<(Packet, std::net::SocketAddr)> {
+    let started = std::time::Instant::now();
+    let variant = packet.variant_name();
+    let mut access = globals.lock().unwrap();
+    if access.dropped_addrs.contains(&source_addr) {
+        // Every connection starts by announcing its version, so this is a new
+        // one from the same address rather than more of the dropped one
+        if !matches!(packet, Packet::NettyVersion(..)) {
+            return vec![];
+        }
+        access.dropped_addrs.remove(&source_addr);
+    }
+    access.last_heard.insert(source_addr, std::time::Instant::now());
+    access.capture(Direction::Recieved, &[(packet.clone(), source_addr)]);
+    drop(access);
+    if let Some(outgoing) = rate_limit(&packet, &globals, source_addr) {
+        return outgoing;
+    }
+    let outgoing = handle(packet, globals.clone(), source_addr);
+    let mut globals = globals.lock().unwrap();
+    globals.metrics.record_handled(variant, started.elapsed());
+    globals.metrics.record_outgoing(&outgoing);
+    globals.capture(Direction::Sent, &outgoing);
+    drop(globals);
+    outgoing
+}
+
+/// Checks a packet against the sender's rate limits. Returns `None` if the
+/// packet should be handled, or the packets to send instead if it shouldn't.
+fn rate_limit(
+    packet: &Packet,
+    globals: &std::sync::Arc<std::sync::Mutex<Globals>>,
+    source_addr: std::net::SocketAddr,
+) -> Option<Vec<(Packet, std::net::SocketAddr)>> {
+    let mut globals = globals.lock().unwrap();
+    let limits = globals.config.rate_limits;
+    let verdict = globals
+        .rate_limiter
+        .check(source_addr, PacketClass::of(packet), &limits);
+    match verdict {
+        Verdict::Allow => None,
+        Verdict::Throttle => Some(vec![]),
+        Verdict::Warn => {
+            warn!("Throttling packets from {source_addr}");
+            Some(vec![(
+                Packet::ChatMessage(ChatMessage::system(
+                    "You're sending too many requests. Slow down!",
+                    Color::RED,
+                )),
+                source_addr,
+            )])
+        }
+        Verdict::Disconnect => {
+            warn!("Disconnecting {source_addr} for flooding the server");
+            let mut outgoing = vec![];
+            if let Some(user) = globals.addr_to_user.get(&source_addr).cloned() {
+                outgoing.append(&mut globals.remove_from_world(&user));
+            }
+            outgoing.push((
+                Packet::Kicked(String::from("Sending too many requests")),
+                source_addr,
+            ));
+            Some(outgoing)
+        }
+    }
+}
+
+fn handle(
+    packet: Packet,
+    globals: std::sync::Arc<std::sync::Mutex<Globals>>,
+    source_addr: std::net::SocketAddr,
 ) -> Vec<(Packet, std::net::SocketAddr)> {
     let mut outgoing = vec![];
     match packet {
-        Packet::NettyVersion(v) => {
-            if v == NETTY_VERSION {
-                outgoing.push((Packet::AllSet, source_addr));
+        Packet::NettyVersion(version) => {
+            let current = ProtocolVersion::current();
+            if current.compatible_with(&version) {
+                info!("{source_addr} connected with protocol version {version}");
+                globals
+                    .lock()
+                    .unwrap()
+                    .client_versions
+                    .insert(source_addr, version);
+                outgoing.push((Packet::AllSet(current), source_addr));
             }
             else {
-                outgoing.push((
-                    Packet::WrongVersion(String::from(NETTY_VERSION)),
-                    source_addr,
-                ));
+                info!("{source_addr} tried to connect with protocol version {version}");
+                outgoing.push((Packet::WrongVersion(current), source_addr));
             }
         }
         Packet::CreateUser(user) => {
             let mut globals = globals.lock().unwrap();
-            let mut tag = 0;
-            for profile in globals.profiles.clone() {
-                if profile.user.username == user.username && profile.user.tag > tag {
-                    tag = profile.user.tag;
-                }
-            }
-            let new_user = User {
-                username: user.username,
-                tag: tag + 1,
-            };
-            if new_user.tag > 9999 {
-                outgoing.push((Packet::OverusedName, source_addr));
-            }
-            else {
-                let new_profile = Profile {
-                    user: new_user.clone(),
-                    avalable_games: vec![],
-                };
-                globals.profiles.push(new_profile.clone());
+            if let Some(new_user) = globals.create_profile(user.username) {
                 globals.user_to_addr.insert(new_user.clone(), source_addr);
                 globals.addr_to_user.insert(source_addr, new_user.clone());
                 drop(globals);
                 outgoing.push((Packet::CreatedUser(new_user), source_addr));
             }
+            else {
+                outgoing.push((Packet::OverusedName, source_addr));
+            }
         }
         Packet::UserPresence(user) => {
+            outgoing.push((Packet::AllSet(ProtocolVersion::current()), source_addr));
             if user.tag > 0 {
                 let mut globals = globals.lock().unwrap();
+                if globals.config.lan_profiles
+                    && !globals.profiles.iter().any(|profile| profile.user == user)
+                {
+                    // Users created on another server (such as one found on
+                    // the local network) keep their name and tag here.
+                    if globals.reserve_profile(user.clone()) {
+                        info!("Creating a profile for {user}, who was made on another server");
+                    }
+                    else {
+                        warn!("Unable to create a profile for {user}");
+                    }
+                }
                 globals.user_to_addr.insert(user.clone(), source_addr);
-                globals.addr_to_user.insert(source_addr, user);
+                globals.addr_to_user.insert(source_addr, user.clone());
+                if let (Some(list), true) =
+                    (globals.friend_list(&user), globals.client_has(source_addr, "friends"))
+                {
+                    outgoing.push((list, source_addr));
+                }
+                outgoing.append(&mut globals.presence_updates(&user));
                 drop(globals);
             }
-            outgoing.push((Packet::AllSet, source_addr));
         }
         Packet::CreateWorld(name) => {
             let mut globals = globals.lock().unwrap();
@@ -62,7 +151,7 @@ pub fn handler(
             if let Some(last) = globals.worlds.last() {
                 world_id = last.internal_id + 1;
             }
-            let mut path = save_folder();
+            let mut path = save_folder(&globals.data_dir);
 
             // This replaces invalid characters (ones that would break file paths) with "I".
             // On windows these are \ / : * ? " < > |
@@ -89,47 +178,58 @@ pub fn handler(
             }
 
             path.push(format!("{}_{}.bic", rname, world_id));
-            let owner = globals
-                .addr_to_user
-                .get(&source_addr)
-                .expect("No user found for an IP adress used with Packet::CreateWorld(String)")
-                .clone();
+            let owner = if let Some(owner) = globals.sender(source_addr, "CreateWorld") {
+                owner
+            }
+            else {
+                return outgoing;
+            };
             for (index, profile) in globals.profiles.clone().into_iter().enumerate() {
                 if owner == profile.user {
                     globals.profiles[index].avalable_games.push(world_id);
                 }
             }
-            let owner = owner.clone();
-            globals.worlds.push(SaveGame {
-                public_name: name,
-                internal_id: world_id,
-                data: world::World::new(),
-                path,
-                whitelist: vec![owner.clone()],
-                played_before: vec![],
-                owner,
-            });
-            outgoing.push((
-                Packet::CreatedWorld(globals.worlds.last().unwrap().internal_id),
-                source_addr,
-            ));
+            let world = SaveGame::new(name, world_id, path, owner, &mut globals.rng);
+            globals.worlds.push(world);
+            outgoing.push((Packet::CreatedWorld(world_id), source_addr));
             drop(globals);
         }
         Packet::JoinWorld(world_id) => {
             let mut globals = globals.lock().unwrap();
-            let packet_user = globals
-                .addr_to_user
-                .get(&source_addr)
-                .expect("A user attempted to join a world before first announcing identity.")
-                .clone();
+            let packet_user = if let Some(user) = globals.sender(source_addr, "JoinWorld") {
+                user
+            }
+            else {
+                return outgoing;
+            };
 
-            let mut world_index = 0;
-            for (index, world) in globals.worlds.iter().enumerate() {
-                if world.internal_id == world_id {
-                    world_index = index;
-                    break;
+            let world_index = globals
+                .worlds
+                .iter()
+                .position(|world| world.internal_id == world_id);
+            let world_index = if let Some(index) = world_index {
+                index
+            }
+            else {
+                warn!("{packet_user} tried to join world {world_id}, which doesn't exist");
+                return outgoing;
+            };
+            let world = &globals.worlds[world_index];
+            if world.data.players.len() >= world.max_players
+                && !world.data.players.iter().any(|player| player.0 == packet_user)
+            {
+                let reason = String::from("This world is full");
+                let packet = if globals.client_has(source_addr, "join_refused") {
+                    Packet::JoinRefused(reason)
                 }
+                else {
+                    // Older clients only know how to leave a world from here
+                    Packet::Kicked(reason)
+                };
+                outgoing.push((packet, source_addr));
+                return outgoing;
             }
+            globals.worlds[world_index].last_played = unix_now();
             let mut player_info = None;
             for (index, player) in globals.worlds[world_index]
                 .data
@@ -157,14 +257,10 @@ pub fn handler(
             }
             let player_info = player_info.unwrap();
             let mut other_players = vec![];
-            for (user, _, _) in globals.worlds[world_id].data.players.clone() {
-                let ip = globals
-                    .user_to_addr
-                    .get(&user)
-                    .expect("A user online on a server had no IP address");
+            for ip in globals.world_addrs(world_index) {
                 other_players.push((
                     Packet::PlayerConnected(packet_user.clone(), player_info.1),
-                    *ip,
+                    ip,
                 ));
             }
             if !globals.worlds[world_index]
@@ -184,46 +280,63 @@ pub fn handler(
             globals
                 .user_to_world
                 .insert(packet_user.clone(), world_index);
+            outgoing.append(&mut globals.presence_updates(&packet_user));
             let spawn_centre_chnks_lack = (
                 (player_info.1.translation.x / 32.0).round() as isize,
                 (player_info.1.translation.y / 32.0).round() as isize,
             );
             let mut constructable_players = vec![];
-            for (us, gp, _) in &globals.worlds[world_id].data.players {
+            for (us, gp, _) in &globals.worlds[world_index].data.players {
                 constructable_players.push((us.clone(), *gp));
             }
             let mut new_objs = vec![];
+            let access = &mut *globals;
             run_matrix_nxn(-2..2, |x, y| {
-                new_objs.append(
-                    &mut globals.worlds[world_index].data.try_generating_objects((
-                        spawn_centre_chnks_lack.0 + x,
-                        spawn_centre_chnks_lack.1 + y,
-                    )),
-                );
+                new_objs.append(&mut access.worlds[world_index].data.try_generating_objects(
+                    (spawn_centre_chnks_lack.0 + x, spawn_centre_chnks_lack.1 + y),
+                    &mut access.rng,
+                ));
             });
             let mut all_players = vec![];
             for object in new_objs {
-                for (user, _, _) in globals.worlds[world_id].data.players.clone() {
-                    let ip = globals
-                        .user_to_addr
-                        .get(&user)
-                        .expect("A user online on a server had no IP address");
+                for ip in globals.world_addrs(world_index) {
                     // if this isn't the player joining...
-                    if ip != &source_addr {
+                    if ip != source_addr {
                         // send over the objects
-                        all_players.push((Packet::CreateObject(object.clone()), *ip));
+                        all_players.push((Packet::CreateObject(object.clone()), ip));
                     }
                 }
             }
 
             outgoing.push((
-                Packet::JoinedGame(player_info.1, globals.worlds[world_id].owner == owner),
+                Packet::JoinedGame(player_info.1, globals.worlds[world_index].owner == owner),
                 source_addr,
             ));
             outgoing.push((
                 Packet::AllObjects(globals.worlds[world_index].data.objects.clone()),
                 source_addr,
             ));
+            if globals.client_has(source_addr, "chat_history") {
+                outgoing.push((
+                    Packet::ChatHistory(
+                        globals.worlds[world_index].data.recent_chat(CHAT_HISTORY_ON_JOIN),
+                    ),
+                    source_addr,
+                ));
+            }
+            if globals.client_has(source_addr, "world_clock") {
+                outgoing.push((
+                    Packet::WorldClock(globals.worlds[world_index].world_clock()),
+                    source_addr,
+                ));
+            }
+            if globals.client_has(source_addr, "weather") {
+                outgoing.push((Packet::Weather(globals.worlds[world_index].weather), source_addr));
+            }
+            if let Some((world, index)) = globals.locate_player(source_addr) {
+                globals.check_unlocks(world, index);
+                outgoing.append(&mut globals.recipe_packets(world, index));
+            }
             drop(globals);
             outgoing.push((Packet::InventoryState(player_info.2.inventory), source_addr));
             outgoing.push((Packet::OnlinePlayers(constructable_players), source_addr));
@@ -232,14 +345,14 @@ pub fn handler(
         }
         Packet::RequestChunk(chunk) => {
             let mut globals = globals.lock().unwrap();
-            let owner = globals
-                .addr_to_user
-                .get(&source_addr)
-                .expect("No user found for an IP adress used with Packet::RequestChunk");
-            let server = *globals
-                .user_to_world
-                .get(owner)
-                .expect("Owner is not in a server for Packet::RequestChunk");
+            let server = if let Some((_, server)) =
+                globals.sender_in_world(source_addr, "RequestChunk")
+            {
+                server
+            }
+            else {
+                return outgoing;
+            };
 
             let chunk_data = globals.worlds[server].data.get_or_gen(chunk);
             drop(globals);
@@ -248,65 +361,91 @@ pub fn handler(
         }
         Packet::RequestMove(pos) => {
             let mut globals = globals.lock().unwrap();
-            let owner = globals
-                .addr_to_user
-                .get(&source_addr)
-                .expect(
-                    "No user found for an IP adress used with Packet::RequestMove(GamePosition)",
-                )
-                .clone();
-
-            let server = *globals
-                .user_to_world
-                .get(&owner)
-                .expect("Owner is not in a server for Packet::RequestMove(GamePosition)");
+            let (owner, server) = if let Some(found) =
+                globals.sender_in_world(source_addr, "RequestMove")
+            {
+                found
+            }
+            else {
+                return outgoing;
+            };
 
             let mut self_index = None;
 
             for (index, player) in globals.worlds[server].data.players.iter().enumerate() {
-                let this_ip = globals
-                    .user_to_addr
-                    .get(&player.0)
-                    .expect("Online player has no IP for a requested move");
-                // send data
-                if this_ip == &source_addr {
-                    // but not to the mover
+                // send data, but not to the mover
+                if player.0 == owner {
                     self_index = Some(index);
                     continue;
                 }
-                outgoing.push((Packet::PlayerPositionUpdate(owner.clone(), pos), *this_ip));
+                if let Some(this_ip) = globals.user_to_addr.get(&player.0) {
+                    outgoing.push((Packet::PlayerPositionUpdate(owner.clone(), pos), *this_ip));
+                }
             }
             // save data to server
-            globals.worlds[server].data.players[self_index.expect("Owner does not have a datablock in a server.")].1 = pos;
+            if let Some(index) = self_index {
+                globals.worlds[server].data.players[index].1 = pos;
+            }
+            else {
+                warn!("{owner} moved in a world they have no datablock in");
+            }
             drop(globals);
         }
         Packet::AvalableServers => {
             let globals = globals.lock().unwrap();
-            let owner = globals
-                .addr_to_user
-                .get(&source_addr)
-                .expect("No user found for an IP address used with Packet::AvalableServers");
+            let owner = if let Some(owner) = globals.sender(source_addr, "AvalableServers") {
+                owner
+            }
+            else {
+                return outgoing;
+            };
 
             // find assoc user
             let mut profile = None;
             for tprofile in &globals.profiles {
-                if &tprofile.user == owner {
+                if tprofile.user == owner {
                     profile = Some(tprofile.clone());
                 }
             }
-            let profile = profile.expect("No profile found for Packet::AvalableServers");
+            let profile = if let Some(profile) = profile {
+                profile
+            }
+            else {
+                // Users from another server only get a profile with lan_profiles
+                warn!("{owner} asked for their worlds, but has no profile");
+                outgoing.push((Packet::ServerList(vec![]), source_addr));
+                return outgoing;
+            };
             // get servers
             let mut listings = vec![];
+            let now = unix_now();
             for server_id in profile.avalable_games {
-                let this_server = &globals.worlds[server_id];
+                let this_server = if let Some(server) = globals.worlds.get(server_id) {
+                    server
+                }
+                else {
+                    warn!("{owner} has world {server_id} listed, which doesn't exist");
+                    continue;
+                };
+                let last_played = if this_server.last_played == 0 {
+                    None
+                }
+                else {
+                    Some(now.saturating_sub(this_server.last_played))
+                };
                 listings.push(GameListing {
                     public_name: this_server.public_name.clone(),
-                    description: String::from("TODO"),
+                    description: this_server.description.clone(),
                     internal_id: server_id,
                     local: false,
-                    address: String::from("NA/TODO"),
+                    address: String::new(),
                     password: false,
-                    played: this_server.played_before.contains(owner),
+                    played: this_server.played_before.contains(&owner),
+                    owner: this_server.owner.clone(),
+                    online: this_server.data.players.len(),
+                    max_players: this_server.max_players,
+                    last_played,
+                    age: now.saturating_sub(this_server.created_at),
                 })
             }
             drop(globals);
@@ -315,24 +454,16 @@ pub fn handler(
         }
         Packet::WhitelistUser(user) => {
             let mut globals = globals.lock().unwrap();
-            let owner = globals
-                .addr_to_user
-                .get(&source_addr)
-                .expect("No user found for an IP adress used with Packet::WhitelistUser");
-
-            let server = *globals
-                .user_to_world
-                .get(owner)
-                .expect("User is not in a server for Packet::WhitelistUser");
-            if &globals.worlds[server].owner == owner {
-                let mut loc = None;
-                for (ind, prof) in globals.profiles.iter().enumerate() {
-                    if prof.user == user {
-                        loc = Some(ind);
-                    }
-                }
-                if let Some(indexable) = loc {
-                    globals.profiles[indexable].avalable_games.push(server);
+            let (owner, server) = if let Some(found) =
+                globals.sender_in_world(source_addr, "WhitelistUser")
+            {
+                found
+            }
+            else {
+                return outgoing;
+            };
+            if globals.worlds[server].owner == owner {
+                if globals.whitelist_user(server, &user) {
                     outgoing.push((Packet::Whitelisted, source_addr));
                 }
                 else {
@@ -346,84 +477,85 @@ pub fn handler(
         }
         Packet::LeaveWorld => {
             let mut globals = globals.lock().unwrap();
-            let owner = globals
-                .addr_to_user
-                .get(&source_addr)
-                .expect("No user found for an IP adress used with Packet::LeaveWorld");
-
-            let server = *globals
-                .user_to_world
-                .get(owner)
-                .expect("Owner is not in a server for Packet::LeaveWorld");
-
-            let mut self_index = None;
+            let owner = if let Some(owner) = globals.sender(source_addr, "LeaveWorld") {
+                owner
+            }
+            else {
+                return outgoing;
+            };
 
-            for (index, player) in globals.worlds[server].data.players.iter().enumerate() {
-                let this_ip = globals
-                    .user_to_addr
-                    .get(&player.0)
-                    .expect("Online player has no IP for a requested disconnect");
-                // send data
-                if this_ip == &source_addr {
-                    // but not to the disconnector
-                    self_index = Some(index);
-                    continue;
-                }
-                outgoing.push((Packet::PlayerDisconnected(owner.clone()), *this_ip));
+            if !globals.user_to_world.contains_key(&owner) {
+                // This happens when a player was kicked and their client
+                // disconnects afterwards.
+                warn!("A user left a world they were not in");
             }
-            // save disconnect to server
-            let p = globals.worlds[server]
-                .data
-                .players
-                .swap_remove(self_index.expect("Owner does not have a datablock in a server."));
-            globals.worlds[server].data.offline_players.push(p);
+            outgoing.append(&mut globals.remove_from_world(&owner));
             drop(globals);
         }
-        Packet::SendChatMessage(msg) => {
-            let globals = globals.lock().unwrap();
+        Packet::SendChatMessage(mut msg) => {
+            let mut globals = globals.lock().unwrap();
             // find assoc user
-            let owner = globals.addr_to_user.get(&source_addr).expect(
-                "No user found for an IP adress used with Packet::RequestMove(GamePosition)",
-            );
+            let (owner, server) = if let Some(found) =
+                globals.sender_in_world(source_addr, "SendChatMessage")
+            {
+                found
+            }
+            else {
+                return outgoing;
+            };
 
-            let server = *globals
-                .user_to_world
-                .get(owner)
-                .expect("Owner is not in a server for Packet::RequestMove(GamePosition)");
+            if msg.text.starts_with('/') {
+                outgoing.append(&mut commands::run(
+                    &mut globals,
+                    owner,
+                    source_addr,
+                    server,
+                    &msg.text,
+                ));
+                return outgoing;
+            }
 
-            let mut sendable_message = msg.clone();
-            sendable_message
-                .text
-                .insert_str(0, &format!("[{}] ", owner.username));
-            for player in &globals.worlds[server].data.players {
-                let this_ip = globals
-                    .user_to_addr
-                    .get(&player.0)
-                    .expect("Online player has no IP for a requested move");
-                // send message
-                outgoing.push((Packet::ChatMessage(sendable_message.clone()), *this_ip));
+            match globals.check_chat(server, &owner, &msg.text) {
+                Ok(text) => msg.text = text,
+                Err(reason) => {
+                    outgoing.push((commands::system_message(reason, Color::RED), source_addr));
+                    return outgoing;
+                }
             }
+            outgoing.append(&mut chat::route(
+                &mut globals,
+                &owner,
+                source_addr,
+                server,
+                msg,
+            ));
             drop(globals);
         }
         Packet::UpdateObject(obj) => {
             let mut globals = globals.lock().unwrap();
             // find assoc user
-            let owner = globals
-                .addr_to_user
-                .get(&source_addr)
-                .expect("No user found for an IP adress used with Packet::UpdateObject");
-
-            let server = *globals
-                .user_to_world
-                .get(owner)
-                .expect("Owner is not in a server for Packet::UpdateObject");
+            let server = if let Some((_, server)) =
+                globals.sender_in_world(source_addr, "UpdateObject")
+            {
+                server
+            }
+            else {
+                return outgoing;
+            };
+            // rocks only change through Packet::Mine, which the server checks
+            if globals.is_rock(server, obj.uuid) {
+                warn!("A client tried to change a rock without mining it");
+                return outgoing;
+            }
 
             // for each player
             for player in &globals.worlds[server].data.players {
-                let this_ip = globals
-                    .user_to_addr
-                    .get(&player.0)
-                    .expect("Online player has no IP for a requested update");
+                let this_ip = if let Some(this_ip) = globals.user_to_addr.get(&player.0) {
+                    this_ip
+                }
+                else {
+                    continue;
+                };
                 // if this isn't the player who sent originally
                 if this_ip != &source_addr {
                     // reflect update
@@ -439,30 +571,39 @@ pub fn handler(
                     break;
                 }
             }
-            let object_index =
-                object_index.expect("No object found with given uuid for Packet::UpdateObject");
-            globals.worlds[server].data.objects[object_index] = obj;
+            if let Some(object_index) = object_index {
+                globals.worlds[server].data.objects[object_index] = obj;
+            }
+            else {
+                warn!("A client updated an object that doesn't exist");
+            }
             drop(globals);
         }
         Packet::RemoveObject(uuid) => {
             let mut globals = globals.lock().unwrap();
             // find assoc user
-            let owner = globals
-                .addr_to_user
-                .get(&source_addr)
-                .expect("No user found for an IP adress used with Packet::RemoveObject");
-
-            let server = *globals
-                .user_to_world
-                .get(owner)
-                .expect("Owner is not in a server for Packet::RemoveObject");
+            let server = if let Some((_, server)) =
+                globals.sender_in_world(source_addr, "RemoveObject")
+            {
+                server
+            }
+            else {
+                return outgoing;
+            };
+            // rocks only change through Packet::Mine, which the server checks
+            if globals.is_rock(server, uuid) {
+                warn!("A client tried to change a rock without mining it");
+                return outgoing;
+            }
 
             // for each player
             for player in &globals.worlds[server].data.players {
-                let this_ip = globals
-                    .user_to_addr
-                    .get(&player.0)
-                    .expect("Online player has no IP for a requested animation");
+                let this_ip = if let Some(this_ip) = globals.user_to_addr.get(&player.0) {
+                    this_ip
+                }
+                else {
+                    continue;
+                };
                 // if this isn't the player who sent originally
                 if this_ip != &source_addr {
                     // reflect removal
@@ -477,75 +618,49 @@ pub fn handler(
                     object_index = Some(index);
                 }
             }
-            if object_index.is_none() {
-                warn!("All objects: {:#?}", globals.worlds[server].data.objects);
+            let object_index = if let Some(object_index) = object_index {
+                object_index
+            }
+            else {
                 warn!("Requested UUID: {:?}", uuid);
                 warn!("Unable to remove this object from a given world. Please Investigate!");
-            }
-            let object_position = globals.worlds[server].data.objects[object_index.unwrap()]
-                .pos;
-            let object_representation = globals.worlds[server].data.objects[object_index.unwrap()]
+                return outgoing;
+            };
+            let object_position = globals.worlds[server].data.objects[object_index].pos;
+            let object_representation = globals.worlds[server].data.objects[object_index]
                 .rep
                 .clone();
-            match object_representation {
-                ObjectType::Tree(_) => {
-                    // spawn 2-3 wood
-                    let amount = random(2, 3);
-                    for _ in 0..amount {
-                        let x_offset = random(0, 64) as f32;
-                        let y_offset = random(0, 64) as f32;
-                        let uuid = uuid::Uuid::from_u128(rand::random());
-                        let n_object = Object {
-                            pos: Transform::from_xyz(
-                                object_position.translation.x + x_offset - 32.0,
-                                object_position.translation.y + y_offset - 32.0,
-                                0.0,
-                            ),
-                            rep: ObjectType::GroundItem(Item::Wood),
-                            uuid,
-                        };
-                        for player in &globals.worlds[server].data.players {
-                            let this_ip = globals
-                                .user_to_addr
-                                .get(&player.0)
-                                .expect("Online player has no IP for a requested animation");
-                            // send new object packet
-                            outgoing.push((Packet::CreateObject(n_object.clone()), *this_ip));
-                        }
-                        globals.worlds[server].data.objects.push(n_object);
-                    }
-                }
-                _ => {}
-            }
+            // drop whatever it was made of
+            let drops = object_representation.drops(&mut globals.rng);
+            outgoing.append(&mut globals.scatter_items(server, object_position, &drops));
             // remove object from server
-            globals.worlds[server].data.objects.remove(
-                object_index.expect("No object found with given uuid for Packet::RemoveObject"),
-            );
+            globals.worlds[server].data.objects.remove(object_index);
             drop(globals);
         }
         Packet::ActionAnimation(action) => {
             let globals = globals.lock().unwrap();
             // find assoc user
-            let owner = globals
-                .addr_to_user
-                .get(&source_addr)
-                .expect("No user found for an IP adress used with Packet::ActionAnimation");
-
-            let server = *globals
-                .user_to_world
-                .get(owner)
-                .expect("Owner is not in a server for Packet::ActionAnimation");
+            let (user, server) = if let Some(found) =
+                globals.sender_in_world(source_addr, "ActionAnimation")
+            {
+                found
+            }
+            else {
+                return outgoing;
+            };
 
             // for each player
             for player in &globals.worlds[server].data.players {
-                let this_ip = globals
-                    .user_to_addr
-                    .get(&player.0)
-                    .expect("Online player has no IP for a requested animation");
-                // if this isn't the player who sent originally
-                if this_ip != &source_addr {
+                let this_ip = if let Some(this_ip) = globals.user_to_addr.get(&player.0) {
+                    this_ip
+                }
+                else {
+                    continue;
+                };
+                // if this isn't the player who sent originally, and can tell who it was
+                if this_ip != &source_addr && globals.client_has(*this_ip, "player_actions") {
                     // send animation
-                    outgoing.push((Packet::ActionAnimation(action), *this_ip));
+                    outgoing.push((Packet::PlayerAction(user.clone(), action), *this_ip));
                 }
             }
             drop(globals);
@@ -553,21 +668,22 @@ pub fn handler(
         Packet::TileUpdate(chunk, tile, tilestate) => {
             let mut globals = globals.lock().unwrap();
             // find assoc user
-            let owner = globals
-                .addr_to_user
-                .get(&source_addr)
-                .expect("No user found for an IP adress used with Packet::TileUpdate");
-
-            let server = *globals
-                .user_to_world
-                .get(owner)
-                .expect("Owner is not in a server for Packet::TileUpdate");
+            let server = if let Some((_, server)) =
+                globals.sender_in_world(source_addr, "TileUpdate")
+            {
+                server
+            }
+            else {
+                return outgoing;
+            };
             // for each player
             for player in &globals.worlds[server].data.players {
-                let this_ip = globals
-                    .user_to_addr
-                    .get(&player.0)
-                    .expect("Online player has no IP for a requested animation");
+                let this_ip = if let Some(this_ip) = globals.user_to_addr.get(&player.0) {
+                    this_ip
+                }
+                else {
+                    continue;
+                };
                 // if this isn't the player who sent originally
                 if this_ip != &source_addr {
                     // reflect
@@ -577,7 +693,107 @@ pub fn handler(
             globals.worlds[server].data.modify_tile(chunk, tile, tilestate);
             drop(globals);
         }
-        _ => todo!(),
+        Packet::SendFriendRequest(user) => {
+            let mut globals = globals.lock().unwrap();
+            let owner = if let Some(owner) = globals.sender(source_addr, "SendFriendRequest") {
+                owner
+            }
+            else {
+                return outgoing;
+            };
+            outgoing.append(&mut globals.send_friend_request(&owner, &user));
+            drop(globals);
+        }
+        Packet::RespondFriendRequest(user, accepted) => {
+            let mut globals = globals.lock().unwrap();
+            let owner = if let Some(owner) = globals.sender(source_addr, "RespondFriendRequest") {
+                owner
+            }
+            else {
+                return outgoing;
+            };
+            outgoing.append(&mut globals.respond_friend_request(&owner, &user, accepted));
+            drop(globals);
+        }
+        Packet::RemoveFriend(user) => {
+            let mut globals = globals.lock().unwrap();
+            let owner = if let Some(owner) = globals.sender(source_addr, "RemoveFriend") {
+                owner
+            }
+            else {
+                return outgoing;
+            };
+            outgoing.append(&mut globals.remove_friend(&owner, &user));
+            drop(globals);
+        }
+        Packet::Dig(target) => {
+            let mut globals = globals.lock().unwrap();
+            outgoing.append(&mut globals.dig(source_addr, target));
+            drop(globals);
+        }
+        Packet::Water(target) => {
+            let mut globals = globals.lock().unwrap();
+            outgoing.append(&mut globals.water(source_addr, target));
+            drop(globals);
+        }
+        Packet::Plant(target, crop) => {
+            let mut globals = globals.lock().unwrap();
+            outgoing.append(&mut globals.plant(source_addr, target, crop));
+            drop(globals);
+        }
+        Packet::Cast(target) => {
+            let mut globals = globals.lock().unwrap();
+            outgoing.append(&mut globals.cast(source_addr, target));
+            drop(globals);
+        }
+        Packet::Reel => {
+            let mut globals = globals.lock().unwrap();
+            outgoing.append(&mut globals.reel(source_addr));
+            drop(globals);
+        }
+        Packet::Mine(uuid) => {
+            let mut globals = globals.lock().unwrap();
+            outgoing.append(&mut globals.mine(source_addr, uuid));
+            drop(globals);
+        }
+        Packet::Craft(recipe) => {
+            let mut globals = globals.lock().unwrap();
+            outgoing.append(&mut globals.craft(source_addr, recipe));
+            drop(globals);
+        }
+        Packet::MoveStack(from, to) => {
+            let mut globals = globals.lock().unwrap();
+            outgoing.append(&mut globals.move_stack(source_addr, from, to));
+            drop(globals);
+        }
+        Packet::SplitStack(from, to) => {
+            let mut globals = globals.lock().unwrap();
+            outgoing.append(&mut globals.split_stack(source_addr, from, to));
+            drop(globals);
+        }
+        Packet::DropItems(slot, count, towards) => {
+            let mut globals = globals.lock().unwrap();
+            outgoing.append(&mut globals.drop_items(source_addr, slot, count, towards));
+            drop(globals);
+        }
+        Packet::SelectSlot(slot) => {
+            let mut globals = globals.lock().unwrap();
+            globals.select_slot(source_addr, slot);
+            drop(globals);
+        }
+        Packet::KeepAlive => {}
+        Packet::FailedDeserialize => {
+            // Usually means the connection was closed, but could be a broken
+            // or malicious client. Either way nothing more is read from it.
+            let mut globals = globals.lock().unwrap();
+            outgoing.append(&mut globals.disconnect(source_addr));
+            globals.dropped_addrs.insert(source_addr);
+            drop(globals);
+        }
+        packet => {
+            // Only the server sends these
+            warn!("{source_addr} sent {}, which clients don't send", packet.variant_name());
+        }
     }
     outgoing
 }
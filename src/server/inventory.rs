@@ -0,0 +1,98 @@
+//! Rearranging stacks in a player's inventory, and dropping them.
+
+use crate::prelude::*;
+use crate::shared::player::Inventory;
+use std::net::SocketAddr;
+
+impl Globals {
+    /// Moves a stack onto another slot, merging or swapping them.
+    pub fn move_stack(
+        &mut self,
+        addr: SocketAddr,
+        from: usize,
+        to: usize,
+    ) -> Vec<(Packet, SocketAddr)> {
+        self.rearrange(addr, |inventory| inventory.move_stack(from, to))
+    }
+    /// Moves half of a stack into an empty slot.
+    pub fn split_stack(
+        &mut self,
+        addr: SocketAddr,
+        from: usize,
+        to: usize,
+    ) -> Vec<(Packet, SocketAddr)> {
+        self.rearrange(addr, |inventory| inventory.split_stack(from, to))
+    }
+    /// Remembers which hotbar slot a player is holding.
+    pub fn select_slot(&mut self, addr: SocketAddr, slot: usize) {
+        if slot >= HOTBAR_SIZE {
+            warn!("{addr} selected hotbar slot {slot}, which doesn't exist");
+            return;
+        }
+        if let Some((world, index)) = self.locate_player(addr) {
+            self.worlds[world].data.players[index].2.inventory.selected_slot = slot;
+        }
+    }
+    /// Throws items from a slot onto the ground, as far towards `towards` as
+    /// a player can throw. They can't be picked up again for a moment.
+    pub fn drop_items(
+        &mut self,
+        addr: SocketAddr,
+        slot: usize,
+        count: usize,
+        towards: Transform,
+    ) -> Vec<(Packet, SocketAddr)> {
+        let (world, index) = if let Some(found) = self.locate_player(addr) {
+            found
+        }
+        else {
+            return vec![];
+        };
+        let (_, position, data) = &mut self.worlds[world].data.players[index];
+        if !towards.translation.is_finite() {
+            warn!("{addr} tried to drop items towards {}", towards.translation);
+            return vec![(Packet::InventoryState(data.inventory.clone()), addr)];
+        }
+        let stack = if let Some(stack) = data.inventory.take(slot, count) {
+            stack
+        }
+        else {
+            return vec![(Packet::InventoryState(data.inventory.clone()), addr)];
+        };
+        let mut outgoing = vec![(Packet::InventoryState(data.inventory.clone()), addr)];
+        let offset = towards.translation.truncate() - position.translation.truncate();
+        let landing =
+            position.translation.truncate() + offset.clamp_length_max(ITEM_THROW_DISTANCE);
+        let object = Object {
+            pos: Transform::from_xyz(landing.x, landing.y, 0.0),
+            rep: ObjectType::GroundItem(stack),
+            uuid: random_uuid(&mut self.rng),
+        };
+        let server = &mut self.worlds[world];
+        server.pickup_after.insert(object.uuid, server.clock + ITEM_DROP_COOLDOWN);
+        server.data.objects.push(object.clone());
+        for addr in self.world_addrs(world) {
+            outgoing.push((Packet::CreateObject(object.clone()), addr));
+        }
+        outgoing
+    }
+    /// Changes a player's inventory, and sends it back to them. It's sent even
+    /// if nothing changed, so a client that guessed wrong is corrected.
+    fn rearrange(
+        &mut self,
+        addr: SocketAddr,
+        change: impl FnOnce(&mut Inventory) -> bool,
+    ) -> Vec<(Packet, SocketAddr)> {
+        let (world, index) = if let Some(found) = self.locate_player(addr) {
+            found
+        }
+        else {
+            return vec![];
+        };
+        let inventory = &mut self.worlds[world].data.players[index].2.inventory;
+        if !change(inventory) {
+            warn!("A client tried to move items in a way that isn't possible");
+        }
+        vec![(Packet::InventoryState(inventory.clone()), addr)]
+    }
+}
@@ -0,0 +1,53 @@
+//! Announces the server to clients on the local network.
+
+use super::{shared_globals, Config};
+use crate::prelude::*;
+use crate::shared::{lan::LanAnnouncement, version::ProtocolVersion};
+use std::net::{Ipv4Addr, UdpSocket};
+
+/// Starts broadcasting LAN announcements on a background thread, if enabled in
+/// the config.
+pub fn launch(config: &Config) {
+    if !config.lan_discovery {
+        return;
+    }
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)) {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("Unable to start LAN discovery: {e}");
+            return;
+        }
+    };
+    if let Err(e) = socket.set_broadcast(true) {
+        warn!("Unable to start LAN discovery: {e}");
+        return;
+    }
+    info!("Announcing this server on the local network");
+    let name = config.server_name.clone();
+    std::thread::spawn(move || loop {
+        // Nothing is announced until the server is ready for players
+        let globals = if let Some(globals) = shared_globals() {
+            globals
+        }
+        else {
+            std::thread::sleep(LAN_ANNOUNCE_INTERVAL);
+            continue;
+        };
+        let globals_access = globals.lock().unwrap();
+        let announcement = LanAnnouncement {
+            version: ProtocolVersion::current(),
+            name: name.clone(),
+            tcp_port: TCP_PORT,
+            ws_port: WS_PORT,
+            worlds: globals_access.worlds.len(),
+            online: globals_access.user_to_world.len(),
+        };
+        drop(globals_access);
+        let enc = serde_json::to_vec(&announcement)
+            .expect("Unable to serialize a LAN announcement.");
+        if let Err(e) = socket.send_to(&enc, (Ipv4Addr::BROADCAST, LAN_DISCOVERY_PORT)) {
+            warn!("Unable to send a LAN announcement: {e}");
+        }
+        std::thread::sleep(LAN_ANNOUNCE_INTERVAL);
+    });
+}
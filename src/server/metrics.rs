@@ -0,0 +1,223 @@
+//! Load and health statistics for a running server.
+//!
+//! Counters are collected as packets are handled and ticks run. A summary is
+//! logged every `metrics_log_interval` seconds, and if `metrics_port` is set
+//! everything is served in the Prometheus text format on localhost.
+
+use super::{shared_globals, Config};
+use crate::prelude::*;
+use bevy::utils::HashMap;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+#[derive(Clone)]
+pub struct Metrics {
+    /// Packets recieved, by variant
+    packets_in: HashMap<&'static str, u64>,
+    /// Packets sent, by variant
+    packets_out: HashMap<&'static str, u64>,
+    /// Total time spent in `handler`, by variant of the handled packet
+    handler_time: HashMap<&'static str, Duration>,
+    /// Longest time spent in `handler` for a single packet
+    handler_max: Duration,
+    /// Amount of ticks run
+    ticks: u64,
+    /// Total time spent in `tick`
+    tick_time: Duration,
+    /// Time spent in the most recent `tick`
+    last_tick: Duration,
+    /// Longest time spent in a single `tick`
+    tick_max: Duration,
+    /// When the summary was last logged
+    last_report: Instant,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics {
+            packets_in: default(),
+            packets_out: default(),
+            handler_time: default(),
+            handler_max: Duration::ZERO,
+            ticks: 0,
+            tick_time: Duration::ZERO,
+            last_tick: Duration::ZERO,
+            tick_max: Duration::ZERO,
+            last_report: Instant::now(),
+        }
+    }
+}
+
+impl Metrics {
+    /// Records a packet that was handled and how long handling it took.
+    pub fn record_handled(&mut self, variant: &'static str, took: Duration) {
+        *self.packets_in.entry(variant).or_insert(0) += 1;
+        *self.handler_time.entry(variant).or_insert(Duration::ZERO) += took;
+        self.handler_max = self.handler_max.max(took);
+    }
+    /// Records packets that are about to be sent.
+    pub fn record_outgoing(&mut self, outgoing: &[(Packet, std::net::SocketAddr)]) {
+        for (packet, _) in outgoing {
+            *self.packets_out.entry(packet.variant_name()).or_insert(0) += 1;
+        }
+    }
+    /// Records how long a tick took.
+    pub fn record_tick(&mut self, took: Duration) {
+        self.ticks += 1;
+        self.tick_time += took;
+        self.last_tick = took;
+        self.tick_max = self.tick_max.max(took);
+    }
+    /// Should a summary be logged now?
+    pub fn report_due(&self, config: &Config) -> bool {
+        config.metrics_log_interval > 0 &&
+        self.last_report.elapsed() > Duration::from_secs(config.metrics_log_interval)
+    }
+}
+
+impl Globals {
+    /// Logs a short summary of the server's metrics.
+    pub fn log_metrics(&mut self) {
+        self.metrics.last_report = Instant::now();
+        let packets_in: u64 = self.metrics.packets_in.values().sum();
+        let packets_out: u64 = self.metrics.packets_out.values().sum();
+        let average_tick = if self.metrics.ticks > 0 {
+            self.metrics.tick_time / self.metrics.ticks as u32
+        }
+        else {
+            Duration::ZERO
+        };
+        info!(
+            "Metrics: {} players online, {} packets in, {} packets out, {:?} average tick ({:?} max)",
+            self.user_to_world.len(),
+            packets_in,
+            packets_out,
+            average_tick,
+            self.metrics.tick_max,
+        );
+        for world in &self.worlds {
+            info!(
+                "Metrics: world {} has {} players, {} chunks and {} objects",
+                world.internal_id,
+                world.data.players.len(),
+                world.data.terrain.len(),
+                world.data.objects.len(),
+            );
+        }
+    }
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render_metrics(&self) -> String {
+        let mut out = String::new();
+        let m = &self.metrics;
+
+        out.push_str("# TYPE socktile_packets_in_total counter\n");
+        for (variant, count) in &m.packets_in {
+            let _ = writeln!(out, "socktile_packets_in_total{{variant=\"{variant}\"}} {count}");
+        }
+        out.push_str("# TYPE socktile_packets_out_total counter\n");
+        for (variant, count) in &m.packets_out {
+            let _ = writeln!(out, "socktile_packets_out_total{{variant=\"{variant}\"}} {count}");
+        }
+        out.push_str("# TYPE socktile_handler_seconds_total counter\n");
+        for (variant, time) in &m.handler_time {
+            let _ = writeln!(
+                out,
+                "socktile_handler_seconds_total{{variant=\"{variant}\"}} {}",
+                time.as_secs_f64()
+            );
+        }
+        out.push_str("# TYPE socktile_handler_max_seconds gauge\n");
+        let _ = writeln!(out, "socktile_handler_max_seconds {}", m.handler_max.as_secs_f64());
+        out.push_str("# TYPE socktile_ticks_total counter\n");
+        let _ = writeln!(out, "socktile_ticks_total {}", m.ticks);
+        out.push_str("# TYPE socktile_tick_seconds_total counter\n");
+        let _ = writeln!(out, "socktile_tick_seconds_total {}", m.tick_time.as_secs_f64());
+        out.push_str("# TYPE socktile_last_tick_seconds gauge\n");
+        let _ = writeln!(out, "socktile_last_tick_seconds {}", m.last_tick.as_secs_f64());
+        out.push_str("# TYPE socktile_tick_max_seconds gauge\n");
+        let _ = writeln!(out, "socktile_tick_max_seconds {}", m.tick_max.as_secs_f64());
+        out.push_str("# TYPE socktile_connected_users gauge\n");
+        let _ = writeln!(out, "socktile_connected_users {}", self.user_to_addr.len());
+
+        out.push_str("# TYPE socktile_world_players gauge\n");
+        for world in &self.worlds {
+            let _ = writeln!(
+                out,
+                "socktile_world_players{{world=\"{}\"}} {}",
+                world.internal_id,
+                world.data.players.len()
+            );
+        }
+        out.push_str("# TYPE socktile_world_chunks gauge\n");
+        for world in &self.worlds {
+            let _ = writeln!(
+                out,
+                "socktile_world_chunks{{world=\"{}\"}} {}",
+                world.internal_id,
+                world.data.terrain.len()
+            );
+        }
+        out.push_str("# TYPE socktile_world_objects gauge\n");
+        for world in &self.worlds {
+            let _ = writeln!(
+                out,
+                "socktile_world_objects{{world=\"{}\"}} {}",
+                world.internal_id,
+                world.data.objects.len()
+            );
+        }
+        out
+    }
+}
+
+/// Starts the metrics endpoint on its own thread if it's enabled in `config`.
+pub fn launch(config: &Config) {
+    let port = if let Some(port) = config.metrics_port {
+        port
+    }
+    else {
+        return;
+    };
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Unable to start the metrics endpoint on port {port}: {e}");
+            return;
+        }
+    };
+    info!("Metrics endpoint listening on http://127.0.0.1:{port}/metrics");
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = if let Ok(stream) = stream {
+                stream
+            }
+            else {
+                continue;
+            };
+            // A scraper that connects and never sends anything would otherwise
+            // hold up every request after it.
+            let _ = stream.set_read_timeout(Some(METRICS_TIMEOUT));
+            let _ = stream.set_write_timeout(Some(METRICS_TIMEOUT));
+            // We serve the same thing for every path, so the request itself
+            // only needs to be drained.
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let globals = if let Some(globals) = shared_globals() {
+                globals
+            }
+            else {
+                let _ = stream.write_all(b"HTTP/1.0 503 Service Unavailable\r\n\r\n");
+                continue;
+            };
+            let body = globals.lock().unwrap().render_metrics();
+            let response = format!(
+                "HTTP/1.0 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+}
@@ -0,0 +1,77 @@
+//! Breaking rocks with pickaxes.
+
+use super::commands::system_message;
+use crate::prelude::*;
+use rand::Rng;
+use std::net::SocketAddr;
+
+impl Globals {
+    /// Is this object in a world a rock?
+    pub fn is_rock(&self, world: usize, uuid: uuid::Uuid) -> bool {
+        self.worlds[world]
+            .data
+            .objects
+            .iter()
+            .any(|object| object.uuid == uuid && matches!(object.rep, ObjectType::Rock(..)))
+    }
+    /// Hits a rock with the pickaxe a player is holding. Rocks break once their
+    /// strength runs out, dropping stone or ore.
+    pub fn mine(&mut self, addr: SocketAddr, uuid: uuid::Uuid) -> Vec<(Packet, SocketAddr)> {
+        let (world, index) = if let Some(found) = self.locate_player(addr) {
+            found
+        }
+        else {
+            return vec![];
+        };
+        let (_, position, data) = &self.worlds[world].data.players[index];
+        let (position, mining) = (*position, data.stats.mining);
+        let power = self.held(addr, world, index, |action| match action {
+            ItemAction::Mine(power) => Some(power),
+            _ => None,
+        });
+        let power = if let Some(power) = power {
+            power
+        }
+        else {
+            return vec![];
+        };
+        let objects = &mut self.worlds[world].data.objects;
+        let object_index = if let Some(object_index) = objects
+            .iter()
+            .position(|object| object.uuid == uuid && distance(object.pos, position) < MINE_REACH)
+        {
+            object_index
+        }
+        else {
+            return vec![];
+        };
+        let (ore, strength) = if let ObjectType::Rock(ore, strength) = objects[object_index].rep {
+            (ore, strength)
+        }
+        else {
+            return vec![];
+        };
+        if ore.hardness() > power {
+            let message = "This rock is too hard for your pickaxe.";
+            return vec![(system_message(message, Color::DARK_GRAY), addr)];
+        }
+        if strength > power {
+            objects[object_index].rep = ObjectType::Rock(ore, strength - power);
+            let object = objects[object_index].clone();
+            return self.object_updated(world, &object);
+        }
+        let object = objects.remove(object_index);
+        let mut outgoing: Vec<(Packet, SocketAddr)> = self
+            .world_addrs(world)
+            .into_iter()
+            .map(|addr| (Packet::RemoveObject(object.uuid), addr))
+            .collect();
+        let mut drops = object.rep.drops(&mut self.rng);
+        let bonus = MINING_BONUS_CHANCE * mining.saturating_sub(1) as f32;
+        if self.rng.gen::<f32>() < bonus {
+            drops.push(ore.drops().0);
+        }
+        outgoing.append(&mut self.scatter_items(world, object.pos, &drops));
+        outgoing
+    }
+}
@@ -0,0 +1,131 @@
+//! Chat moderation: message length limits, the word filter, and mutes.
+
+use super::SaveGame;
+use crate::prelude::*;
+
+/// Words that are censored from chat, loaded from `word_filter.txt` in the
+/// data directory. The file has one word or phrase per line, and lines
+/// starting with `#` are ignored.
+#[derive(Clone, Debug, Default)]
+pub struct WordFilter {
+    /// Lowercase words to censor
+    words: Vec<Vec<char>>,
+}
+
+impl WordFilter {
+    /// Loads the word filter from the disk. If there is no filter file, nothing
+    /// is censored.
+    pub fn load(data_dir: &std::path::Path) -> WordFilter {
+        let mut path = data_dir.to_path_buf();
+        path.push("word_filter.txt");
+        let data = if let Ok(data) = std::fs::read_to_string(&path) {
+            data
+        }
+        else {
+            return WordFilter::default();
+        };
+        let words: Vec<Vec<char>> = data
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.chars().map(lowercase).collect())
+            .collect();
+        info!("Loaded {} filtered words", words.len());
+        WordFilter { words }
+    }
+    /// Replaces every filtered word in `text` with `*`s, ignoring case.
+    pub fn censor(&self, text: &str) -> String {
+        let mut chars: Vec<char> = text.chars().collect();
+        let lower: Vec<char> = chars.iter().map(|c| lowercase(*c)).collect();
+        for word in &self.words {
+            if word.len() > lower.len() {
+                continue;
+            }
+            for start in 0..=(lower.len() - word.len()) {
+                if lower[start..start + word.len()] == word[..] {
+                    for c in &mut chars[start..start + word.len()] {
+                        *c = '*';
+                    }
+                }
+            }
+        }
+        chars.into_iter().collect()
+    }
+}
+
+fn lowercase(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Returns the current unix time in seconds.
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("The system clock is set before 1970.")
+        .as_secs()
+}
+
+/// Parses a duration like `30s`, `10m`, `2h` or `1d` into seconds. Numbers
+/// without a unit are minutes.
+pub fn parse_duration(from: &str) -> Option<u64> {
+    let (number, unit) = match from.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => from.split_at(index),
+        None => (from, "m"),
+    };
+    let number: u64 = number.parse().ok()?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => return None,
+    };
+    number.checked_mul(multiplier)
+}
+
+impl SaveGame {
+    /// Mutes a player in this world for some number of seconds.
+    pub fn mute(&mut self, user: &User, seconds: u64) {
+        self.mutes
+            .insert(user.clone(), unix_now().saturating_add(seconds));
+    }
+    /// Unmutes a player. Returns false if they weren't muted.
+    pub fn unmute(&mut self, user: &User) -> bool {
+        self.mutes.remove(user).is_some()
+    }
+    /// Returns how many seconds a player is still muted for, if they are.
+    pub fn mute_remaining(&self, user: &User) -> Option<u64> {
+        let until = *self.mutes.get(user)?;
+        let now = unix_now();
+        if until > now {
+            Some(until - now)
+        }
+        else {
+            None
+        }
+    }
+}
+
+impl Globals {
+    /// Checks that a player is allowed to send a chat message in their world,
+    /// returning the text with filtered words censored or an explanation of
+    /// why it can't be sent.
+    pub fn check_chat(&mut self, world: usize, sender: &User, text: &str) -> Result<String, String> {
+        if let Some(remaining) = self.worlds[world].mute_remaining(sender) {
+            return Err(format!(
+                "You are muted for another {}.",
+                format_duration(remaining)
+            ));
+        }
+        // Forget mutes that have run out
+        self.worlds[world].mutes.remove(sender);
+        let length = text.chars().count();
+        if length > self.config.max_chat_length {
+            return Err(format!(
+                "Your message is too long. ({length}/{})",
+                self.config.max_chat_length
+            ));
+        }
+        Ok(self.word_filter.censor(text))
+    }
+}
@@ -0,0 +1,94 @@
+//! Parties: small groups of players that can chat with each other from any
+//! world. Parties only exist while the server is running.
+
+use crate::prelude::*;
+use bevy::utils::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct Party {
+    pub leader: User,
+    /// Everyone in the party, including the leader
+    pub members: Vec<User>,
+}
+
+#[derive(Clone, Default)]
+pub struct Parties {
+    parties: Vec<Party>,
+    /// Invited user -> party leader who invited them
+    invites: HashMap<User, User>,
+}
+
+impl Parties {
+    /// Returns the party a user is in.
+    pub fn party_of(&self, user: &User) -> Option<&Party> {
+        self.parties
+            .iter()
+            .find(|party| party.members.contains(user))
+    }
+    /// Invites a user to the inviter's party, creating one if needed. Returns
+    /// false if the inviter is in a party they don't lead.
+    pub fn invite(&mut self, inviter: &User, invited: &User) -> bool {
+        match self.party_of(inviter) {
+            Some(party) if &party.leader != inviter => return false,
+            Some(_) => {}
+            None => {
+                self.parties.push(Party {
+                    leader: inviter.clone(),
+                    members: vec![inviter.clone()],
+                });
+            }
+        }
+        self.invites.insert(invited.clone(), inviter.clone());
+        true
+    }
+    /// Accepts a user's pending invite, leaving their current party. Returns
+    /// the party joined.
+    pub fn accept(&mut self, user: &User) -> Option<&Party> {
+        let leader = self.invites.remove(user)?;
+        let index = self
+            .parties
+            .iter()
+            .position(|party| party.leader == leader)?;
+        if self.parties[index].members.contains(user) {
+            return Some(&self.parties[index]);
+        }
+        self.leave(user);
+        // Leaving may have removed a party before this one
+        let index = self
+            .parties
+            .iter()
+            .position(|party| party.leader == leader)?;
+        self.parties[index].members.push(user.clone());
+        Some(&self.parties[index])
+    }
+    /// Removes a user from their party. Parties left with one member are
+    /// disbanded, and the oldest member takes over if the leader leaves.
+    /// Returns the members that are still in the party.
+    pub fn leave(&mut self, user: &User) -> Vec<User> {
+        let index = if let Some(index) = self
+            .parties
+            .iter()
+            .position(|party| party.members.contains(user))
+        {
+            index
+        }
+        else {
+            return vec![];
+        };
+        let party = &mut self.parties[index];
+        party.members.retain(|member| member != user);
+        if &party.leader == user {
+            if let Some(new_leader) = party.members.first() {
+                party.leader = new_leader.clone();
+            }
+            self.invites.retain(|_, leader| leader != user);
+        }
+        let remaining = party.members.clone();
+        if remaining.len() < 2 {
+            let leader = party.leader.clone();
+            self.parties.swap_remove(index);
+            self.invites.retain(|_, inviter| inviter != &leader);
+        }
+        remaining
+    }
+}
@@ -0,0 +1,181 @@
+//! Per-connection flood protection.
+//!
+//! Every connection gets a token bucket for each [PacketClass]. Packets that
+//! arrive with an empty bucket are dropped, and connections that keep sending
+//! them are warned and eventually removed from their world. Everything else
+//! they send is ignored for `FLOOD_PENALTY` afterwards.
+
+use crate::prelude::*;
+use bevy::utils::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// How long a connection has to behave before its violations are forgotten.
+const VIOLATION_MEMORY: Duration = Duration::from_secs(60);
+/// How long a connection removed for flooding is ignored for.
+const FLOOD_PENALTY: Duration = Duration::from_secs(60 * 5);
+
+/// Groups of packets that share a rate limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PacketClass {
+    Chunk,
+    Chat,
+    Move,
+    Other,
+}
+
+impl PacketClass {
+    pub fn of(packet: &Packet) -> PacketClass {
+        match packet {
+            Packet::RequestChunk(_) => PacketClass::Chunk,
+            Packet::SendChatMessage(_) => PacketClass::Chat,
+            Packet::RequestMove(_) => PacketClass::Move,
+            _ => PacketClass::Other,
+        }
+    }
+}
+
+/// A token bucket's refill rate and capacity.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Packets allowed per second, on average
+    pub per_second: f32,
+    /// Packets that can be sent at once after being idle
+    pub burst: f32,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimits {
+    pub chunk: RateLimit,
+    pub chat: RateLimit,
+    #[serde(rename = "move")]
+    pub move_: RateLimit,
+    pub other: RateLimit,
+    /// Dropped packets before a connection is warned
+    pub warn_after: u32,
+    /// Dropped packets before a connection is removed from its world
+    pub disconnect_after: u32,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            // A full 5x5 set of chunks is requested when joining
+            chunk: RateLimit { per_second: 30.0, burst: 50.0 },
+            chat: RateLimit { per_second: 2.0, burst: 5.0 },
+            // Clients send a move every frame while walking
+            move_: RateLimit { per_second: 90.0, burst: 120.0 },
+            other: RateLimit { per_second: 30.0, burst: 60.0 },
+            warn_after: 10,
+            disconnect_after: 100,
+        }
+    }
+}
+
+impl RateLimits {
+    /// Limits that are never hit, for when packets are sent by the server
+    /// itself, such as when replaying a capture.
+    pub fn unlimited() -> RateLimits {
+        let unlimited = RateLimit {
+            per_second: 1.0e9,
+            burst: 1.0e9,
+        };
+        RateLimits {
+            chunk: unlimited,
+            chat: unlimited,
+            move_: unlimited,
+            other: unlimited,
+            warn_after: u32::MAX,
+            disconnect_after: u32::MAX,
+        }
+    }
+    fn get(&self, class: PacketClass) -> RateLimit {
+        match class {
+            PacketClass::Chunk => self.chunk,
+            PacketClass::Chat => self.chat,
+            PacketClass::Move => self.move_,
+            PacketClass::Other => self.other,
+        }
+    }
+}
+
+/// What should happen to a packet after checking its rate limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Handle the packet normally
+    Allow,
+    /// Drop the packet
+    Throttle,
+    /// Drop the packet and tell the sender to slow down
+    Warn,
+    /// Drop the packet and remove the sender from their world. Anything else
+    /// they send is throttled until their penalty ends.
+    Disconnect,
+}
+
+#[derive(Clone, Copy)]
+struct Bucket {
+    tokens: f32,
+    last_refill: Instant,
+}
+
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    buckets: HashMap<(SocketAddr, PacketClass), Bucket>,
+    /// (Dropped packets, Time of the last one)
+    violations: HashMap<SocketAddr, (u32, Instant)>,
+    /// Connections removed for flooding, and when they'll be listened to again
+    penalties: HashMap<SocketAddr, Instant>,
+}
+
+impl RateLimiter {
+    /// Takes a token for a packet from `addr`, and decides what to do with it.
+    pub fn check(&mut self, addr: SocketAddr, class: PacketClass, limits: &RateLimits) -> Verdict {
+        if let Some(until) = self.penalties.get(&addr) {
+            if Instant::now() < *until {
+                return Verdict::Throttle;
+            }
+            self.penalties.remove(&addr);
+        }
+        let limit = limits.get(class);
+        let bucket = self.buckets.entry((addr, class)).or_insert(Bucket {
+            tokens: limit.burst,
+            last_refill: Instant::now(),
+        });
+        let refill = bucket.last_refill.elapsed().as_secs_f32() * limit.per_second;
+        bucket.tokens = (bucket.tokens + refill).min(limit.burst);
+        bucket.last_refill = Instant::now();
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Verdict::Allow;
+        }
+
+        let violations = self.violations.entry(addr).or_insert((0, Instant::now()));
+        if violations.1.elapsed() > VIOLATION_MEMORY {
+            violations.0 = 0;
+        }
+        violations.0 += 1;
+        violations.1 = Instant::now();
+        if violations.0 >= limits.disconnect_after {
+            self.violations.remove(&addr);
+            self.buckets.retain(|(bucket_addr, _), _| *bucket_addr != addr);
+            let now = Instant::now();
+            self.penalties.retain(|_, until| *until > now);
+            self.penalties.insert(addr, now + FLOOD_PENALTY);
+            Verdict::Disconnect
+        }
+        else if violations.0 == limits.warn_after {
+            Verdict::Warn
+        }
+        else {
+            Verdict::Throttle
+        }
+    }
+    /// Forgets a connection's buckets and violations. Penalties are kept until
+    /// they run out, so closing a connection doesn't end one.
+    pub fn forget(&mut self, addr: SocketAddr) {
+        self.buckets.retain(|(bucket_addr, _), _| *bucket_addr != addr);
+        self.violations.remove(&addr);
+    }
+}
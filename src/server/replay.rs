@@ -0,0 +1,135 @@
+//! Replays a packet capture against a fresh server, to reproduce bugs without
+//! needing the players that caused them.
+//!
+//! Server captures record the seed of the server's random rolls, and are
+//! timestamped with the time the server had simulated, so replaying one sends
+//! the same packets the server did. The wall clock (used by rate limits,
+//! keepalives and timestamps) isn't recorded, so anything that depends on it
+//! can still come out differently. Client captures don't know the seed.
+
+use super::{advance, handler, rate_limit::RateLimits};
+use crate::prelude::*;
+use crate::shared::capture::{read_capture, CaptureHeader, CapturedPacket, Direction, Side};
+use crate::shared::version::ProtocolVersion;
+use bevy::utils::HashMap;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Runs `socktile replay <capture file>`. Every packet the server recieved (or
+/// the client sent) is handled in order, with ticks run in between, and how
+/// many of each kind of packet were sent back is compared with the capture.
+pub fn replay(arguments: Vec<String>) -> ! {
+    let path = if let Some(path) = arguments.get(1) {
+        std::path::PathBuf::from(path)
+    }
+    else {
+        error!("Usage: socktile replay <capture file>");
+        std::process::exit(1);
+    };
+    let (header, packets) = match read_capture(&path) {
+        Ok(capture) => capture,
+        Err(e) => {
+            error!("Unable to read {}: {e}", path.display());
+            std::process::exit(1);
+        }
+    };
+    if !ProtocolVersion::current().compatible_with(&header.version) {
+        warn!(
+            "This capture was made with protocol version {}, and this is {}",
+            header.version,
+            ProtocolVersion::current()
+        );
+    }
+
+    // The replayed server gets an empty data directory of its own
+    let dir = std::env::temp_dir().join(format!("socktile-replay-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("Unable to create a directory to replay in.");
+    info!("Replaying {} in {}", path.display(), dir.display());
+    let globals = Arc::new(Mutex::new(Globals::load(dir.clone())));
+    let outgoing = replay_capture(globals, &header, &packets);
+
+    let output = output_direction(&header);
+    let peers: HashSet<SocketAddr> = packets.iter().map(|captured| captured.peer).collect();
+    let mut recorded: HashMap<&'static str, usize> = default();
+    let mut replayed: HashMap<&'static str, usize> = default();
+    let mut handled = 0;
+    for captured in &packets {
+        if captured.direction == output {
+            *recorded.entry(captured.packet.variant_name()).or_default() += 1;
+        }
+        else {
+            handled += 1;
+        }
+    }
+    for (packet, to) in outgoing {
+        if peers.contains(&to) {
+            *replayed.entry(packet.variant_name()).or_default() += 1;
+        }
+    }
+
+    info!("Replayed {handled} packets");
+    let mut variants: Vec<&str> = recorded.keys().chain(replayed.keys()).copied().collect();
+    variants.sort();
+    variants.dedup();
+    let mut differences = 0;
+    for variant in variants {
+        let before = recorded.get(variant).copied().unwrap_or(0);
+        let after = replayed.get(variant).copied().unwrap_or(0);
+        if before != after {
+            differences += 1;
+            warn!("{variant}: {before} in the capture, {after} when replayed");
+        }
+    }
+    if differences == 0 {
+        info!("The replay sent the same packets as the capture");
+    }
+    else if header.seed.is_none() {
+        info!("This capture doesn't have the server's seed, so some differences are expected");
+    }
+    std::fs::remove_dir_all(&dir).ok();
+    std::process::exit(0);
+}
+
+/// Handles every packet sent to the server in a capture, advancing the server
+/// by the time between them, and returns everything it sent.
+pub fn replay_capture(
+    globals: Arc<Mutex<Globals>>,
+    header: &CaptureHeader,
+    packets: &[CapturedPacket],
+) -> Vec<(Packet, SocketAddr)> {
+    {
+        let mut globals = globals.lock().unwrap();
+        // Packets are replayed as fast as possible, which would trip flood protection
+        globals.config.rate_limits = RateLimits::unlimited();
+        if let Some(seed) = header.seed {
+            globals.reseed(seed);
+        }
+    }
+    let output = output_direction(header);
+    let mut outgoing = vec![];
+    let mut simulated = Duration::ZERO;
+    for captured in packets {
+        // Run the steps that happened before this packet
+        if captured.elapsed > simulated {
+            outgoing.append(&mut advance(globals.clone(), captured.elapsed - simulated));
+            simulated = captured.elapsed;
+        }
+        if captured.direction == output {
+            continue;
+        }
+        debug!("Replaying {:?} from {}", captured.packet, captured.peer);
+        outgoing.append(&mut handler(captured.packet.clone(), globals.clone(), captured.peer));
+    }
+    outgoing
+}
+
+/// Which packets in a capture came from the server. Everything else was sent
+/// to it.
+fn output_direction(header: &CaptureHeader) -> Direction {
+    match header.side {
+        Side::Server => Direction::Sent,
+        Side::Client => Direction::Recieved,
+    }
+}
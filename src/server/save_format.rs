@@ -0,0 +1,223 @@
+//! How worlds and profiles are stored on disk.
+//!
+//! Files start with `MAGIC` and the `SAVE_FORMAT_VERSION` they were written
+//! with, followed by their bincode data. Files written before there was a
+//! header are read with the original layouts in [v0] and upgraded. Anything
+//! from a newer version is refused, rather than read as garbage.
+
+use super::{world::World, Profile, SaveGame};
+use crate::prelude::*;
+use crate::shared::player::{Inventory, Recipes, Stats};
+use anyhow::bail;
+
+/// Marks a file as having a version header.
+const MAGIC: [u8; 8] = *b"socktile";
+
+/// Encodes a world or profile for the disk, with a header.
+pub fn encode<T: Serialize>(data: &T) -> Vec<u8> {
+    let mut enc = MAGIC.to_vec();
+    enc.extend_from_slice(&SAVE_FORMAT_VERSION.to_le_bytes());
+    enc.append(&mut bincode::serialize(data).expect("Unable to serialize save data."));
+    enc
+}
+
+/// Splits a file into the format version it was written with and its data.
+fn split(bytes: &[u8]) -> (u32, &[u8]) {
+    if bytes.len() >= MAGIC.len() + 4 && bytes[..MAGIC.len()] == MAGIC {
+        let mut version = [0; 4];
+        version.copy_from_slice(&bytes[MAGIC.len()..MAGIC.len() + 4]);
+        (u32::from_le_bytes(version), &bytes[MAGIC.len() + 4..])
+    }
+    else {
+        (0, bytes)
+    }
+}
+
+/// Reads a world, upgrading it if it's from an older version.
+pub fn decode_world(bytes: &[u8]) -> anyhow::Result<SaveGame> {
+    match split(bytes) {
+        (0, data) => Ok(bincode::deserialize::<v0::SaveGame>(data)?.upgrade()),
+        (SAVE_FORMAT_VERSION, data) => Ok(bincode::deserialize(data)?),
+        (version, _) => bail!(newer(version)),
+    }
+}
+
+/// Reads a profile, upgrading it if it's from an older version.
+pub fn decode_profile(bytes: &[u8]) -> anyhow::Result<Profile> {
+    match split(bytes) {
+        (0, data) => Ok(bincode::deserialize::<v0::Profile>(data)?.upgrade()),
+        (SAVE_FORMAT_VERSION, data) => Ok(bincode::deserialize(data)?),
+        (version, _) => bail!(newer(version)),
+    }
+}
+
+/// Why a file from a newer version can't be loaded.
+fn newer(version: u32) -> String {
+    format!(
+        "it was saved in format {version}, but this server only understands up to format \
+        {SAVE_FORMAT_VERSION}. Update the server to load it."
+    )
+}
+
+/// Layouts from before save files had a version header.
+pub mod v0 {
+    use crate::prelude::*;
+    use crate::shared::player::Achievements;
+    use bevy::utils::HashMap;
+    use std::path::PathBuf;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct SaveGame {
+        pub public_name: String,
+        pub internal_id: usize,
+        pub data: World,
+        pub path: PathBuf,
+        pub whitelist: Vec<User>,
+        pub played_before: Vec<User>,
+        pub owner: User,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct World {
+        pub players: Vec<(User, Transform, PlayerData)>,
+        pub offline_players: Vec<(User, Transform, PlayerData)>,
+        pub terrain: HashMap<(isize, isize), Vec<usize>>,
+        pub objects: Vec<Object>,
+        pub generated_objects: Vec<(isize, isize)>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct PlayerData {
+        pub inventory: Inventory,
+        pub stats: Stats,
+        pub recipes: Recipes,
+        pub achievements: Achievements,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Inventory {
+        pub selected_slot: usize,
+        pub hotbar: [Option<Item>; 10],
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Stats {
+        pub mining: usize,
+        pub fishing: usize,
+        pub cooking: usize,
+        pub crafting: usize,
+        pub trading: usize,
+    }
+
+    /// Recipes were never unlocked before crafting existed, so these are only
+    /// read to get past them.
+    #[derive(Serialize, Deserialize)]
+    pub struct Recipes {
+        pub unlocked: Vec<Recipe>,
+        pub locked: Vec<Recipe>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum Recipe {
+        BigRock,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Object {
+        pub pos: Transform,
+        pub rep: ObjectType,
+        pub uuid: uuid::Uuid,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum ObjectType {
+        Tree(usize),
+        GroundItem(Item),
+        Npc(Npc),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Profile {
+        pub user: User,
+        pub avalable_games: Vec<usize>,
+    }
+}
+
+impl v0::SaveGame {
+    /// Fills in everything added since, as if the world had just been created.
+    fn upgrade(self) -> SaveGame {
+        let upgrade_players = |players: Vec<(User, Transform, v0::PlayerData)>| -> Vec<_> {
+            players
+                .into_iter()
+                .map(|(user, position, data)| (user, position, data.upgrade()))
+                .collect()
+        };
+        let mut save = SaveGame::new(
+            self.public_name,
+            self.internal_id,
+            self.path,
+            self.owner,
+            &mut rand::thread_rng(),
+        );
+        save.whitelist = self.whitelist;
+        save.played_before = self.played_before;
+        save.data = World {
+            players: upgrade_players(self.data.players),
+            offline_players: upgrade_players(self.data.offline_players),
+            terrain: self.data.terrain,
+            objects: self.data.objects.into_iter().map(v0::Object::upgrade).collect(),
+            generated_objects: self.data.generated_objects,
+            chat_log: vec![],
+        };
+        save
+    }
+}
+
+impl v0::PlayerData {
+    fn upgrade(self) -> PlayerData {
+        let mut inventory = Inventory::empty();
+        inventory.selected_slot = self.inventory.selected_slot;
+        for (slot, item) in self.inventory.hotbar.into_iter().enumerate() {
+            inventory.hotbar[slot] = item.map(ItemStack::one);
+        }
+        PlayerData {
+            inventory,
+            stats: Stats {
+                mining: self.stats.mining,
+                fishing: self.stats.fishing,
+                cooking: self.stats.cooking,
+                crafting: self.stats.crafting,
+                trading: self.stats.trading,
+                crafts: 0,
+            },
+            recipes: Recipes::starting(),
+            achievements: self.achievements,
+        }
+    }
+}
+
+impl v0::Object {
+    fn upgrade(self) -> Object {
+        let rep = match self.rep {
+            v0::ObjectType::Tree(strength) => ObjectType::Tree(strength),
+            v0::ObjectType::GroundItem(item) => ObjectType::GroundItem(ItemStack::one(item)),
+            v0::ObjectType::Npc(npc) => ObjectType::Npc(npc),
+        };
+        Object {
+            pos: self.pos,
+            rep,
+            uuid: self.uuid,
+        }
+    }
+}
+
+impl v0::Profile {
+    fn upgrade(self) -> Profile {
+        Profile {
+            user: self.user,
+            avalable_games: self.avalable_games,
+            friends: vec![],
+            friend_requests: vec![],
+        }
+    }
+}
@@ -0,0 +1,943 @@
+//! In-process scenario tests. A `Scenario` is a real server over a temporary
+//! data directory, driven by scripted packets from fake clients.
+
+use super::{advance, handler, rate_limit::RateLimits};
+use crate::prelude::*;
+use crate::resources::{ChatChannel, ChatMessage};
+use crate::shared::player::Inventory;
+use crate::shared::version::ProtocolVersion;
+use crate::shared::weather::Weather;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+struct Scenario {
+    globals: Arc<Mutex<Globals>>,
+    dir: PathBuf,
+}
+
+impl Scenario {
+    /// Starts a server with no worlds or profiles.
+    fn new() -> Scenario {
+        Scenario::load(Scenario::data_dir())
+    }
+    /// Makes an empty temporary data directory.
+    fn data_dir() -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("socktile-scenario-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("Unable to create a scenario directory.");
+        dir
+    }
+    /// Starts a server with whatever is already in `dir`.
+    fn load(dir: PathBuf) -> Scenario {
+        let mut globals = Globals::load(dir.clone());
+        // Scripted clients send as fast as they like
+        globals.config.rate_limits = RateLimits::unlimited();
+        Scenario {
+            globals: Arc::new(Mutex::new(globals)),
+            dir,
+        }
+    }
+    /// The address of a fake client.
+    fn client(number: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], 40000 + number))
+    }
+    fn globals(&self) -> MutexGuard<'_, Globals> {
+        self.globals.lock().unwrap()
+    }
+    /// Handles a packet from `from`, returning everything the server sends.
+    fn send(&self, from: SocketAddr, packet: Packet) -> Vec<(Packet, SocketAddr)> {
+        handler(packet, self.globals.clone(), from)
+    }
+    /// Runs one simulation step.
+    fn tick(&self) -> Vec<(Packet, SocketAddr)> {
+        let step = self.globals().config.tick_length();
+        self.wait(step)
+    }
+    /// Runs the server as if `elapsed` time had passed.
+    fn wait(&self, elapsed: Duration) -> Vec<(Packet, SocketAddr)> {
+        advance(self.globals.clone(), elapsed)
+    }
+    /// Sends a chat message, such as a command, to the world.
+    fn chat(&self, from: SocketAddr, text: &str) -> Vec<(Packet, SocketAddr)> {
+        self.send(
+            from,
+            Packet::SendChatMessage(ChatMessage {
+                text: String::from(text),
+                color: Color::BLACK,
+                channel: ChatChannel::World,
+                sender: None,
+                sent_at: std::time::Instant::now(),
+            }),
+        )
+    }
+    /// Connects a client and makes a user for it.
+    fn create_user(&self, from: SocketAddr, username: &str) -> User {
+        self.send(from, Packet::NettyVersion(ProtocolVersion::current()));
+        let outgoing = self.send(
+            from,
+            Packet::CreateUser(User {
+                username: String::from(username),
+                tag: 0,
+            }),
+        );
+        for packet in to(&outgoing, from) {
+            if let Packet::CreatedUser(user) = packet {
+                return user;
+            }
+        }
+        panic!("No user was created: {outgoing:?}");
+    }
+    fn create_world(&self, from: SocketAddr, name: &str) -> usize {
+        let outgoing = self.send(from, Packet::CreateWorld(String::from(name)));
+        for packet in to(&outgoing, from) {
+            if let Packet::CreatedWorld(world) = packet {
+                return world;
+            }
+        }
+        panic!("No world was created: {outgoing:?}");
+    }
+    /// Joins a world, returning where the player spawned and if they own it.
+    fn join_world(&self, from: SocketAddr, world: usize) -> (Transform, bool) {
+        let outgoing = self.send(from, Packet::JoinWorld(world));
+        for packet in to(&outgoing, from) {
+            if let Packet::JoinedGame(position, owner) = packet {
+                return (position, owner);
+            }
+        }
+        panic!("The world wasn't joined: {outgoing:?}");
+    }
+    /// Makes a user who has created and joined a world.
+    fn owner_in_world(&self, from: SocketAddr) -> (User, usize, Transform) {
+        let user = self.create_user(from, "Owner");
+        let world = self.create_world(from, "Test World");
+        let (position, _) = self.join_world(from, world);
+        (user, world, position)
+    }
+    /// Adds an object to a world, as if it had been generated.
+    fn spawn(&self, world: usize, rep: ObjectType, position: Transform) -> uuid::Uuid {
+        let uuid = uuid::Uuid::new_v4();
+        self.globals().worlds[world].data.objects.push(Object {
+            pos: position,
+            rep,
+            uuid,
+        });
+        uuid
+    }
+}
+
+impl Drop for Scenario {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.dir).ok();
+    }
+}
+
+/// The packets sent to one client.
+fn to(outgoing: &[(Packet, SocketAddr)], addr: SocketAddr) -> Vec<Packet> {
+    outgoing
+        .iter()
+        .filter(|(_, to)| *to == addr)
+        .map(|(packet, _)| packet.clone())
+        .collect()
+}
+
+#[test]
+fn create_user() {
+    let scenario = Scenario::new();
+    let first = scenario.create_user(Scenario::client(1), "Player");
+    let second = scenario.create_user(Scenario::client(2), "Player");
+    assert_eq!(first.tag, 1);
+    assert_eq!(second.tag, 2);
+    let globals = scenario.globals();
+    assert!(globals.profiles.iter().any(|profile| profile.user == first));
+    assert_eq!(globals.addr_to_user.get(&Scenario::client(2)), Some(&second));
+}
+
+#[test]
+fn baseline_save() {
+    use super::save_format::{self, v0};
+    use super::tick::{profile_folder, save_folder};
+    use crate::shared::player::Achievements;
+
+    let dir = Scenario::data_dir();
+    let owner = User {
+        username: String::from("Owner"),
+        tag: 1,
+    };
+    let mut hotbar = [None; 10];
+    hotbar[0] = Some(Item::MakeshiftAxe);
+    hotbar[3] = Some(Item::Wood);
+    let data = v0::PlayerData {
+        inventory: v0::Inventory {
+            selected_slot: 3,
+            hotbar,
+        },
+        stats: v0::Stats {
+            mining: 2,
+            fishing: 1,
+            cooking: 1,
+            crafting: 1,
+            trading: 1,
+        },
+        recipes: v0::Recipes {
+            unlocked: vec![],
+            locked: vec![v0::Recipe::BigRock],
+        },
+        achievements: Achievements::none(),
+    };
+    let world = v0::SaveGame {
+        public_name: String::from("Old World"),
+        internal_id: 0,
+        data: v0::World {
+            players: vec![],
+            offline_players: vec![(owner.clone(), Transform::from_xyz(64.0, 0.0, 0.0), data)],
+            terrain: default(),
+            objects: vec![v0::Object {
+                pos: Transform::from_xyz(0.0, 0.0, 0.0),
+                rep: v0::ObjectType::GroundItem(Item::Wood),
+                uuid: uuid::Uuid::new_v4(),
+            }],
+            generated_objects: vec![],
+        },
+        path: save_folder(&dir).join("Old_0.bic"),
+        whitelist: vec![owner.clone()],
+        played_before: vec![owner.clone()],
+        owner: owner.clone(),
+    };
+    let profile = v0::Profile {
+        user: owner.clone(),
+        avalable_games: vec![0],
+    };
+    // Saves used to be bare bincode, with no header
+    let path = world.path.clone();
+    std::fs::write(&path, bincode::serialize(&world).unwrap()).unwrap();
+    let profile_path = profile_folder(&dir).join("Owner1.bic");
+    std::fs::write(profile_path, bincode::serialize(&profile).unwrap()).unwrap();
+
+    let scenario = Scenario::load(dir);
+    {
+        let globals = scenario.globals();
+        let world = &globals.worlds[0];
+        assert_eq!(world.public_name, "Old World");
+        assert_eq!(world.owner, owner);
+        assert_eq!(world.clock, 0.0);
+        assert_eq!(world.day_length, DEFAULT_DAY_LENGTH);
+        assert!(world.pickup_after.is_empty());
+        let (user, position, data) = &world.data.offline_players[0];
+        assert_eq!(user, &owner);
+        assert_eq!(position.translation.x, 64.0);
+        assert_eq!(data.inventory.selected_slot, 3);
+        assert_eq!(data.inventory.hotbar[0], Some(ItemStack::one(Item::MakeshiftAxe)));
+        assert_eq!(data.inventory.hotbar[3], Some(ItemStack::one(Item::Wood)));
+        assert_eq!(data.inventory.backpack, [None; BACKPACK_SIZE]);
+        assert_eq!(data.stats.mining, 2);
+        assert!(data.recipes.unlocked().is_empty());
+        let wood = ObjectType::GroundItem(ItemStack::one(Item::Wood));
+        assert_eq!(world.data.objects[0].rep, wood);
+        assert_eq!(globals.profiles[0].user, owner);
+        assert_eq!(globals.profiles[0].avalable_games, vec![0]);
+        assert!(globals.profiles[0].friends.is_empty());
+    }
+
+    // Upgraded worlds are saved with a header, and load the same way again
+    scenario.globals().save_all();
+    assert!(std::fs::read(&path).unwrap().starts_with(b"socktile"));
+    let reloaded = Globals::load(scenario.dir.clone());
+    assert_eq!(reloaded.worlds, scenario.globals().worlds);
+    assert_eq!(reloaded.profiles, scenario.globals().profiles);
+
+    // Saves from newer versions are refused instead of misread
+    let mut newer = b"socktile".to_vec();
+    newer.extend_from_slice(&(SAVE_FORMAT_VERSION + 1).to_le_bytes());
+    newer.append(&mut bincode::serialize(&reloaded.worlds[0]).unwrap());
+    assert!(save_format::decode_world(&newer).is_err());
+}
+
+#[test]
+fn create_and_join_world() {
+    let scenario = Scenario::new();
+    let owner = Scenario::client(1);
+    let guest = Scenario::client(2);
+    let owner_user = scenario.create_user(owner, "Owner");
+    let world = scenario.create_world(owner, "Test World");
+    assert_eq!(scenario.globals().worlds[world].owner, owner_user);
+
+    let (_, owns) = scenario.join_world(owner, world);
+    assert!(owns);
+    let guest_user = scenario.create_user(guest, "Guest");
+    let outgoing = scenario.send(guest, Packet::JoinWorld(world));
+    let spawn = Transform::from_xyz(0.0, 0.0, 0.0);
+    assert!(to(&outgoing, guest).contains(&Packet::JoinedGame(spawn, false)));
+    // The owner hears about the guest
+    assert!(to(&outgoing, owner)
+        .iter()
+        .any(|packet| matches!(packet, Packet::PlayerConnected(user, _) if *user == guest_user)));
+    let globals = scenario.globals();
+    assert_eq!(globals.worlds[world].data.players.len(), 2);
+    assert_eq!(globals.user_to_world.get(&guest_user), Some(&world));
+}
+
+#[test]
+fn chop() {
+    let scenario = Scenario::new();
+    let player = Scenario::client(1);
+    let (_, world, position) = scenario.owner_in_world(player);
+    let tree = scenario.spawn(world, ObjectType::Tree(0), position);
+    let before = scenario.globals().worlds[world].data.objects.len();
+
+    let outgoing = scenario.send(player, Packet::RemoveObject(tree));
+    let created = to(&outgoing, player)
+        .into_iter()
+        .filter(|packet| {
+            let wood = ObjectType::GroundItem(ItemStack::one(Item::Wood));
+            matches!(packet, Packet::CreateObject(object) if object.rep == wood)
+        })
+        .count();
+    assert!((2..=3).contains(&created));
+    let globals = scenario.globals();
+    let objects = &globals.worlds[world].data.objects;
+    assert!(!objects.iter().any(|object| object.uuid == tree));
+    assert_eq!(objects.len(), before - 1 + created);
+}
+
+#[test]
+fn player_actions() {
+    let scenario = Scenario::new();
+    let player = Scenario::client(1);
+    let guest = Scenario::client(2);
+    let (user, world, _) = scenario.owner_in_world(player);
+    scenario.create_user(guest, "Guest");
+    scenario.join_world(guest, world);
+
+    // Everyone else sees who used the item
+    let action = ItemAction::Chop(1);
+    let outgoing = scenario.send(player, Packet::ActionAnimation(action));
+    assert_eq!(to(&outgoing, guest), vec![Packet::PlayerAction(user, action)]);
+    assert!(to(&outgoing, player).is_empty());
+
+    // Clients that can't be told who it was aren't sent anything
+    let mut older = ProtocolVersion::current();
+    older.capabilities.retain(|capability| capability != "player_actions");
+    scenario.send(guest, Packet::NettyVersion(older));
+    assert!(scenario.send(player, Packet::ActionAnimation(action)).is_empty());
+}
+
+#[test]
+fn mine() {
+    use crate::shared::mining::Ore;
+
+    let scenario = Scenario::new();
+    let player = Scenario::client(1);
+    let (_, world, position) = scenario.owner_in_world(player);
+    let stone = scenario.spawn(world, ObjectType::Rock(Ore::Stone, 2), position);
+    let iron = scenario.spawn(world, ObjectType::Rock(Ore::Iron, 8), position);
+    let rock = |uuid| {
+        let globals = scenario.globals();
+        let objects = &globals.worlds[world].data.objects;
+        objects.iter().find(|object| object.uuid == uuid).map(|object| object.rep.clone())
+    };
+
+    // Mining needs a pickaxe, and clients can't break rocks themselves
+    assert!(scenario.send(player, Packet::Mine(stone)).is_empty());
+    assert!(scenario.send(player, Packet::RemoveObject(stone)).is_empty());
+    assert_eq!(rock(stone), Some(ObjectType::Rock(Ore::Stone, 2)));
+
+    scenario.globals().worlds[world].data.players[0].2.inventory.hotbar[0] =
+        Some(ItemStack::one(Item::MakeshiftPickaxe));
+    // It has to be the one in hand
+    scenario.send(player, Packet::SelectSlot(1));
+    assert!(scenario.send(player, Packet::Mine(stone)).is_empty());
+    assert_eq!(rock(stone), Some(ObjectType::Rock(Ore::Stone, 2)));
+    scenario.send(player, Packet::SelectSlot(0));
+    let outgoing = scenario.send(player, Packet::Mine(iron));
+    assert!(matches!(to(&outgoing, player).as_slice(), [Packet::ChatMessage(..)]));
+    assert_eq!(rock(iron), Some(ObjectType::Rock(Ore::Iron, 8)));
+
+    scenario.send(player, Packet::Mine(stone));
+    assert_eq!(rock(stone), Some(ObjectType::Rock(Ore::Stone, 1)));
+    let outgoing = scenario.send(player, Packet::Mine(stone));
+    assert_eq!(rock(stone), None);
+    let packets = to(&outgoing, player);
+    assert!(packets.contains(&Packet::RemoveObject(stone)));
+    let dropped = packets
+        .iter()
+        .filter(|packet| {
+            let stone = ObjectType::GroundItem(ItemStack::one(Item::Stone));
+            matches!(packet, Packet::CreateObject(object) if object.rep == stone)
+        })
+        .count();
+    assert!((1..=3).contains(&dropped));
+}
+
+#[test]
+fn pickup() {
+    let scenario = Scenario::new();
+    let player = Scenario::client(1);
+    let (_, world, position) = scenario.owner_in_world(player);
+    let rep = ObjectType::GroundItem(ItemStack::one(Item::Wood));
+    let wood = scenario.spawn(world, rep, position);
+    let before = scenario.globals().worlds[world].data.objects.len();
+
+    let outgoing = scenario.tick();
+    let packets = to(&outgoing, player);
+    assert!(packets.contains(&Packet::RemoveObject(wood)));
+    assert!(packets.iter().any(|packet| {
+        matches!(packet, Packet::InventoryState(inventory) if inventory.contains(Item::Wood))
+    }));
+    assert_eq!(scenario.globals().worlds[world].data.objects.len(), before - 1);
+}
+
+#[test]
+fn whitelist() {
+    let scenario = Scenario::new();
+    let owner = Scenario::client(1);
+    let guest = Scenario::client(2);
+    let (_, world, _) = scenario.owner_in_world(owner);
+    let guest_user = scenario.create_user(guest, "Guest");
+
+    // Users that don't exist can't be whitelisted
+    let outgoing = scenario.send(
+        owner,
+        Packet::WhitelistUser(User {
+            username: String::from("Nobody"),
+            tag: 1,
+        }),
+    );
+    assert_eq!(to(&outgoing, owner), vec![Packet::UnwhitelistableUser]);
+
+    let outgoing = scenario.send(owner, Packet::WhitelistUser(guest_user.clone()));
+    assert_eq!(to(&outgoing, owner), vec![Packet::Whitelisted]);
+    assert!(scenario.globals().worlds[world].whitelist.contains(&guest_user));
+    // The world is listed for the guest now
+    let outgoing = scenario.send(guest, Packet::AvalableServers);
+    assert!(to(&outgoing, guest).iter().any(|packet| match packet {
+        Packet::ServerList(list) => list.iter().any(|listing| listing.internal_id == world),
+        _ => false,
+    }));
+
+    // Only the owner can whitelist
+    scenario.join_world(guest, world);
+    let outgoing = scenario.send(guest, Packet::WhitelistUser(guest_user));
+    assert_eq!(to(&outgoing, guest), vec![Packet::NoWhitelistPermission]);
+}
+
+#[test]
+fn full_world() {
+    let scenario = Scenario::new();
+    let owner = Scenario::client(1);
+    let guest = Scenario::client(2);
+    let (owner_user, world, _) = scenario.owner_in_world(owner);
+    let guest_user = scenario.create_user(guest, "Guest");
+    scenario.send(owner, Packet::WhitelistUser(guest_user.clone()));
+    scenario.globals().worlds[world].max_players = 1;
+
+    let outgoing = scenario.send(guest, Packet::JoinWorld(world));
+    assert!(matches!(to(&outgoing, guest).as_slice(), [Packet::JoinRefused(..)]));
+    assert!(to(&outgoing, owner).is_empty());
+    // The guest is still on the title screen, as far as the server knows
+    let globals = scenario.globals();
+    assert!(!globals.user_to_world.contains_key(&guest_user));
+    assert_eq!(globals.user_to_world.get(&owner_user), Some(&world));
+    let players = &globals.worlds[world].data;
+    assert!(!players.players.iter().chain(&players.offline_players).any(|p| p.0 == guest_user));
+}
+
+#[test]
+fn lan_profiles() {
+    let scenario = Scenario::new();
+    let visitor = Scenario::client(1);
+    let user = User {
+        username: String::from("Visitor"),
+        tag: 7,
+    };
+    scenario.send(visitor, Packet::NettyVersion(ProtocolVersion::current()));
+
+    // Unknown users don't get a profile unless the server allows it
+    scenario.send(visitor, Packet::UserPresence(user.clone()));
+    assert!(!scenario.globals().profiles.iter().any(|profile| profile.user == user));
+
+    scenario.globals().config.lan_profiles = true;
+    scenario.send(visitor, Packet::UserPresence(user.clone()));
+    assert!(scenario.globals().profiles.iter().any(|profile| profile.user == user));
+    // Their tag is taken for anyone made here afterwards
+    let local = scenario.create_user(Scenario::client(2), "Visitor");
+    assert_eq!(local.tag, 8);
+}
+
+#[test]
+fn flood() {
+    let scenario = Scenario::new();
+    let player = Scenario::client(1);
+    let (user, _, _) = scenario.owner_in_world(player);
+    let mut limits = RateLimits::default();
+    limits.other.per_second = 1.0e-3;
+    limits.other.burst = 1.0;
+    limits.warn_after = 2;
+    limits.disconnect_after = 3;
+    scenario.globals().config.rate_limits = limits;
+
+    assert!(!scenario.send(player, Packet::AvalableServers).is_empty());
+    assert!(scenario.send(player, Packet::AvalableServers).is_empty());
+    let outgoing = scenario.send(player, Packet::AvalableServers);
+    assert!(matches!(to(&outgoing, player).as_slice(), [Packet::ChatMessage(..)]));
+    let outgoing = scenario.send(player, Packet::AvalableServers);
+    assert!(matches!(to(&outgoing, player).as_slice(), [Packet::Kicked(..)]));
+    assert!(!scenario.globals().user_to_world.contains_key(&user));
+
+    // Flooders don't get a fresh start after being removed
+    for _ in 0..10 {
+        assert!(scenario.send(player, Packet::AvalableServers).is_empty());
+    }
+}
+
+#[test]
+fn timeout() {
+    let scenario = Scenario::new();
+    let owner = Scenario::client(1);
+    let guest = Scenario::client(2);
+    let (_, world, _) = scenario.owner_in_world(owner);
+    let guest_user = scenario.create_user(guest, "Guest");
+    scenario.join_world(guest, world);
+
+    // Keepalives count as hearing from a client
+    scenario.send(guest, Packet::KeepAlive);
+    scenario.tick();
+    assert!(scenario.globals().user_to_world.contains_key(&guest_user));
+    let silent = std::time::Instant::now().checked_sub(CONNECTION_TIMEOUT * 2).unwrap();
+    scenario.globals().last_heard.insert(guest, silent);
+    let outgoing = scenario.tick();
+    assert!(to(&outgoing, owner).contains(&Packet::PlayerDisconnected(guest_user.clone())));
+    let globals = scenario.globals();
+    assert!(!globals.user_to_world.contains_key(&guest_user));
+    assert!(!globals.addr_to_user.contains_key(&guest));
+}
+
+#[test]
+fn broken_clients() {
+    let scenario = Scenario::new();
+    let owner = Scenario::client(1);
+    let stranger = Scenario::client(2);
+    let (owner_user, world, position) = scenario.owner_in_world(owner);
+
+    // Packets from clients that haven't announced themselves are ignored
+    scenario.send(stranger, Packet::NettyVersion(ProtocolVersion::current()));
+    assert!(scenario.send(stranger, Packet::JoinWorld(world)).is_empty());
+    assert!(scenario.send(stranger, Packet::RequestChunk((0, 0))).is_empty());
+    assert!(scenario.send(stranger, Packet::LeaveWorld).is_empty());
+    // As are packets only the server sends, and worlds that don't exist
+    assert!(scenario.send(owner, Packet::Whitelisted).is_empty());
+    assert!(scenario.send(owner, Packet::JoinWorld(world + 100)).is_empty());
+
+    // A dropped connection is ignored, until a new one announces itself
+    scenario.send(stranger, Packet::FailedDeserialize);
+    assert!(scenario.send(stranger, Packet::AvalableServers).is_empty());
+    let outgoing = scenario.send(stranger, Packet::NettyVersion(ProtocolVersion::current()));
+    assert!(matches!(to(&outgoing, stranger).as_slice(), [Packet::AllSet(..)]));
+    assert!(!scenario.globals().dropped_addrs.contains(&stranger));
+
+    // Players whose address is missing are simulated without being sent anything
+    scenario.globals().user_to_addr.remove(&owner_user);
+    scenario.spawn(world, ObjectType::GroundItem(ItemStack::one(Item::Wood)), position);
+    assert!(to(&scenario.tick(), owner).is_empty());
+    let inventory = scenario.globals().worlds[world].data.players[0].2.inventory.clone();
+    assert_eq!(inventory.count(Item::Wood), 1);
+}
+
+#[test]
+fn magnet_ignores_tick_rate() {
+    let mut positions = vec![];
+    for tick_rate in [10, 20, 60] {
+        let scenario = Scenario::new();
+        scenario.globals().config.tick_rate = tick_rate;
+        let player = Scenario::client(1);
+        let (_, world, position) = scenario.owner_in_world(player);
+        let mut start = position;
+        start.translation.x += 200.0;
+        let rep = ObjectType::GroundItem(ItemStack::one(Item::Wood));
+        let wood = scenario.spawn(world, rep, start);
+
+        scenario.wait(Duration::from_millis(500));
+        let globals = scenario.globals();
+        assert!((globals.worlds[world].clock - 0.5).abs() < 1.0e-6);
+        let object = globals.worlds[world].data.objects.iter().find(|object| object.uuid == wood);
+        let x = object.expect("The wood was picked up too soon").pos.translation.x;
+        positions.push(x - position.translation.x);
+    }
+    // Finer steps follow the curve more closely, but only slightly
+    assert!(positions.iter().all(|x| (x - positions[1]).abs() < 0.1), "{positions:?}");
+    assert!(positions[1] < 199.0);
+}
+
+#[test]
+fn weather() {
+    let scenario = Scenario::new();
+    let player = Scenario::client(1);
+    let (_, world, _) = scenario.owner_in_world(player);
+
+    let outgoing = scenario.chat(player, "/weather storm");
+    assert!(to(&outgoing, player).contains(&Packet::Weather(Weather::Storm)));
+    assert_eq!(scenario.globals().worlds[world].weather, Weather::Storm);
+
+    // Storms always clear up once they've run their course
+    let next = scenario.globals().worlds[world].next_weather;
+    let mut outgoing = vec![];
+    while scenario.globals().worlds[world].clock <= next {
+        outgoing.append(&mut scenario.wait(Duration::from_secs(1)));
+    }
+    let weather = scenario.globals().worlds[world].weather;
+    assert_ne!(weather, Weather::Storm);
+    assert!(to(&outgoing, player).contains(&Packet::Weather(weather)));
+}
+
+#[test]
+fn teleport() {
+    let scenario = Scenario::new();
+    let player = Scenario::client(1);
+    let (_, world, _) = scenario.owner_in_world(player);
+    let position = || scenario.globals().worlds[world].data.players[0].1.translation;
+
+    scenario.chat(player, "/tp 64 -32");
+    assert_eq!(position(), Vec3::new(64.0, -32.0, 0.0));
+    // Coordinates have to be actual numbers
+    for command in ["/tp NaN 0", "/tp 0 inf", "/tp -inf -inf"] {
+        let outgoing = scenario.chat(player, command);
+        assert!(!to(&outgoing, player).iter().any(|packet| {
+            matches!(packet, Packet::PlayerPositionUpdate(..))
+        }));
+        assert_eq!(position(), Vec3::new(64.0, -32.0, 0.0));
+    }
+}
+
+#[test]
+fn farming() {
+    use crate::shared::farming::{Crop, Farmland};
+
+    let scenario = Scenario::new();
+    let player = Scenario::client(1);
+    let (_, world, position) = scenario.owner_in_world(player);
+    let (chunk, tile) = tile_at(position);
+    let centre = tile_centre(chunk, tile);
+    {
+        let mut globals = scenario.globals();
+        // Rain would water the crop for us
+        globals.worlds[world].weather = Weather::Clear;
+        globals.worlds[world].next_weather = f64::MAX;
+        let data = &mut globals.worlds[world].data;
+        data.modify_tile(chunk, tile, TERRAIN_GRASS);
+        data.objects.retain(|object| distance(object.pos, centre) >= 32.0);
+        let hotbar = &mut data.players[0].2.inventory.hotbar;
+        hotbar[0] = Some(ItemStack::one(Item::MakeshiftHoe));
+        hotbar[1] = Some(ItemStack::one(Item::WateringCan));
+        hotbar[2] = Some(ItemStack::one(Item::WheatSeeds));
+    }
+    let farmland = |scenario: &Scenario| {
+        let globals = scenario.globals();
+        globals.worlds[world]
+            .data
+            .objects
+            .iter()
+            .find_map(|object| match object.rep {
+                ObjectType::Farmland(farmland) => Some(farmland),
+                _ => None,
+            })
+    };
+
+    // Seeds need tilled ground
+    scenario.send(player, Packet::Plant(position, Crop::Wheat));
+    assert_eq!(farmland(&scenario), None);
+    scenario.send(player, Packet::Dig(position));
+    assert_eq!(farmland(&scenario), Some(Farmland::tilled()));
+    scenario.send(player, Packet::Plant(position, Crop::Wheat));
+    assert_eq!(farmland(&scenario).unwrap().crop, Some(Crop::Wheat));
+    assert_eq!(scenario.globals().worlds[world].data.players[0].2.inventory.hotbar[2], None);
+
+    // Tools only work while they're held
+    scenario.send(player, Packet::Water(position));
+    assert!(!farmland(&scenario).unwrap().watered);
+    scenario.send(player, Packet::SelectSlot(1));
+
+    // Crops only grow while watered, and dry out at each stage
+    for stage in 1..Crop::Wheat.stages() {
+        scenario.wait(Duration::from_secs_f32(Crop::Wheat.stage_length() / 2.0));
+        scenario.send(player, Packet::Water(position));
+        while farmland(&scenario).unwrap().stage < stage {
+            scenario.wait(Duration::from_secs(1));
+        }
+        assert!(!farmland(&scenario).unwrap().watered);
+    }
+    assert!(farmland(&scenario).unwrap().ripe());
+
+    scenario.send(player, Packet::SelectSlot(0));
+    let outgoing = scenario.send(player, Packet::Dig(position));
+    assert_eq!(farmland(&scenario), Some(Farmland::tilled()));
+    assert!(to(&outgoing, player).iter().any(|packet| match packet {
+        Packet::InventoryState(inventory) => inventory.contains(Item::Wheat),
+        _ => false,
+    }));
+}
+
+#[test]
+fn fishing() {
+    let scenario = Scenario::new();
+    let player = Scenario::client(1);
+    let (_, world, position) = scenario.owner_in_world(player);
+    let mut target = position;
+    target.translation.x += 128.0;
+    let (chunk, tile) = tile_at(target);
+    {
+        let mut globals = scenario.globals();
+        // Storms would make bites slower
+        globals.worlds[world].weather = Weather::Clear;
+        globals.worlds[world].next_weather = f64::MAX;
+        globals.worlds[world].data.modify_tile(chunk, tile, TERRAIN_GRASS);
+        globals.worlds[world].data.players[0].2.inventory.hotbar[0] =
+            Some(ItemStack::one(Item::MakeshiftFishingRod));
+    }
+    let wait_for_bite = |scenario: &Scenario| {
+        for _ in 0..200 {
+            let outgoing = scenario.wait(Duration::from_millis(100));
+            if to(&outgoing, player).contains(&Packet::Bite) {
+                return;
+            }
+        }
+        panic!("Nothing bit");
+    };
+
+    // Only water can be fished in
+    assert!(to(&scenario.send(player, Packet::Cast(target)), player).is_empty());
+    scenario.globals().worlds[world].data.modify_tile(chunk, tile, TERRAIN_WATER);
+    // And only with the rod in hand
+    scenario.send(player, Packet::SelectSlot(1));
+    assert!(to(&scenario.send(player, Packet::Cast(target)), player).is_empty());
+    scenario.send(player, Packet::SelectSlot(0));
+    let outgoing = scenario.send(player, Packet::Cast(target));
+    assert_eq!(to(&outgoing, player), vec![Packet::Bobber(target)]);
+
+    // Reeling in before a bite catches nothing
+    let outgoing = scenario.send(player, Packet::Reel);
+    assert_eq!(to(&outgoing, player), vec![Packet::Caught(None)]);
+
+    // Reeling in too late catches nothing either
+    scenario.send(player, Packet::Cast(target));
+    wait_for_bite(&scenario);
+    let window = Duration::from_secs_f32(FISH_BITE_WINDOW + 0.5);
+    assert!(to(&scenario.wait(window), player).contains(&Packet::Caught(None)));
+    assert!(scenario.send(player, Packet::Reel).is_empty());
+
+    scenario.send(player, Packet::Cast(target));
+    wait_for_bite(&scenario);
+    let outgoing = scenario.send(player, Packet::Reel);
+    let caught = match to(&outgoing, player).first() {
+        Some(Packet::Caught(Some(item))) => *item,
+        other => panic!("Expected a catch, got {other:?}"),
+    };
+    assert!(scenario.globals().worlds[world].data.players[0].2.inventory.contains(caught));
+}
+
+#[test]
+fn craft() {
+    use crate::shared::crafting::Station;
+    use crate::shared::player::Recipe;
+
+    let scenario = Scenario::new();
+    let player = Scenario::client(1);
+    let (_, world, position) = scenario.owner_in_world(player);
+    scenario.globals().worlds[world]
+        .data
+        .objects
+        .retain(|object| !matches!(object.rep, ObjectType::Station(..)));
+    let give = |item: Item, count: usize| {
+        let mut globals = scenario.globals();
+        let inventory = &mut globals.worlds[world].data.players[0].2.inventory;
+        *inventory = Inventory::empty();
+        inventory.add(item, count);
+    };
+    let hotbar = || scenario.globals().worlds[world].data.players[0].2.inventory.hotbar;
+    let axe = Recipe(String::from("makeshift_axe"));
+    let seeds = Recipe(String::from("wheat_seeds"));
+
+    // Recipes without conditions are known from the start, but still cost something
+    assert!(scenario.send(player, Packet::Craft(axe.clone())).is_empty());
+    give(Item::Wood, 3);
+    let outgoing = scenario.send(player, Packet::Craft(axe.clone()));
+    assert!(matches!(to(&outgoing, player).as_slice(), [Packet::InventoryState(..)]));
+    assert_eq!(hotbar()[0], Some(ItemStack::one(Item::MakeshiftAxe)));
+    assert_eq!(hotbar().iter().flatten().count(), 1);
+    assert_eq!(scenario.globals().worlds[world].data.players[0].2.stats.crafts, 1);
+
+    // Picking up wheat unlocks making seeds from it, but only at a workbench
+    scenario.globals().worlds[world].data.players[0].2.inventory = Inventory::empty();
+    assert!(scenario.send(player, Packet::Craft(seeds.clone())).is_empty());
+    scenario.spawn(world, ObjectType::GroundItem(ItemStack::one(Item::Wheat)), position);
+    let outgoing = scenario.tick();
+    assert!(to(&outgoing, player).iter().any(|packet| {
+        matches!(packet, Packet::UnlockedRecipes(unlocked) if unlocked.contains(&seeds))
+    }));
+    let outgoing = scenario.send(player, Packet::Craft(seeds.clone()));
+    assert!(matches!(to(&outgoing, player).as_slice(), [Packet::ChatMessage(..)]));
+    assert_eq!(hotbar()[0], Some(ItemStack::one(Item::Wheat)));
+    scenario.spawn(world, ObjectType::Station(Station::Workbench), position);
+    scenario.send(player, Packet::Craft(seeds));
+    assert_eq!(hotbar()[0], Some(ItemStack::new(Item::WheatSeeds, 2)));
+
+    // Crafting enough raises the player's crafting level
+    scenario.globals().worlds[world].data.players[0].2.stats.crafts = CRAFTS_PER_LEVEL - 1;
+    give(Item::Wood, 3);
+    let outgoing = scenario.send(player, Packet::Craft(axe));
+    assert!(to(&outgoing, player).iter().any(|packet| matches!(packet, Packet::ChatMessage(..))));
+    assert_eq!(scenario.globals().worlds[world].data.players[0].2.stats.crafting, 2);
+}
+
+#[test]
+fn stacks() {
+    let scenario = Scenario::new();
+    let player = Scenario::client(1);
+    let (_, world, position) = scenario.owner_in_world(player);
+    let inventory = || scenario.globals().worlds[world].data.players[0].2.inventory.clone();
+    scenario.globals().worlds[world].data.players[0].2.inventory.hotbar[0] =
+        Some(ItemStack::new(Item::Wood, 5));
+
+    // Picked up items go on top of what's already carried
+    scenario.spawn(world, ObjectType::GroundItem(ItemStack::one(Item::Wood)), position);
+    scenario.tick();
+    assert_eq!(inventory().hotbar[0], Some(ItemStack::new(Item::Wood, 6)));
+    assert_eq!(inventory().slots().flatten().count(), 1);
+
+    // Backpack slots are numbered after the hotbar
+    let outgoing = scenario.send(player, Packet::SplitStack(0, HOTBAR_SIZE + 2));
+    assert!(matches!(to(&outgoing, player).as_slice(), [Packet::InventoryState(..)]));
+    assert_eq!(inventory().hotbar[0], Some(ItemStack::new(Item::Wood, 3)));
+    assert_eq!(inventory().backpack[2], Some(ItemStack::new(Item::Wood, 3)));
+    scenario.send(player, Packet::MoveStack(HOTBAR_SIZE + 2, 0));
+    assert_eq!(inventory().hotbar[0], Some(ItemStack::new(Item::Wood, 6)));
+    assert_eq!(inventory().backpack[2], None);
+
+    // Different items swap, and stacks never go over their limit
+    scenario.globals().worlds[world].data.players[0].2.inventory.hotbar[1] =
+        Some(ItemStack::new(Item::Wood, Item::Wood.max_stack()));
+    scenario.send(player, Packet::MoveStack(0, 1));
+    assert_eq!(inventory().hotbar[0], Some(ItemStack::new(Item::Wood, 6)));
+    assert_eq!(inventory().count(Item::Wood), 6 + Item::Wood.max_stack());
+    scenario.globals().worlds[world].data.players[0].2.inventory.hotbar[1] =
+        Some(ItemStack::one(Item::MakeshiftAxe));
+    scenario.send(player, Packet::MoveStack(0, 1));
+    assert_eq!(inventory().hotbar[0], Some(ItemStack::one(Item::MakeshiftAxe)));
+    assert_eq!(inventory().hotbar[1], Some(ItemStack::new(Item::Wood, 6)));
+
+    // Moves that aren't possible change nothing, but the client is corrected
+    let before = inventory();
+    let outgoing = scenario.send(player, Packet::MoveStack(0, HOTBAR_SIZE + BACKPACK_SIZE));
+    assert_eq!(to(&outgoing, player), vec![Packet::InventoryState(before.clone())]);
+    scenario.send(player, Packet::SplitStack(0, 2));
+    assert_eq!(inventory(), before);
+}
+
+#[test]
+fn drop() {
+    let scenario = Scenario::new();
+    let player = Scenario::client(1);
+    let guest = Scenario::client(2);
+    let (_, world, position) = scenario.owner_in_world(player);
+    scenario.create_user(guest, "Guest");
+    scenario.join_world(guest, world);
+    let inventory = || scenario.globals().worlds[world].data.players[0].2.inventory.clone();
+    scenario.globals().worlds[world].data.players[0].2.inventory.hotbar[0] =
+        Some(ItemStack::new(Item::Wood, 5));
+
+    // Throws land no further than a player can throw, and everyone sees them
+    let mut far = position;
+    far.translation.x += ITEM_THROW_DISTANCE * 10.0;
+    let outgoing = scenario.send(player, Packet::DropItems(0, 2, far));
+    assert!(to(&outgoing, player).contains(&Packet::InventoryState(inventory())));
+    assert_eq!(inventory().hotbar[0], Some(ItemStack::new(Item::Wood, 3)));
+    let dropped = to(&outgoing, guest)
+        .into_iter()
+        .find_map(|packet| match packet {
+            Packet::CreateObject(object) => Some(object),
+            _ => None,
+        })
+        .expect("The drop wasn't broadcast");
+    assert_eq!(dropped.rep, ObjectType::GroundItem(ItemStack::new(Item::Wood, 2)));
+    assert!((distance(dropped.pos, position) - ITEM_THROW_DISTANCE).abs() < 1.0e-3);
+
+    // Throws towards nowhere in particular are refused
+    let mut nowhere = position;
+    nowhere.translation.x = f32::NAN;
+    let outgoing = scenario.send(player, Packet::DropItems(0, 1, nowhere));
+    assert_eq!(to(&outgoing, player), vec![Packet::InventoryState(inventory())]);
+    assert!(to(&outgoing, guest).is_empty());
+    nowhere.translation.x = f32::INFINITY;
+    scenario.send(player, Packet::DropItems(0, 1, nowhere));
+    assert_eq!(inventory().hotbar[0], Some(ItemStack::new(Item::Wood, 3)));
+
+    // Items dropped at the player's feet aren't picked straight back up
+    let outgoing = scenario.send(player, Packet::DropItems(0, 10, position));
+    assert_eq!(inventory().hotbar[0], None);
+    let at_feet = to(&outgoing, player)
+        .into_iter()
+        .find_map(|packet| match packet {
+            Packet::CreateObject(object) => Some(object.uuid),
+            _ => None,
+        })
+        .expect("Nothing was dropped");
+    scenario.tick();
+    assert_eq!(inventory().count(Item::Wood), 0);
+
+    // Empty slots have nothing to drop
+    let outgoing = scenario.send(player, Packet::DropItems(0, 1, position));
+    assert_eq!(to(&outgoing, player), vec![Packet::InventoryState(inventory())]);
+
+    // Once the cooldown is over, they can be picked up again
+    let mut outgoing = vec![];
+    for _ in 0..((ITEM_DROP_COOLDOWN + 2.0) as usize) {
+        outgoing.append(&mut scenario.wait(Duration::from_secs(1)));
+    }
+    assert!(to(&outgoing, guest).contains(&Packet::RemoveObject(at_feet)));
+    assert_eq!(inventory().count(Item::Wood), 3);
+    assert!(scenario.globals().worlds[world].pickup_after.is_empty());
+}
+
+#[test]
+fn replay() {
+    use super::replay::replay_capture;
+    use crate::shared::capture::{read_capture, Direction};
+
+    let dir = Scenario::data_dir();
+    let capture = dir.join("capture.bin");
+    let config = serde_json::json!({ "capture_file": capture });
+    std::fs::write(dir.join("server_config.json"), config.to_string())
+        .expect("Unable to write a config.");
+    let scenario = Scenario::load(dir);
+    let owner = Scenario::client(1);
+    let guest = Scenario::client(2);
+    let (_, world, position) = scenario.owner_in_world(owner);
+    scenario.create_user(guest, "Guest");
+    scenario.chat(owner, "/weather storm");
+    scenario.chat(owner, "/give MakeshiftHoe");
+    scenario.send(owner, Packet::Dig(position));
+    scenario.join_world(guest, world);
+    // Long enough for the weather to change at least once
+    for _ in 0..WEATHER_MAX_LENGTH / 4 {
+        scenario.wait(Duration::from_secs(4));
+    }
+    scenario.chat(guest, "Still here");
+
+    let (header, packets) = read_capture(&capture).expect("Unable to read the capture");
+    let sent: Vec<_> = packets
+        .iter()
+        .filter(|captured| captured.direction == Direction::Sent)
+        .map(|captured| (captured.packet.clone(), captured.peer))
+        .collect();
+    assert!(sent.iter().any(|(packet, _)| matches!(packet, Packet::Weather(_))));
+    let replay = Scenario::new();
+    let replayed = replay_capture(replay.globals.clone(), &header, &packets);
+    // Compared as they're sent, since chat messages carry when they arrived
+    let encode = |outgoing: &[(Packet, SocketAddr)]| -> Vec<Vec<u8>> {
+        outgoing
+            .iter()
+            .map(|sent| bincode::serialize(sent).expect("Unable to encode a packet"))
+            .collect()
+    };
+    assert_eq!(encode(&replayed), encode(&sent));
+}
@@ -1,135 +1,216 @@
-use super::{Profile, SaveGame};
+use super::{farming, save_format, Profile, SaveGame, SHARED_GLOBALS};
 use crate::prelude::*;
+use bevy::utils::HashMap;
 use std::net::SocketAddr;
+use std::time::Duration;
 
 use std::sync::{Arc, Mutex};
 
-#[allow(non_snake_case)]
+/// Runs the server, catching the simulation up with however much real time
+/// has passed since the last call.
 pub fn tick(globals: Arc<Mutex<Globals>>) -> Vec<(Packet, SocketAddr)> {
+    SHARED_GLOBALS.get_or_init(|| globals.clone());
+    let elapsed = {
+        let mut glob_access = globals.lock().unwrap();
+        let elapsed = glob_access.last_tick.elapsed();
+        glob_access.last_tick = std::time::Instant::now();
+        elapsed
+    };
+    advance(globals, elapsed)
+}
+
+/// Runs the server as if `elapsed` time had passed. Worlds are simulated in
+/// fixed steps of `Config::tick_length`, and time left over is carried into
+/// the next call.
+pub fn advance(globals: Arc<Mutex<Globals>>, elapsed: Duration) -> Vec<(Packet, SocketAddr)> {
+    let started = std::time::Instant::now();
     let mut outgoing: Vec<(Packet, SocketAddr)> = vec![];
     let mut glob_access = globals.lock().unwrap();
+    outgoing.append(&mut glob_access.queued_packets);
+    outgoing.append(&mut glob_access.disconnect_timed_out());
     if glob_access.last_autosave.elapsed() > AUTOSAVE_FREQUENCY {
-        glob_access.last_autosave = std::time::Instant::now();
-        info!("Saving worlds and profiles");
-        for world in glob_access.worlds.clone() {
-            save_world(world);
+        glob_access.save_all();
+    }
+    if glob_access.metrics.report_due(&glob_access.config) {
+        glob_access.log_metrics();
+    }
+    let step = glob_access.config.tick_length();
+    glob_access.uptime += elapsed;
+    glob_access.unsimulated += elapsed;
+    let mut steps = 0;
+    while glob_access.unsimulated >= step {
+        if steps == MAX_CATCH_UP_STEPS {
+            warn!("The server fell {:?} behind, skipping ahead", glob_access.unsimulated);
+            glob_access.unsimulated = Duration::ZERO;
+            break;
         }
-        for profile in glob_access.profiles.clone() {
-            save_profile(profile);
+        glob_access.unsimulated -= step;
+        steps += 1;
+        let ips = glob_access.user_to_addr.clone();
+        for world in 0..glob_access.worlds.len() {
+            let access = &mut *glob_access;
+            let server = &mut access.worlds[world];
+            let syncs = (server.clock / CLOCK_SYNC_INTERVAL).floor();
+            server.clock += step.as_secs_f64();
+            let sync_due = (server.clock / CLOCK_SYNC_INTERVAL).floor() != syncs;
+            let mut stepped = step_world(server, &ips, step.as_secs_f32());
+            // Players who picked something up might unlock recipes with it
+            let picked_up: Vec<SocketAddr> = stepped
+                .iter()
+                .filter(|(packet, _)| matches!(packet, Packet::InventoryState(_)))
+                .map(|(_, addr)| *addr)
+                .collect();
+            outgoing.append(&mut stepped);
+            let new_weather = server.update_weather(&mut access.rng);
+            outgoing.append(&mut glob_access.update_unlocks(&picked_up));
+            if sync_due {
+                outgoing.append(&mut glob_access.clock_packets(world));
+            }
+            if let Some(weather) = new_weather {
+                let packet = Packet::Weather(weather);
+                outgoing.append(&mut glob_access.capable_world_packets(world, "weather", packet));
+            }
         }
-        info!("Done saving");
+        outgoing.append(&mut glob_access.update_casts());
     }
-    // For every world...
-    let ips = glob_access.user_to_addr.clone();
-    for server in &mut glob_access.worlds {
-        let mut removed = 0;
-        // For every object...
-        'object: for (object_index, object) in server.data.objects.clone().iter().enumerate() {
-            // If the object is an item...
-            if let ObjectType::GroundItem(item) = object.rep {
-                let server_players = &server.data.players;
-                // Item pickup
-                // For every player...
-                for (index, (_user, pos, data)) in server_players.iter().enumerate() {
-                    // If they are in pickup distance...
-                    if distance(object.pos, *pos) < ITEM_PICKUP_DISTANCE {
-                        // And have avalable hotbar space...
-                        if let Some(slot) = data.inventory.hotbar_empty_space() {
-                            // Remove entity from every player
-                            for player in server_players {
-                                outgoing.push((
-                                    Packet::RemoveObject(object.uuid),
-                                    *ips.get(&player.0)
-                                        .expect("No IP found for a user connected to a server"),
-                                ));
+    glob_access.metrics.record_outgoing(&outgoing);
+    glob_access.metrics.record_tick(started.elapsed());
+    glob_access.capture(super::Direction::Sent, &outgoing);
+    outgoing
+}
+
+/// Simulates `dt` seconds of one world.
+#[allow(non_snake_case)]
+fn step_world(
+    server: &mut SaveGame,
+    ips: &HashMap<User, SocketAddr>,
+    dt: f32,
+) -> Vec<(Packet, SocketAddr)> {
+    let mut outgoing = vec![];
+    let mut removed = 0;
+    let clock = server.clock;
+    server.pickup_after.retain(|_, after| *after > clock);
+    // Anyone without an address is skipped, rather than panicking with the lock held
+    let addrs: Vec<SocketAddr> =
+        server.data.players.iter().filter_map(|player| ips.get(&player.0).copied()).collect();
+    // For every object...
+    'object: for (object_index, object) in server.data.objects.clone().iter().enumerate() {
+        // If the object is an item that wasn't just dropped...
+        if let ObjectType::GroundItem(stack) = object.rep {
+            if server.pickup_after.contains_key(&object.uuid) {
+                continue;
+            }
+            let item = stack.item;
+            let server_players = &server.data.players;
+            // Item pickup
+            // For every player...
+            for (index, (_user, pos, data)) in server_players.iter().enumerate() {
+                // If they are in pickup distance...
+                if distance(object.pos, *pos) < ITEM_PICKUP_DISTANCE {
+                    // And have room for it...
+                    if data.inventory.fits(item) {
+                        // Add items to inventory, on top of any stack of them
+                        let left = server.data.players[index].2.inventory.add(item, stack.count);
+                        // Tell user they have new items
+                        let (user, _, data) = &server.data.players[index];
+                        if let Some(addr) = ips.get(user) {
+                            outgoing.push((Packet::InventoryState(data.inventory.clone()), *addr));
+                        }
+                        // If some didn't fit, leave them on the ground
+                        if left > 0 {
+                            let mut new_object = object.clone();
+                            new_object.rep = ObjectType::GroundItem(ItemStack::new(item, left));
+                            for addr in &addrs {
+                                outgoing.push((Packet::UpdateObject(new_object.clone()), *addr));
                             }
-                            // Add item to hotbar
-                            server.data.players[index].2.inventory.hotbar[slot] = Some(item);
-                            // Tell user they have a new item
-                            outgoing.push((
-                                Packet::InventoryState(
-                                    server.data.players[index].2.inventory.clone(),
-                                ),
-                                *ips.get(&server.data.players[index].0)
-                                    .expect("No IP found for a user connected to a server"),
-                            ));
-                            // Remove entity from server data
-                            server.data.objects.remove(object_index - removed);
-                            removed += 1;
+                            server.data.objects[object_index - removed] = new_object;
                             continue 'object;
                         }
+                        // Remove entity from every player
+                        for addr in &addrs {
+                            outgoing.push((Packet::RemoveObject(object.uuid), *addr));
+                        }
+                        // Remove entity from server data
+                        server.data.objects.remove(object_index - removed);
+                        removed += 1;
+                        continue 'object;
                     }
                 }
-                // Item magnet
-                // reinit for new ref
-                let server_players = &server.data.players;
-                // If not picked up, for every player...
-                for (_user, pos, data) in server_players.iter() {
-                    // If they are in magnet distance...
-                    if distance(object.pos, *pos) < ITEM_MAGNET_DISTANCE {
-                        // And have avalable hotbar space...
-                        if let Some(_slot) = data.inventory.hotbar_empty_space() {
-                            // dtotal=√((x_2-x_1)²+(y_2-y_1)²)
-                            let dx = pos.translation.x - object.pos.translation.x;
-                            let dy = pos.translation.y - object.pos.translation.y;
-                            let dtotal = ((dx.powi(2)) + (dy.powi(2))).sqrt();
-                            let Δ = 64.0 / (((dtotal.powi(2)) + 100.0).sqrt());
-                            let Δx = Δ * (dx / dtotal);
-                            let Δy = Δ * (dy / dtotal);
-                            let new_pos = Transform::from_xyz(
-                                object.pos.translation.x + Δx,
-                                object.pos.translation.y + Δy,
-                                0.0,
-                            );
-                            let mut new_object = object.clone();
-                            new_object.pos = new_pos;
-                            // Update entity for every player
-                            for player in server_players {
-                                outgoing.push((
-                                    Packet::UpdateObject(new_object.clone()),
-                                    *ips.get(&player.0)
-                                        .expect("No IP found for a user connected to a server"),
-                                ));
-                            }
-                            // Update entity on the server side
-                            server.data.objects[object_index - removed].pos = new_pos;
-                            break;
+            }
+            // Item magnet
+            // reinit for new ref
+            let server_players = &server.data.players;
+            // If not picked up, for every player...
+            for (_user, pos, data) in server_players.iter() {
+                // If they are in magnet distance...
+                if distance(object.pos, *pos) < ITEM_MAGNET_DISTANCE {
+                    // And have room for it...
+                    if data.inventory.fits(item) {
+                        // dtotal=√((x_2-x_1)²+(y_2-y_1)²)
+                        let dx = pos.translation.x - object.pos.translation.x;
+                        let dy = pos.translation.y - object.pos.translation.y;
+                        let dtotal = ((dx.powi(2)) + (dy.powi(2))).sqrt();
+                        let Δ = (ITEM_MAGNET_STRENGTH / (((dtotal.powi(2)) + 100.0).sqrt()) * dt)
+                            .min(dtotal);
+                        let Δx = Δ * (dx / dtotal);
+                        let Δy = Δ * (dy / dtotal);
+                        let new_pos = Transform::from_xyz(
+                            object.pos.translation.x + Δx,
+                            object.pos.translation.y + Δy,
+                            0.0,
+                        );
+                        let mut new_object = object.clone();
+                        new_object.pos = new_pos;
+                        // Update entity for every player
+                        for addr in &addrs {
+                            outgoing.push((Packet::UpdateObject(new_object.clone()), *addr));
                         }
+                        // Update entity on the server side
+                        server.data.objects[object_index - removed].pos = new_pos;
+                        break;
                     }
                 }
             }
         }
     }
+    // Crops
+    let waters = server.weather.waters_crops();
+    for object in farming::grow_crops(&mut server.data.objects, waters, dt) {
+        for addr in &addrs {
+            outgoing.push((Packet::UpdateObject(object.clone()), *addr));
+        }
+    }
     outgoing
 }
 
-fn save_world(save: SaveGame) {
-    let enc = bincode::serialize(&save).expect("Unable to serialize a SaveGame.");
+pub fn save_world(save: SaveGame) {
+    let enc = save_format::encode(&save);
     std::fs::write(save.path, enc).expect("Unable to write a SaveGame to disk.");
 }
 
-pub fn save_folder() -> std::path::PathBuf {
-    let mut dir = std::env::current_dir().expect("Unable to access the current directory.");
+/// Returns a `PathBuf` to the folder in `data_dir` used for storing worlds.
+pub fn save_folder(data_dir: &std::path::Path) -> std::path::PathBuf {
+    let mut dir = data_dir.to_path_buf();
     dir.push("saves");
     std::fs::create_dir_all(dir.clone()).expect("Unable to create required directories.");
     dir
 }
 
-/// Returns a `PathBuf` to the folder used for storing profiles.
-pub fn profile_folder() -> std::path::PathBuf {
-    let mut dir = std::env::current_dir().expect("Unable to access the current directory.");
+/// Returns a `PathBuf` to the folder in `data_dir` used for storing profiles.
+pub fn profile_folder(data_dir: &std::path::Path) -> std::path::PathBuf {
+    let mut dir = data_dir.to_path_buf();
     dir.push("users");
     std::fs::create_dir_all(dir.clone()).expect("Unable to create required directories.");
     dir
 }
 
 /// Saves a `Profile` to the disk.
-fn save_profile(profile: Profile) {
+pub fn save_profile(profile: Profile, data_dir: &std::path::Path) {
     // Encode profile
-    let enc = bincode::serialize(&profile).expect("Unable to serialize a Profile.");
+    let enc = save_format::encode(&profile);
 
     // Get appropriate path and name
-    let mut path = profile_folder();
+    let mut path = profile_folder(data_dir);
     path.push(format!("{}{}.bic", profile.user.username, profile.user.tag));
 
     // Save to disk
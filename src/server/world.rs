@@ -1,4 +1,7 @@
 use crate::prelude::*;
+use crate::resources::ChatMessage;
+use crate::shared::crafting::Station;
+use crate::shared::mining::Ore;
 use bevy::utils::HashMap;
 
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -12,6 +15,9 @@ pub struct World {
     pub terrain: HashMap<(isize, isize), Vec<usize>>,
     pub objects: Vec<Object>,
     pub generated_objects: Vec<(isize, isize)>,
+    /// The most recent chat messages sent in this world, oldest first. Holds at
+    /// most `CHAT_LOG_LENGTH` messages.
+    pub chat_log: Vec<ChatMessage>,
 }
 
 impl World {
@@ -22,6 +28,7 @@ impl World {
             terrain: default(),
             objects: vec![],
             generated_objects: vec![],
+            chat_log: vec![],
         }
     }
     pub fn get_or_gen(&mut self, chunk: (isize, isize)) -> Vec<usize> {
@@ -101,7 +108,11 @@ impl World {
         // save data
         self.terrain.insert(chunk, final_data);
     }
-    pub fn try_generating_objects(&mut self, chunk: (isize, isize)) -> Vec<Object> {
+    pub fn try_generating_objects(
+        &mut self,
+        chunk: (isize, isize),
+        rng: &mut impl rand::Rng,
+    ) -> Vec<Object> {
         if self.generated_objects.contains(&chunk) {
             // no chunk generation needed
             return vec![];
@@ -180,7 +191,7 @@ impl World {
                                             (1080.0 / 2.0) - entity.px[1] as f32 - 32.0 + (1088.0 * chunk.1 as f32),
                                             0.0
                                         ),
-                                        rep: ObjectType::GroundItem(item),
+                                        rep: ObjectType::GroundItem(ItemStack::one(item)),
                                         uuid: uuid::Uuid::parse_str(&entity.iid)
                                             .expect("FATAL: LDtk entity had an invalid UUID"),
                                     });
@@ -213,6 +224,56 @@ impl World {
                                 }
                             }
                         }
+                        "Rock" => {
+                            // Rocks without an ore are plain stone
+                            let ore = entity
+                                .field_instances
+                                .iter()
+                                .find(|dataseg| dataseg.identifier == "Ore")
+                                .and_then(|dataseg| dataseg.value.as_ref())
+                                .and_then(|value| value.as_str())
+                                .map(|name| {
+                                    Ore::try_from_str(name)
+                                        .expect("FATAL: LDtk Rock had an unknown Ore")
+                                })
+                                .unwrap_or(Ore::Stone);
+                            self.objects.push(Object {
+                                pos: Transform::from_xyz(
+                                    (-1920.0 / 2.0) + entity.px[0] as f32 + 32.0 + (1920.0 * chunk.0 as f32),
+                                    (1080.0 / 2.0) - entity.px[1] as f32 - 32.0 + (1088.0 * chunk.1 as f32),
+                                    0.0
+                                ),
+                                rep: ObjectType::Rock(ore, ore.strength()),
+                                uuid: uuid::Uuid::parse_str(&entity.iid)
+                                    .expect("FATAL: LDtk entity had an invalid UUID"),
+                            });
+                            dupe_objects.push(self.objects[self.objects.len() - 1].clone());
+                        }
+                        "Station" => {
+                            for dataseg in &entity.field_instances {
+                                if dataseg.identifier == "StationName" {
+                                    let name = dataseg
+                                        .value
+                                        .as_ref()
+                                        .expect("FATAL: LDtk entity of type Station had no StationName")
+                                        .as_str()
+                                        .expect("FATAL: LDtk entity had a non-string StationName");
+                                    let station = Station::try_from_str(name)
+                                        .expect("FATAL: LDtk Station had an unknown StationName");
+                                    self.objects.push(Object {
+                                        pos: Transform::from_xyz(
+                                            (-1920.0 / 2.0) + entity.px[0] as f32 + 32.0 + (1920.0 * chunk.0 as f32),
+                                            (1080.0 / 2.0) - entity.px[1] as f32 - 32.0 + (1088.0 * chunk.1 as f32),
+                                            0.0
+                                        ),
+                                        rep: ObjectType::Station(station),
+                                        uuid: uuid::Uuid::parse_str(&entity.iid)
+                                            .expect("FATAL: LDtk entity had an invalid UUID"),
+                                    });
+                                    dupe_objects.push(self.objects[self.objects.len() - 1].clone());
+                                }
+                            }
+                        }
                         _ => {
                             // ignored or otherwise unknown.
                         }
@@ -221,10 +282,46 @@ impl World {
             }
         }
 
+        // Scatter rocks over stone, away from anything placed in LDtk
+        let terrain = self.get_or_gen(chunk);
+        for y in 0..CHUNK_HEIGHT {
+            for x in 0..CHUNK_WIDTH {
+                if terrain[x + (y * CHUNK_WIDTH)] != TERRAIN_STONE
+                    || rng.gen::<f32>() >= ROCK_CHANCE
+                {
+                    continue;
+                }
+                let pos = tile_centre(chunk, (x, y));
+                if self.objects.iter().any(|object| distance(object.pos, pos) < 64.0) {
+                    continue;
+                }
+                let ore = Ore::generate(rng.gen());
+                self.objects.push(Object {
+                    pos,
+                    rep: ObjectType::Rock(ore, ore.strength()),
+                    uuid: random_uuid(rng),
+                });
+                dupe_objects.push(self.objects[self.objects.len() - 1].clone());
+            }
+        }
+
         self.generated_objects.push(chunk);
 
         dupe_objects
     }
+    /// Adds a message to the chat log, dropping the oldest if it's full.
+    pub fn log_chat(&mut self, message: ChatMessage) {
+        self.chat_log.push(message);
+        if self.chat_log.len() > CHAT_LOG_LENGTH {
+            let excess = self.chat_log.len() - CHAT_LOG_LENGTH;
+            self.chat_log.drain(0..excess);
+        }
+    }
+    /// Returns the last `amount` messages from the chat log, oldest first.
+    pub fn recent_chat(&self, amount: usize) -> Vec<ChatMessage> {
+        let start = self.chat_log.len().saturating_sub(amount);
+        self.chat_log[start..].to_vec()
+    }
     /// Input tile coordinates are world aligned (+x right, +y up) starting in the logical bottom
     /// left
     pub fn modify_tile(&mut self, chunk: (isize, isize), tile: (usize, usize), state: usize) {
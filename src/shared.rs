@@ -1,5 +1,14 @@
+pub mod capture;
+pub mod clock;
+pub mod crafting;
+pub mod farming;
+pub mod friends;
+pub mod lan;
 pub mod listing;
+pub mod mining;
 pub mod network;
 pub mod object;
 pub mod player;
 pub mod saves;
+pub mod version;
+pub mod weather;
@@ -0,0 +1,112 @@
+//! Recording packets to a capture file, for debugging desyncs. Captures are a
+//! `CaptureHeader` followed by any number of `CapturedPacket`s, all bincode.
+
+use super::version::ProtocolVersion;
+use crate::prelude::*;
+use std::io::{BufReader, BufWriter, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Which end of the connection made a capture.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum Side {
+    Client,
+    Server,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum Direction {
+    Sent,
+    Recieved,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct CaptureHeader {
+    pub side: Side,
+    pub version: ProtocolVersion,
+    /// The seed of the server's random rolls. Clients don't know it.
+    pub seed: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct CapturedPacket {
+    /// Time since the capture started. Servers count the time they've
+    /// simulated rather than the wall clock, so replays can step the same way.
+    pub elapsed: Duration,
+    pub direction: Direction,
+    /// Who the packet was sent to or recieved from
+    pub peer: SocketAddr,
+    pub packet: Packet,
+}
+
+/// Writes every packet it's given to a capture file.
+pub struct Recorder {
+    file: BufWriter<std::fs::File>,
+    started: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, side: Side, seed: Option<u64>) -> Result<Recorder, anyhow::Error> {
+        let mut file = BufWriter::new(std::fs::File::create(path)?);
+        bincode::serialize_into(
+            &mut file,
+            &CaptureHeader {
+                side,
+                version: ProtocolVersion::current(),
+                seed,
+            },
+        )?;
+        info!("Capturing packets to {}", path.display());
+        Ok(Recorder {
+            file,
+            started: Instant::now(),
+        })
+    }
+    pub fn record(&mut self, direction: Direction, peer: SocketAddr, packet: &Packet) {
+        self.record_at(self.started.elapsed(), direction, peer, packet);
+    }
+    /// Records a packet as if it happened `elapsed` after the capture started.
+    pub fn record_at(
+        &mut self,
+        elapsed: Duration,
+        direction: Direction,
+        peer: SocketAddr,
+        packet: &Packet,
+    ) {
+        let captured = CapturedPacket {
+            elapsed,
+            direction,
+            peer,
+            packet: packet.clone(),
+        };
+        if let Err(e) = bincode::serialize_into(&mut self.file, &captured) {
+            warn!("Unable to capture a packet: {e}");
+        }
+        // Flushed every time so nothing is lost if the game crashes
+        else if let Err(e) = self.file.flush() {
+            warn!("Unable to capture a packet: {e}");
+        }
+    }
+}
+
+/// Reads a whole capture file.
+pub fn read_capture(path: &Path) -> Result<(CaptureHeader, Vec<CapturedPacket>), anyhow::Error> {
+    let mut file = BufReader::new(std::fs::File::open(path)?);
+    let header: CaptureHeader = bincode::deserialize_from(&mut file)?;
+    let mut packets = vec![];
+    loop {
+        match bincode::deserialize_from(&mut file) {
+            Ok(packet) => packets.push(packet),
+            Err(e) => {
+                if let bincode::ErrorKind::Io(io) = e.as_ref() {
+                    if io.kind() == std::io::ErrorKind::UnexpectedEof {
+                        break;
+                    }
+                }
+                return Err(e.into());
+            }
+        }
+    }
+    Ok((header, packets))
+}
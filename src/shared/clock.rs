@@ -0,0 +1,110 @@
+use crate::prelude::*;
+
+/// How far through the day a world is. Sent by the server every so often, and
+/// advanced by the client in between.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+pub struct WorldClock {
+    /// Seconds of game time the world has been simulated for
+    pub clock: f64,
+    /// Seconds in one day of this world
+    pub day_length: f64,
+}
+
+impl WorldClock {
+    /// Moves the clock forward by `seconds`.
+    pub fn advance(&mut self, seconds: f64) {
+        self.clock += seconds;
+    }
+    /// How far through the day it is, from 0 (midnight) up to 1. Worlds start
+    /// at `DAY_START`.
+    pub fn time_of_day(&self) -> f64 {
+        (self.clock / self.day_length.max(1.0) + DAY_START).rem_euclid(1.0)
+    }
+    /// How bright it is, from 0 (night) to 1 (day). Dawn is centred on 6:00
+    /// and dusk on 18:00.
+    pub fn daylight(&self) -> f32 {
+        let height = ((self.time_of_day() - 0.25) * std::f64::consts::TAU).sin();
+        ((height + 0.25) / 0.5).clamp(0.0, 1.0) as f32
+    }
+    /// The time of day on a 24 hour clock, as (hours, minutes).
+    pub fn hours_minutes(&self) -> (u32, u32) {
+        let minutes = (self.time_of_day() * 24.0 * 60.0) as u32;
+        (minutes / 60 % 24, minutes % 60)
+    }
+}
+
+impl std::fmt::Display for WorldClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (hours, minutes) = self.hours_minutes();
+        write!(f, "{hours:02}:{minutes:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: f64 = 1200.0;
+
+    /// A clock at `hours` into a `DAY` long day, counting from midnight.
+    fn at(hours: f64) -> WorldClock {
+        let time = (hours / 24.0 - DAY_START).rem_euclid(1.0);
+        WorldClock {
+            clock: time * DAY,
+            day_length: DAY,
+        }
+    }
+
+    #[test]
+    fn new_worlds_start_in_the_morning() {
+        let clock = WorldClock {
+            clock: 0.0,
+            day_length: DAY,
+        };
+        assert_eq!(clock.time_of_day(), DAY_START);
+        assert_eq!(clock.hours_minutes(), (6, 0));
+    }
+
+    #[test]
+    fn midnight() {
+        let clock = at(0.0);
+        assert_eq!(clock.time_of_day(), 0.0);
+        assert_eq!(clock.hours_minutes(), (0, 0));
+        assert_eq!(clock.daylight(), 0.0);
+        assert_eq!(clock.to_string(), "00:00");
+        // The clock wraps around instead of reaching 24:00
+        let mut before = at(0.0);
+        before.clock += DAY - 0.5;
+        assert_eq!(before.hours_minutes(), (23, 59));
+        before.advance(0.5);
+        assert_eq!(before.hours_minutes(), (0, 0));
+    }
+
+    #[test]
+    fn dawn_and_dusk() {
+        let dawn = at(6.0);
+        assert_eq!(dawn.hours_minutes(), (6, 0));
+        assert!((dawn.daylight() - 0.5).abs() < 1.0e-6);
+        let dusk = at(18.0);
+        assert_eq!(dusk.hours_minutes(), (18, 0));
+        assert!((dusk.daylight() - 0.5).abs() < 1.0e-6);
+        // Brighter after dawn and darker after dusk
+        assert!(at(7.0).daylight() > dawn.daylight());
+        assert!(at(19.0).daylight() < dusk.daylight());
+        assert_eq!(at(12.0).daylight(), 1.0);
+    }
+
+    #[test]
+    fn short_days() {
+        for day_length in [0.0, 0.5, -3.0] {
+            let clock = WorldClock {
+                clock: 0.25,
+                day_length,
+            };
+            // Anything under a second long is treated as a one second day
+            assert_eq!(clock.time_of_day(), 0.5);
+            assert_eq!(clock.hours_minutes(), (12, 0));
+            assert!(clock.daylight().is_finite());
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::prelude::*;
+use crate::shared::player::{Inventory, Recipe, Stats};
+
+/// Something recipes can need the player to be standing at.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum Station {
+    Workbench,
+    Furnace,
+}
+
+impl Station {
+    /// Like `Item::try_from_str`, for stations placed in LDtk.
+    pub fn try_from_str(from: &str) -> Option<Station> {
+        match from {
+            "Workbench" => Some(Station::Workbench),
+            "Furnace" => Some(Station::Furnace),
+            _ => None,
+        }
+    }
+    /// The name shown to players.
+    pub fn name(self) -> &'static str {
+        match self {
+            Station::Workbench => "workbench",
+            Station::Furnace => "furnace",
+        }
+    }
+}
+
+/// Every recipe in the game, loaded from `metadata/recipes.json`. Built into
+/// both the client and server so they always agree.
+#[derive(Clone, Deserialize, Debug)]
+pub struct RecipeBook {
+    pub recipes: Vec<RecipeInfo>,
+}
+
+impl RecipeBook {
+    pub fn load() -> RecipeBook {
+        serde_json::from_slice(include_bytes!("../../assets/metadata/recipes.json"))
+            .expect("Encountered a courrupted recipe book.")
+    }
+    pub fn get(&self, recipe: &Recipe) -> Option<&RecipeInfo> {
+        self.recipes.iter().find(|info| &info.name == recipe)
+    }
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct RecipeInfo {
+    pub name: Recipe,
+    /// Items used up, and how many of each
+    pub inputs: Vec<(Item, usize)>,
+    /// Items made, and how many of each
+    pub outputs: Vec<(Item, usize)>,
+    /// Where the player has to be to craft this, if anywhere
+    #[serde(default)]
+    pub station: Option<Station>,
+    #[serde(default)]
+    pub unlock: Unlock,
+}
+
+impl RecipeInfo {
+    /// What this makes, for showing to players.
+    pub fn label(&self) -> String {
+        let outputs: Vec<String> =
+            self.outputs.iter().map(|(item, amount)| count(*item, *amount)).collect();
+        outputs.join(", ")
+    }
+    /// What this uses up, for showing to players.
+    pub fn cost(&self) -> String {
+        let inputs: Vec<String> =
+            self.inputs.iter().map(|(item, amount)| count(*item, *amount)).collect();
+        inputs.join(", ")
+    }
+    /// Does an inventory have everything this uses up?
+    pub fn affordable(&self, inventory: &Inventory) -> bool {
+        self.inputs.iter().all(|(item, amount)| inventory.count(*item) >= *amount)
+    }
+}
+
+/// What a player needs before they can use a recipe. Recipes with no
+/// conditions are known from the start.
+#[derive(Clone, Default, Deserialize, Debug)]
+pub struct Unlock {
+    /// Crafting level needed
+    #[serde(default)]
+    pub crafting: usize,
+    /// An item the player must have found
+    #[serde(default)]
+    pub holding: Option<Item>,
+}
+
+impl Unlock {
+    pub fn met(&self, stats: &Stats, inventory: &Inventory) -> bool {
+        stats.crafting >= self.crafting
+            && self.holding.map_or(true, |item| inventory.contains(item))
+    }
+}
+
+fn count(item: Item, amount: usize) -> String {
+    if amount == 1 {
+        item.name().to_string()
+    }
+    else {
+        format!("{amount} {}", item.name())
+    }
+}
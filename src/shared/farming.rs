@@ -0,0 +1,93 @@
+use crate::prelude::*;
+
+/// Something that can be grown on farmland.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum Crop {
+    Wheat,
+    Carrot,
+}
+
+impl Crop {
+    /// The item planted to grow this crop.
+    pub fn seeds(self) -> Item {
+        match self {
+            Crop::Wheat => Item::WheatSeeds,
+            Crop::Carrot => Item::CarrotSeeds,
+        }
+    }
+    /// The item this crop is harvested for.
+    pub fn produce(self) -> Item {
+        match self {
+            Crop::Wheat => Item::Wheat,
+            Crop::Carrot => Item::Carrot,
+        }
+    }
+    /// Amount of growth stages, including being just planted. Crops are ripe
+    /// at the last stage.
+    pub fn stages(self) -> usize {
+        match self {
+            Crop::Wheat => 4,
+            Crop::Carrot => 3,
+        }
+    }
+    /// Seconds of game time a watered crop takes to reach its next stage.
+    pub fn stage_length(self) -> f32 {
+        match self {
+            Crop::Wheat => 120.0,
+            Crop::Carrot => 180.0,
+        }
+    }
+}
+
+/// A tilled tile, which may have a crop planted in it.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+pub struct Farmland {
+    pub crop: Option<Crop>,
+    /// Growth stage of the crop, starting at 0 when planted
+    pub stage: usize,
+    /// Seconds the crop has grown towards its next stage
+    pub growth: f32,
+    /// Crops only grow while watered. Drys out every time the crop grows.
+    pub watered: bool,
+}
+
+impl Farmland {
+    /// Freshly tilled farmland, with nothing planted.
+    pub fn tilled() -> Farmland {
+        Farmland {
+            crop: None,
+            stage: 0,
+            growth: 0.0,
+            watered: false,
+        }
+    }
+    /// Is there a crop here that can be harvested?
+    pub fn ripe(&self) -> bool {
+        if let Some(crop) = self.crop {
+            self.stage + 1 >= crop.stages()
+        }
+        else {
+            false
+        }
+    }
+    /// Grows the crop for `dt` seconds. Returns true if it reached a new stage.
+    pub fn grow(&mut self, dt: f32) -> bool {
+        let crop = if let Some(crop) = self.crop {
+            crop
+        }
+        else {
+            return false;
+        };
+        if !self.watered || self.ripe() {
+            return false;
+        }
+        self.growth += dt;
+        if self.growth < crop.stage_length() {
+            return false;
+        }
+        self.growth = 0.0;
+        self.stage += 1;
+        self.watered = false;
+        true
+    }
+}
@@ -0,0 +1,21 @@
+use crate::prelude::*;
+
+/// What a user is currently doing, as seen by their friends.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum Presence {
+    Offline,
+    /// Connected, but not in a world
+    TitleScreen,
+    /// Playing in a world
+    /// (World ID, World Name)
+    InWorld(usize, String),
+}
+
+/// One entry in a user's friends list.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct FriendStatus {
+    pub user: User,
+    pub presence: Presence,
+    /// Can the user viewing this join the world their friend is in?
+    pub can_join: bool,
+}
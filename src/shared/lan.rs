@@ -0,0 +1,17 @@
+use super::version::ProtocolVersion;
+use crate::prelude::*;
+
+/// Broadcast by servers with LAN discovery enabled, so that clients on the
+/// same network can list them. Sent as JSON over UDP to `LAN_DISCOVERY_PORT`.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct LanAnnouncement {
+    /// The server's protocol version
+    pub version: ProtocolVersion,
+    pub name: String,
+    pub tcp_port: u16,
+    pub ws_port: u16,
+    /// Amount of worlds on the server
+    pub worlds: usize,
+    /// Amount of players in a world right now
+    pub online: usize,
+}
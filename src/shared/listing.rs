@@ -9,4 +9,70 @@ pub struct GameListing {
     pub address: String,
     pub password: bool,
     pub played: bool,
+    pub owner: User,
+    /// Players in the world right now
+    pub online: usize,
+    pub max_players: usize,
+    /// Seconds since someone last joined, if anyone has
+    pub last_played: Option<u64>,
+    /// Seconds since the world was created
+    pub age: u64,
+}
+
+impl GameListing {
+    /// Does this listing's name, description or owner contain `filter`?
+    /// Ignores case.
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        self.public_name.to_lowercase().contains(&filter)
+            || self.description.to_lowercase().contains(&filter)
+            || self.owner.to_string().to_lowercase().contains(&filter)
+    }
+}
+
+/// Orders for the server list.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ServerSort {
+    /// Most recently played first
+    LastPlayed,
+    /// Alphabetical
+    Name,
+    /// Most players online first
+    Online,
+    /// Newest first
+    Age,
+}
+
+impl ServerSort {
+    pub fn next(self) -> ServerSort {
+        match self {
+            ServerSort::LastPlayed => ServerSort::Name,
+            ServerSort::Name => ServerSort::Online,
+            ServerSort::Online => ServerSort::Age,
+            ServerSort::Age => ServerSort::LastPlayed,
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            ServerSort::LastPlayed => "Last Played",
+            ServerSort::Name => "Name",
+            ServerSort::Online => "Online",
+            ServerSort::Age => "Newest",
+        }
+    }
+    pub fn sort(self, listings: &mut [GameListing]) {
+        match self {
+            // Never played sorts last
+            ServerSort::LastPlayed => {
+                listings.sort_by_key(|listing| listing.last_played.unwrap_or(u64::MAX))
+            }
+            ServerSort::Name => {
+                listings.sort_by_key(|listing| listing.public_name.to_lowercase())
+            }
+            ServerSort::Online => {
+                listings.sort_by_key(|listing| std::cmp::Reverse(listing.online))
+            }
+            ServerSort::Age => listings.sort_by_key(|listing| listing.age),
+        }
+    }
 }
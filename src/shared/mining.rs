@@ -0,0 +1,56 @@
+use crate::prelude::*;
+
+/// What a rock is made of.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum Ore {
+    Stone,
+    Copper,
+    Iron,
+}
+
+impl Ore {
+    /// Like `Item::try_from_str`, for rocks placed in LDtk.
+    pub fn try_from_str(from: &str) -> Option<Ore> {
+        match from {
+            "Stone" => Some(Ore::Stone),
+            "Copper" => Some(Ore::Copper),
+            "Iron" => Some(Ore::Iron),
+            _ => None,
+        }
+    }
+    /// Picks what a generated rock is made of, where `roll` is from 0 to 1.
+    pub fn generate(roll: f32) -> Ore {
+        if roll < 0.07 {
+            Ore::Iron
+        }
+        else if roll < 0.25 {
+            Ore::Copper
+        }
+        else {
+            Ore::Stone
+        }
+    }
+    /// Amount of mining power it takes to break a new rock.
+    pub fn strength(self) -> usize {
+        match self {
+            Ore::Stone => 4,
+            Ore::Copper => 6,
+            Ore::Iron => 8,
+        }
+    }
+    /// The pickaxe power needed to mine this at all.
+    pub fn hardness(self) -> usize {
+        match self {
+            Ore::Stone | Ore::Copper => 1,
+            Ore::Iron => 2,
+        }
+    }
+    /// The item dropped when a rock breaks, and the least and most dropped.
+    pub fn drops(self) -> (Item, usize, usize) {
+        match self {
+            Ore::Stone => (Item::Stone, 1, 3),
+            Ore::Copper => (Item::CopperOre, 1, 2),
+            Ore::Iron => (Item::IronOre, 1, 2),
+        }
+    }
+}
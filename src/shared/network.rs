@@ -1,21 +1,29 @@
 use crate::prelude::*;
 use crate::{
     resources::ChatMessage,
-    shared::{listing::GameListing, player::Inventory},
+    shared::{
+        clock::WorldClock,
+        farming::Crop,
+        friends::FriendStatus,
+        listing::GameListing,
+        player::{Inventory, Recipe},
+        version::ProtocolVersion,
+        weather::Weather,
+    },
 };
 
 #[derive(Clone, PartialEq, Deserialize, Serialize, Debug)]
 pub enum Packet {
     /// Post the version of the network protocol being used.
     /// A response is expected, regardless of the version on either end.
-    /// (Netty Version)
-    NettyVersion(String),
-    /// The server appears to use the same version. Continue.
-    /// (No Data)
-    AllSet,
-    /// The server is running a newer version. Exit.
+    /// (Client's Version)
+    NettyVersion(ProtocolVersion),
+    /// The server can talk to this version. Continue.
+    /// (Server's Version)
+    AllSet(ProtocolVersion),
+    /// The server is running an incompatible version. Tell the player.
     /// (Server's Version)
-    WrongVersion(String),
+    WrongVersion(ProtocolVersion),
     /// Data was recieved but unable to be deserizalized.
     /// (This occurs on data courruption or a disconnect, usually the latter.)
     /// (No Data)
@@ -114,36 +122,528 @@ pub enum Packet {
     /// Recieves a chat message.
     /// (Message)
     ChatMessage(ChatMessage),
+    /// Recieves the recent chat of a world after joining it, oldest first.
+    /// (Array (Message))
+    ChatHistory(Vec<ChatMessage>),
     /// Sends/Recieves an animation for a player using an item
     /// (Action)
     ActionAnimation(ItemAction),
+    /// You have been removed from the world you were in.
+    /// (Reason)
+    Kicked(String),
+    /// Asks to become friends with another user.
+    /// (User)
+    SendFriendRequest(User),
+    /// Accepts or declines a friend request from another user.
+    /// (User, Accepted)
+    RespondFriendRequest(User, bool),
+    /// Removes a user from the sender's friends list.
+    /// (User)
+    RemoveFriend(User),
+    /// The full friends list of the recieving user, along with the users who have sent them
+    /// friend requests.
+    /// (Array (Friend), Array (User))
+    FriendList(Vec<FriendStatus>, Vec<User>),
+    /// A friend's presence has changed.
+    /// (Friend)
+    FriendPresence(FriendStatus),
+    /// A friend request couldn't be sent.
+    /// (Reason)
+    FriendRequestFailed(String),
+    /// The time in the world the player is in. Sent on joining and every
+    /// `CLOCK_SYNC_INTERVAL` after.
+    /// (Clock)
+    WorldClock(WorldClock),
+    /// The weather changed in the world the player is in. Also sent on joining.
+    /// (Weather)
+    Weather(Weather),
+    /// Tills the tile at a position, or harvests the crop growing there.
+    /// (Position)
+    Dig(Transform),
+    /// Waters the farmland at a position.
+    /// (Position)
+    Water(Transform),
+    /// Plants a crop in the farmland at a position, using up its seeds.
+    /// (Position, Crop)
+    Plant(Transform, Crop),
+    /// Casts a fishing line into the water at a position.
+    /// (Position)
+    Cast(Transform),
+    /// A cast landed in the water, and is waiting for a bite.
+    /// (Position)
+    Bobber(Transform),
+    /// A fish is biting. Reel in quickly to catch it!
+    Bite,
+    /// Reels in the player's fishing line.
+    Reel,
+    /// The player's fishing line is out of the water, with what they caught.
+    /// (Catch)
+    Caught(Option<Item>),
+    /// Hits a rock with the player's best pickaxe.
+    /// (Object)
+    Mine(uuid::Uuid),
+    /// Crafts a recipe, using up its inputs.
+    /// (Recipe)
+    Craft(Recipe),
+    /// Every recipe the player has unlocked, sent whenever it changes.
+    /// (Recipes)
+    UnlockedRecipes(Vec<Recipe>),
+    /// Moves a stack in the player's inventory onto another slot, merging or
+    /// swapping them.
+    /// (From Slot, To Slot)
+    MoveStack(usize, usize),
+    /// Moves half of a stack in the player's inventory into an empty slot.
+    /// (From Slot, To Slot)
+    SplitStack(usize, usize),
+    /// Drops items from the player's inventory, thrown towards a position.
+    /// (Slot, Count, Towards)
+    DropItems(usize, usize, Transform),
+    /// Sent by clients every `KEEPALIVE_INTERVAL` if the server has the
+    /// "keepalive" capability, so it can tell when they're gone.
+    KeepAlive,
+    /// You couldn't join the world you asked to, and are still on the title
+    /// screen. Only sent to clients with the "join_refused" capability.
+    /// (Reason)
+    JoinRefused(String),
+    /// Tells the server which hotbar slot the player is holding. Only sent to
+    /// servers with the "selected_slot" capability.
+    /// (Slot)
+    SelectSlot(usize),
+    /// Another player in your world used an item. Only sent to clients with
+    /// the "player_actions" capability.
+    /// (Player, Action)
+    PlayerAction(User, ItemAction),
+}
+
+impl Packet {
+    /// Returns the name of this packet's variant, for logging and metrics.
+    pub fn variant_name(&self) -> &'static str {
+        match self {
+            Packet::NettyVersion(..) => "NettyVersion",
+            Packet::AllSet(..) => "AllSet",
+            Packet::WrongVersion(..) => "WrongVersion",
+            Packet::FailedDeserialize => "FailedDeserialize",
+            Packet::CreateUser(..) => "CreateUser",
+            Packet::CreatedUser(..) => "CreatedUser",
+            Packet::OverusedName => "OverusedName",
+            Packet::UserPresence(..) => "UserPresence",
+            Packet::CreateWorld(..) => "CreateWorld",
+            Packet::CreatedWorld(..) => "CreatedWorld",
+            Packet::JoinWorld(..) => "JoinWorld",
+            Packet::AvalableServers => "AvalableServers",
+            Packet::ServerList(..) => "ServerList",
+            Packet::LeaveWorld => "LeaveWorld",
+            Packet::JoinedGame(..) => "JoinedGame",
+            Packet::InventoryState(..) => "InventoryState",
+            Packet::OnlinePlayers(..) => "OnlinePlayers",
+            Packet::RequestChunk(..) => "RequestChunk",
+            Packet::ChunkData(..) => "ChunkData",
+            Packet::TileUpdate(..) => "TileUpdate",
+            Packet::AllObjects(..) => "AllObjects",
+            Packet::UpdateObject(..) => "UpdateObject",
+            Packet::RemoveObject(..) => "RemoveObject",
+            Packet::CreateObject(..) => "CreateObject",
+            Packet::RequestMove(..) => "RequestMove",
+            Packet::PlayerPositionUpdate(..) => "PlayerPositionUpdate",
+            Packet::PlayerDisconnected(..) => "PlayerDisconnected",
+            Packet::WhitelistUser(..) => "WhitelistUser",
+            Packet::NoWhitelistPermission => "NoWhitelistPermission",
+            Packet::UnwhitelistableUser => "UnwhitelistableUser",
+            Packet::Whitelisted => "Whitelisted",
+            Packet::PlayerConnected(..) => "PlayerConnected",
+            Packet::SendChatMessage(..) => "SendChatMessage",
+            Packet::ChatMessage(..) => "ChatMessage",
+            Packet::ChatHistory(..) => "ChatHistory",
+            Packet::ActionAnimation(..) => "ActionAnimation",
+            Packet::Kicked(..) => "Kicked",
+            Packet::SendFriendRequest(..) => "SendFriendRequest",
+            Packet::RespondFriendRequest(..) => "RespondFriendRequest",
+            Packet::RemoveFriend(..) => "RemoveFriend",
+            Packet::FriendList(..) => "FriendList",
+            Packet::FriendPresence(..) => "FriendPresence",
+            Packet::FriendRequestFailed(..) => "FriendRequestFailed",
+            Packet::WorldClock(..) => "WorldClock",
+            Packet::Weather(..) => "Weather",
+            Packet::Dig(..) => "Dig",
+            Packet::Water(..) => "Water",
+            Packet::Plant(..) => "Plant",
+            Packet::Cast(..) => "Cast",
+            Packet::Bobber(..) => "Bobber",
+            Packet::Bite => "Bite",
+            Packet::Reel => "Reel",
+            Packet::Caught(..) => "Caught",
+            Packet::Mine(..) => "Mine",
+            Packet::Craft(..) => "Craft",
+            Packet::UnlockedRecipes(..) => "UnlockedRecipes",
+            Packet::MoveStack(..) => "MoveStack",
+            Packet::SplitStack(..) => "SplitStack",
+            Packet::DropItems(..) => "DropItems",
+            Packet::KeepAlive => "KeepAlive",
+            Packet::JoinRefused(..) => "JoinRefused",
+            Packet::SelectSlot(..) => "SelectSlot",
+            Packet::PlayerAction(..) => "PlayerAction",
+        }
+    }
+}
+
+/// Why a packet couldn't be read.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The connection closed or failed partway through a packet
+    Io(std::io::Error),
+    /// The packet claimed to be bigger than `MAX_PACKET_SIZE`
+    TooLarge(u64),
+    /// The packet's contents weren't a valid `Packet`
+    Malformed(bincode::Error),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Io(e) => write!(f, "connection error: {e}"),
+            DecodeError::TooLarge(size) => {
+                write!(f, "packet of {size} bytes is over the {MAX_PACKET_SIZE} byte limit")
+            }
+            DecodeError::Malformed(e) => write!(f, "malformed packet: {e}"),
+        }
+    }
+}
+
+/// The bincode settings packets are encoded with.
+fn codec() -> impl bincode::Options {
+    use bincode::Options;
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_limit(MAX_PACKET_SIZE)
+}
+
+impl Packet {
+    /// Encodes this packet as a little endian `u32` length followed by that
+    /// many bytes of bincode.
+    pub fn encode(&self) -> Result<Vec<u8>, bincode::Error> {
+        use bincode::Options;
+        let body = codec().serialize(self)?;
+        let mut frame = Vec::with_capacity(body.len() + 4);
+        frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&body);
+        Ok(frame)
+    }
+    /// Reads one packet written by `encode`. The body is only buffered as it
+    /// arrives, so a bad length can't allocate much on its own.
+    pub fn decode<R: std::io::Read>(reader: &mut R) -> Result<Packet, DecodeError> {
+        use bincode::Options;
+        use std::io::Read;
+        let mut length = [0; 4];
+        reader.read_exact(&mut length).map_err(DecodeError::Io)?;
+        let length = u32::from_le_bytes(length) as u64;
+        if length > MAX_PACKET_SIZE {
+            return Err(DecodeError::TooLarge(length));
+        }
+        let mut body = vec![];
+        reader
+            .take(length)
+            .read_to_end(&mut body)
+            .map_err(DecodeError::Io)?;
+        if body.len() as u64 != length {
+            return Err(DecodeError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        codec().deserialize(&body).map_err(DecodeError::Malformed)
+    }
 }
 
 impl netty::Packet for Packet {
+    /// Never panics. Anything that can't be read becomes `FailedDeserialize`,
+    /// and the connection it came from is dropped by whoever recieves it.
     fn from_reader<R: std::io::Read>(reader: &mut R) -> Self {
-        let maybe_pkt = bincode::deserialize_from(reader);
-        match maybe_pkt {
+        match Packet::decode(reader) {
             Ok(pkt) => {
                 trace!("Got a packet {:?}!", pkt);
                 pkt
             }
+            // Usually just a closed connection
+            Err(DecodeError::Io(e)) => {
+                debug!("Unable to read a packet: {e}");
+                Packet::FailedDeserialize
+            }
             Err(e) => {
-                println!("Errored getting packet {:?}!", e);
-                panic!();
+                warn!("Unable to read a packet: {e}");
+                Packet::FailedDeserialize
             }
         }
     }
 
     fn write<W: std::io::Write + ?Sized>(&self, writer: &mut W) {
-        //println!("Writing a packet {:?}!", self);
-        let write_state = writer.write_all(&bincode::serialize(self).expect("Netty unable to serialize packet"));
-        if write_state.is_err() {
-            warn!("Netty unable to write serialized packet due to error: {:?}", write_state);
+        let frame = self.to_vec();
+        if frame.is_empty() {
+            return;
+        }
+        if let Err(e) = writer.write_all(&frame) {
+            warn!("Netty unable to write serialized packet due to error: {:?}", e);
             warn!("Packet data: {:?}", self);
         }
-        writer.flush().expect("Netty unable to flush buffer");
+        else if let Err(e) = writer.flush() {
+            warn!("Netty unable to flush buffer due to error: {:?}", e);
+        }
     }
+    /// Returns nothing if the packet is too large to send.
     fn to_vec(&self) -> Vec<u8> {
-        bincode::serialize(self).unwrap()
+        match self.encode() {
+            Ok(frame) => frame,
+            Err(e) => {
+                error!("Unable to send a {} packet: {e}", self.variant_name());
+                vec![]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::ChatChannel;
+    use crate::shared::friends::Presence;
+    use proptest::prelude::*;
+
+    /// One of every packet, filled in with the given values. Add new variants
+    /// here and to `every_variant_is_covered`.
+    fn every_packet(
+        text: String,
+        number: usize,
+        tag: u16,
+        position: (f32, f32),
+        chunk: (isize, isize),
+        flag: bool,
+        id: u128,
+    ) -> Vec<Packet> {
+        let user = User {
+            username: text.clone(),
+            tag,
+        };
+        let transform = Transform::from_xyz(position.0, position.1, 0.0);
+        let version = ProtocolVersion {
+            major: tag,
+            minor: tag / 2,
+            capabilities: vec![text.clone()],
+        };
+        let message = ChatMessage {
+            text: text.clone(),
+            color: Color::rgb(position.0, position.1, 0.5),
+            channel: ChatChannel::Whisper(user.clone()),
+            sender: Some(user.clone()),
+            sent_at: std::time::Instant::now(),
+        };
+        let object = Object {
+            pos: transform,
+            rep: if flag {
+                ObjectType::Tree(number)
+            }
+            else {
+                ObjectType::GroundItem(ItemStack::new(Item::Wood, number))
+            },
+            uuid: uuid::Uuid::from_u128(id),
+        };
+        let friend = FriendStatus {
+            user: user.clone(),
+            presence: Presence::InWorld(number, text.clone()),
+            can_join: flag,
+        };
+        let listing = GameListing {
+            public_name: text.clone(),
+            description: text.clone(),
+            internal_id: number,
+            local: flag,
+            address: text.clone(),
+            password: flag,
+            played: flag,
+            owner: user.clone(),
+            online: number,
+            max_players: number,
+            last_played: Some(number as u64),
+            age: number as u64,
+        };
+        let mut inventory = Inventory::empty();
+        inventory.selected_slot = number % 10;
+        inventory.hotbar[number % 10] = Some(ItemStack::new(Item::MakeshiftAxe, number));
+        inventory.backpack[number % 10] = Some(ItemStack::one(Item::Wood));
+        let recipe = Recipe(text.clone());
+        let weather = if flag {
+            Weather::Storm
+        }
+        else {
+            Weather::Fog
+        };
+        let crop = if flag {
+            Crop::Wheat
+        }
+        else {
+            Crop::Carrot
+        };
+        vec![
+            Packet::NettyVersion(version.clone()),
+            Packet::AllSet(version.clone()),
+            Packet::WrongVersion(version),
+            Packet::FailedDeserialize,
+            Packet::CreateUser(user.clone()),
+            Packet::CreatedUser(user.clone()),
+            Packet::OverusedName,
+            Packet::UserPresence(user.clone()),
+            Packet::CreateWorld(text.clone()),
+            Packet::CreatedWorld(number),
+            Packet::JoinWorld(number),
+            Packet::AvalableServers,
+            Packet::ServerList(vec![listing]),
+            Packet::LeaveWorld,
+            Packet::JoinedGame(transform, flag),
+            Packet::InventoryState(inventory),
+            Packet::OnlinePlayers(vec![(user.clone(), transform)]),
+            Packet::RequestChunk(chunk),
+            Packet::ChunkData(chunk, vec![number; 4]),
+            Packet::TileUpdate(chunk, (number, number), number),
+            Packet::AllObjects(vec![object.clone()]),
+            Packet::UpdateObject(object.clone()),
+            Packet::RemoveObject(object.uuid),
+            Packet::Mine(object.uuid),
+            Packet::CreateObject(object),
+            Packet::RequestMove(transform),
+            Packet::PlayerPositionUpdate(user.clone(), transform),
+            Packet::PlayerDisconnected(user.clone()),
+            Packet::WhitelistUser(user.clone()),
+            Packet::NoWhitelistPermission,
+            Packet::UnwhitelistableUser,
+            Packet::Whitelisted,
+            Packet::PlayerConnected(user.clone(), transform),
+            Packet::SendChatMessage(message.clone()),
+            Packet::ChatMessage(message.clone()),
+            Packet::ChatHistory(vec![message]),
+            Packet::ActionAnimation(ItemAction::Chop(number)),
+            Packet::Kicked(text.clone()),
+            Packet::SendFriendRequest(user.clone()),
+            Packet::RespondFriendRequest(user.clone(), flag),
+            Packet::RemoveFriend(user.clone()),
+            Packet::FriendList(vec![friend.clone()], vec![user]),
+            Packet::FriendPresence(friend),
+            Packet::FriendRequestFailed(text),
+            Packet::WorldClock(WorldClock {
+                clock: position.0 as f64,
+                day_length: position.1 as f64,
+            }),
+            Packet::Weather(weather),
+            Packet::Dig(transform),
+            Packet::Water(transform),
+            Packet::Plant(transform, crop),
+            Packet::Cast(transform),
+            Packet::Bobber(transform),
+            Packet::Bite,
+            Packet::Reel,
+            Packet::Caught(Some(Item::Minnow)),
+            Packet::Craft(recipe.clone()),
+            Packet::UnlockedRecipes(vec![recipe]),
+            Packet::MoveStack(number, number % 10),
+            Packet::SplitStack(number % 10, number),
+            Packet::DropItems(number % 10, number, transform),
+            Packet::KeepAlive,
+            Packet::JoinRefused(text.clone()),
+            Packet::SelectSlot(number % 10),
+            Packet::PlayerAction(user.clone(), ItemAction::Mine(number)),
+        ]
+    }
+
+    #[test]
+    fn every_variant_is_covered() {
+        let packets = every_packet(String::new(), 0, 0, (0.0, 0.0), (0, 0), false, 0);
+        let mut names: Vec<&str> = packets.iter().map(Packet::variant_name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), packets.len());
+        // Fails to compile when a variant is added, as a reminder to add it to
+        // `every_packet` too
+        for packet in &packets {
+            match packet {
+                Packet::NettyVersion(..) | Packet::AllSet(..) | Packet::WrongVersion(..)
+                | Packet::FailedDeserialize | Packet::CreateUser(..) | Packet::CreatedUser(..)
+                | Packet::OverusedName | Packet::UserPresence(..) | Packet::CreateWorld(..)
+                | Packet::CreatedWorld(..) | Packet::JoinWorld(..) | Packet::AvalableServers
+                | Packet::ServerList(..) | Packet::LeaveWorld | Packet::JoinedGame(..)
+                | Packet::InventoryState(..) | Packet::OnlinePlayers(..)
+                | Packet::RequestChunk(..) | Packet::ChunkData(..) | Packet::TileUpdate(..)
+                | Packet::AllObjects(..) | Packet::UpdateObject(..) | Packet::RemoveObject(..)
+                | Packet::CreateObject(..) | Packet::RequestMove(..)
+                | Packet::PlayerPositionUpdate(..) | Packet::PlayerDisconnected(..)
+                | Packet::WhitelistUser(..) | Packet::NoWhitelistPermission
+                | Packet::UnwhitelistableUser | Packet::Whitelisted
+                | Packet::PlayerConnected(..) | Packet::SendChatMessage(..)
+                | Packet::ChatMessage(..) | Packet::ChatHistory(..)
+                | Packet::ActionAnimation(..) | Packet::Kicked(..)
+                | Packet::SendFriendRequest(..) | Packet::RespondFriendRequest(..)
+                | Packet::RemoveFriend(..) | Packet::FriendList(..)
+                | Packet::FriendPresence(..) | Packet::FriendRequestFailed(..)
+                | Packet::WorldClock(..) | Packet::Weather(..) | Packet::Dig(..)
+                | Packet::Water(..) | Packet::Plant(..) | Packet::Cast(..)
+                | Packet::Bobber(..) | Packet::Bite | Packet::Reel | Packet::Caught(..)
+                | Packet::Mine(..) | Packet::Craft(..) | Packet::UnlockedRecipes(..)
+                | Packet::MoveStack(..) | Packet::SplitStack(..) | Packet::DropItems(..)
+                | Packet::KeepAlive | Packet::JoinRefused(..) | Packet::SelectSlot(..)
+                | Packet::PlayerAction(..) => {}
+            }
+        }
+    }
+
+    #[test]
+    fn oversized_packets_are_rejected_before_reading() {
+        let mut frame = ((MAX_PACKET_SIZE + 1) as u32).to_le_bytes().to_vec();
+        frame.extend_from_slice(&[0; 16]);
+        assert!(matches!(
+            Packet::decode(&mut frame.as_slice()),
+            Err(DecodeError::TooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn closed_connections_become_failed_deserialize() {
+        let mut empty: &[u8] = &[];
+        assert_eq!(
+            <Packet as netty::Packet>::from_reader(&mut empty),
+            Packet::FailedDeserialize
+        );
+    }
+
+    proptest! {
+        // `ChatMessage.sent_at` isn't sent, so packets are compared by their
+        // encoding rather than with `==`.
+        #[test]
+        fn packets_round_trip(
+            text in "\\PC{0,32}",
+            number in any::<usize>(),
+            tag in any::<u16>(),
+            position in (-1.0e6f32..1.0e6, -1.0e6f32..1.0e6),
+            chunk in any::<(isize, isize)>(),
+            flag in any::<bool>(),
+            id in any::<u128>(),
+        ) {
+            for packet in every_packet(text.clone(), number, tag, position, chunk, flag, id) {
+                let frame = packet.encode().unwrap();
+                let decoded = Packet::decode(&mut frame.as_slice()).unwrap();
+                prop_assert_eq!(decoded.variant_name(), packet.variant_name());
+                prop_assert_eq!(decoded.encode().unwrap(), frame);
+            }
+        }
+
+        #[test]
+        fn truncated_packets_are_errors(
+            text in "\\PC{0,32}",
+            number in any::<usize>(),
+            cut in any::<prop::sample::Index>(),
+        ) {
+            for packet in every_packet(text.clone(), number, 1, (0.0, 0.0), (0, 0), true, 0) {
+                let frame = packet.encode().unwrap();
+                let length = cut.index(frame.len());
+                prop_assert!(Packet::decode(&mut &frame[..length]).is_err());
+            }
+        }
+
+        #[test]
+        fn garbage_never_panics(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+            let _ = <Packet as netty::Packet>::from_reader(&mut bytes.as_slice());
+        }
     }
 }
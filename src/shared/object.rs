@@ -1,4 +1,7 @@
 use crate::prelude::*;
+use crate::shared::crafting::Station;
+use crate::shared::farming::Farmland;
+use crate::shared::mining::Ore;
 
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize, Component)]
 /// Represents a single game object.
@@ -19,8 +22,13 @@ impl Object {
 /// Represents the type of a game object.
 pub enum ObjectType {
     Tree(usize),
-    GroundItem(Item),
+    GroundItem(ItemStack),
     Npc(Npc),
+    Farmland(Farmland),
+    /// A rock, and the mining power left before it breaks
+    Rock(Ore, usize),
+    /// Somewhere players can craft things they can't anywhere else
+    Station(Station),
 }
 
 impl ObjectType {
@@ -28,7 +36,20 @@ impl ObjectType {
         match self {
             Self::Tree(_str) => Some((64.0, 64.0)),
             Self::Npc(_who) => Some((64.0, 64.0)),
+            Self::Rock(_ore, _str) => Some((64.0, 64.0)),
+            Self::Station(_station) => Some((64.0, 64.0)),
             _ => None,
         }
     }
+    /// The items dropped when this is destroyed.
+    pub fn drops(&self, rng: &mut impl rand::Rng) -> Vec<Item> {
+        match self {
+            Self::Tree(_str) => vec![Item::Wood; random(rng, 2, 3)],
+            Self::Rock(ore, _str) => {
+                let (item, min, max) = ore.drops();
+                vec![item; random(rng, min, max)]
+            }
+            _ => vec![],
+        }
+    }
 }
@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use crate::shared::farming::Crop;
 
 #[derive(Clone, PartialEq, Deserialize, Serialize, Debug)]
 pub struct PlayerData {
@@ -19,26 +20,189 @@ impl PlayerData {
     }
 }
 
+/// Everything a player carries. Slots are numbered through the hotbar, then
+/// the backpack.
 #[derive(Clone, PartialEq, Deserialize, Serialize, Debug)]
 pub struct Inventory {
     pub selected_slot: usize,
-    pub hotbar: [Option<Item>; 10],
+    pub hotbar: [Option<ItemStack>; HOTBAR_SIZE],
+    pub backpack: [Option<ItemStack>; BACKPACK_SIZE],
 }
 
 impl Inventory {
     pub fn empty() -> Inventory {
         Inventory {
             selected_slot: 0,
-            hotbar: [None; 10],
+            hotbar: [None; HOTBAR_SIZE],
+            backpack: [None; BACKPACK_SIZE],
         }
     }
-    pub fn hotbar_empty_space(&self) -> Option<usize> {
-        for (index, item) in self.hotbar.iter().enumerate() {
-            if item.is_none() {
-                return Some(index);
+    /// The item in the selected hotbar slot.
+    pub fn selected(&self) -> Option<Item> {
+        self.hotbar[self.selected_slot].map(|stack| stack.item)
+    }
+    pub fn slot(&self, slot: usize) -> Option<&Option<ItemStack>> {
+        if slot < HOTBAR_SIZE {
+            self.hotbar.get(slot)
+        }
+        else {
+            self.backpack.get(slot - HOTBAR_SIZE)
+        }
+    }
+    pub fn slot_mut(&mut self, slot: usize) -> Option<&mut Option<ItemStack>> {
+        if slot < HOTBAR_SIZE {
+            self.hotbar.get_mut(slot)
+        }
+        else {
+            self.backpack.get_mut(slot - HOTBAR_SIZE)
+        }
+    }
+    /// Every slot, hotbar first.
+    pub fn slots(&self) -> impl Iterator<Item = &Option<ItemStack>> {
+        self.hotbar.iter().chain(self.backpack.iter())
+    }
+    fn slots_mut(&mut self) -> impl Iterator<Item = &mut Option<ItemStack>> {
+        self.hotbar.iter_mut().chain(self.backpack.iter_mut())
+    }
+    /// How many of an item are carried, across every stack.
+    pub fn count(&self, item: Item) -> usize {
+        self.slots()
+            .flatten()
+            .filter(|stack| stack.item == item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+    pub fn contains(&self, item: Item) -> bool {
+        self.count(item) > 0
+    }
+    /// Is there room for at least one more of an item?
+    pub fn fits(&self, item: Item) -> bool {
+        self.slots().any(|slot| match slot {
+            Some(stack) => stack.item == item && stack.count < item.max_stack(),
+            None => true,
+        })
+    }
+    /// Adds items, topping up existing stacks before starting new ones.
+    /// Returns how many didn't fit.
+    pub fn add(&mut self, item: Item, mut count: usize) -> usize {
+        for stack in self.slots_mut().flatten() {
+            if count == 0 {
+                break;
+            }
+            if stack.item == item {
+                let moved = count.min(item.max_stack().saturating_sub(stack.count));
+                stack.count += moved;
+                count -= moved;
             }
         }
-        None
+        for slot in self.slots_mut() {
+            if count == 0 {
+                break;
+            }
+            if slot.is_none() {
+                let moved = count.min(item.max_stack());
+                *slot = Some(ItemStack::new(item, moved));
+                count -= moved;
+            }
+        }
+        count
+    }
+    /// Takes items out, from the last stacks first. Nothing is taken unless
+    /// there are enough.
+    pub fn remove(&mut self, item: Item, mut count: usize) -> bool {
+        if self.count(item) < count {
+            return false;
+        }
+        let slots: Vec<&mut Option<ItemStack>> = self.slots_mut().collect();
+        for slot in slots.into_iter().rev() {
+            if count == 0 {
+                break;
+            }
+            if let Some(stack) = slot.as_mut() {
+                if stack.item == item {
+                    let taken = count.min(stack.count);
+                    stack.count -= taken;
+                    count -= taken;
+                    if stack.count == 0 {
+                        *slot = None;
+                    }
+                }
+            }
+        }
+        true
+    }
+    /// Takes up to `count` items out of one slot, returning what was taken.
+    pub fn take(&mut self, slot: usize, count: usize) -> Option<ItemStack> {
+        let slot = self.slot_mut(slot)?;
+        let stack = slot.as_mut()?;
+        let taken = ItemStack::new(stack.item, count.min(stack.count));
+        stack.count -= taken.count;
+        if stack.count == 0 {
+            *slot = None;
+        }
+        if taken.count == 0 {
+            return None;
+        }
+        Some(taken)
+    }
+    /// Moves a stack onto another slot. Stacks of the same item are merged as
+    /// far as they'll go, and anything else is swapped. Returns false if
+    /// either slot doesn't exist.
+    pub fn move_stack(&mut self, from: usize, to: usize) -> bool {
+        let (moving, target) = match (self.slot(from), self.slot(to)) {
+            (Some(moving), Some(target)) if from != to => (*moving, *target),
+            _ => return false,
+        };
+        let (onto, left) = match (moving, target) {
+            (Some(mut moving), Some(mut target)) if moving.item == target.item => {
+                let room = target.item.max_stack().saturating_sub(target.count);
+                let moved = moving.count.min(room);
+                target.count += moved;
+                moving.count -= moved;
+                (Some(target), Some(moving).filter(|stack| stack.count > 0))
+            }
+            (moving, target) => (moving, target),
+        };
+        *self.slot_mut(to).expect("A checked slot disappeared") = onto;
+        *self.slot_mut(from).expect("A checked slot disappeared") = left;
+        true
+    }
+    /// Moves half of a stack, rounded down, into an empty slot. Returns false
+    /// if that isn't possible.
+    pub fn split_stack(&mut self, from: usize, to: usize) -> bool {
+        let mut stack = match (self.slot(from), self.slot(to)) {
+            (Some(Some(stack)), Some(None)) if stack.count > 1 => *stack,
+            _ => return false,
+        };
+        let half = stack.count / 2;
+        stack.count -= half;
+        *self.slot_mut(from).expect("A checked slot disappeared") = Some(stack);
+        *self.slot_mut(to).expect("A checked slot disappeared") =
+            Some(ItemStack::new(stack.item, half));
+        true
+    }
+}
+
+/// Some number of one item, kept in a single slot.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Debug)]
+pub struct ItemStack {
+    pub item: Item,
+    pub count: usize,
+}
+
+impl ItemStack {
+    pub fn new(item: Item, count: usize) -> ItemStack {
+        ItemStack { item, count }
+    }
+    pub fn one(item: Item) -> ItemStack {
+        ItemStack::new(item, 1)
+    }
+    /// The number shown on a slot. Single items don't show one.
+    pub fn count_label(slot: &Option<ItemStack>) -> String {
+        match slot {
+            Some(stack) if stack.count > 1 => stack.count.to_string(),
+            _ => String::new(),
+        }
     }
 }
 
@@ -48,19 +212,96 @@ pub enum Item {
     MakeshiftFishingRod,
     Blueprint,
     Wood,
+    MakeshiftHoe,
+    WateringCan,
+    WheatSeeds,
+    CarrotSeeds,
+    Wheat,
+    Carrot,
+    Minnow,
+    Trout,
+    Catfish,
+    OldBoot,
+    MakeshiftPickaxe,
+    CopperPickaxe,
+    IronPickaxe,
+    Stone,
+    CopperOre,
+    IronOre,
 }
 
 impl Item {
     pub fn from_str(from: &str) -> Item {
+        if let Some(item) = Item::try_from_str(from) {
+            item
+        }
+        else {
+            error!("No item with name {from}");
+            panic!("{FATAL_ERROR}");
+        }
+    }
+    /// Like `from_str`, but returns `None` for unknown names instead of
+    /// panicking. Use this for anything a player typed.
+    pub fn try_from_str(from: &str) -> Option<Item> {
         match from {
-            "MakeshiftAxe" => Item::MakeshiftAxe,
-            "MakeshiftFishingRod" => Item::MakeshiftFishingRod,
-            "Blueprint" => Item::Blueprint,
-            "Wood" => Item::Wood,
-            invalid_name => {
-                error!("No item with name {invalid_name}");
-                panic!("{FATAL_ERROR}");
-            }
+            "MakeshiftAxe" => Some(Item::MakeshiftAxe),
+            "MakeshiftFishingRod" => Some(Item::MakeshiftFishingRod),
+            "Blueprint" => Some(Item::Blueprint),
+            "Wood" => Some(Item::Wood),
+            "MakeshiftHoe" => Some(Item::MakeshiftHoe),
+            "WateringCan" => Some(Item::WateringCan),
+            "WheatSeeds" => Some(Item::WheatSeeds),
+            "CarrotSeeds" => Some(Item::CarrotSeeds),
+            "Wheat" => Some(Item::Wheat),
+            "Carrot" => Some(Item::Carrot),
+            "Minnow" => Some(Item::Minnow),
+            "Trout" => Some(Item::Trout),
+            "Catfish" => Some(Item::Catfish),
+            "OldBoot" => Some(Item::OldBoot),
+            "MakeshiftPickaxe" => Some(Item::MakeshiftPickaxe),
+            "CopperPickaxe" => Some(Item::CopperPickaxe),
+            "IronPickaxe" => Some(Item::IronPickaxe),
+            "Stone" => Some(Item::Stone),
+            "CopperOre" => Some(Item::CopperOre),
+            "IronOre" => Some(Item::IronOre),
+            _ => None,
+        }
+    }
+    /// The name shown to players.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Item::MakeshiftAxe => "makeshift axe",
+            Item::MakeshiftFishingRod => "makeshift fishing rod",
+            Item::Blueprint => "blueprint",
+            Item::Wood => "wood",
+            Item::MakeshiftHoe => "makeshift hoe",
+            Item::WateringCan => "watering can",
+            Item::WheatSeeds => "wheat seeds",
+            Item::CarrotSeeds => "carrot seeds",
+            Item::Wheat => "wheat",
+            Item::Carrot => "carrot",
+            Item::Minnow => "minnow",
+            Item::Trout => "trout",
+            Item::Catfish => "catfish",
+            Item::OldBoot => "old boot",
+            Item::MakeshiftPickaxe => "makeshift pickaxe",
+            Item::CopperPickaxe => "copper pickaxe",
+            Item::IronPickaxe => "iron pickaxe",
+            Item::Stone => "stone",
+            Item::CopperOre => "copper ore",
+            Item::IronOre => "iron ore",
+        }
+    }
+    /// How many of this fit in one slot.
+    pub fn max_stack(&self) -> usize {
+        match self.action() {
+            ItemAction::Plant(_) => 99,
+            ItemAction::None => match self {
+                Item::OldBoot => 1,
+                Item::Minnow | Item::Trout | Item::Catfish => 20,
+                _ => 99,
+            },
+            _ => 1,
         }
     }
     pub fn action(&self) -> ItemAction {
@@ -68,6 +309,13 @@ impl Item {
             Item::MakeshiftAxe => ItemAction::Chop(1),
             Item::MakeshiftFishingRod => ItemAction::Fish(1),
             Item::Blueprint => ItemAction::Blueprint,
+            Item::MakeshiftHoe => ItemAction::Dig(1),
+            Item::WateringCan => ItemAction::Water,
+            Item::WheatSeeds => ItemAction::Plant(Crop::Wheat),
+            Item::CarrotSeeds => ItemAction::Plant(Crop::Carrot),
+            Item::MakeshiftPickaxe => ItemAction::Mine(1),
+            Item::CopperPickaxe => ItemAction::Mine(2),
+            Item::IronPickaxe => ItemAction::Mine(3),
             _ => ItemAction::None,
         }
     }
@@ -87,6 +335,10 @@ pub enum ItemAction {
     Mine(usize),
     /// Item modifies terrain
     Blueprint,
+    /// Item waters farmland
+    Water,
+    /// Item is planted in farmland
+    Plant(Crop),
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Debug)]
@@ -96,6 +348,9 @@ pub struct Stats {
     pub cooking: usize,
     pub crafting: usize,
     pub trading: usize, // ...ect
+    /// Times this player has crafted something. Raises `crafting` every
+    /// `CRAFTS_PER_LEVEL`.
+    pub crafts: usize,
 }
 
 impl Stats {
@@ -106,6 +361,7 @@ impl Stats {
             cooking: 1,
             crafting: 1,
             trading: 1,
+            crafts: 0,
         }
     }
 }
@@ -113,32 +369,31 @@ impl Stats {
 #[derive(Clone, PartialEq, Deserialize, Serialize, Debug)]
 pub struct Recipes {
     unlocked: Vec<Recipe>,
-    locked: Vec<Recipe>,
 }
 
 impl Recipes {
     pub fn starting() -> Recipes {
-        Recipes {
-            unlocked: vec![],
-            locked: vec![Recipe::BigRock],
-        }
+        Recipes { unlocked: vec![] }
     }
-    pub fn _unlock_all(&mut self) {
-        for recipe in &self.locked {
-            self.unlocked.push(*recipe);
-        }
-        self.locked.clear();
+    pub fn unlocked(&self) -> &[Recipe] {
+        &self.unlocked
+    }
+    pub fn is_unlocked(&self, recipe: &Recipe) -> bool {
+        self.unlocked.contains(recipe)
     }
-    pub fn _is_unlocked(&mut self, recipe: Recipe) -> bool {
-        self.unlocked.contains(&recipe)
+    /// Unlocks a recipe. Returns false if it already was.
+    pub fn unlock(&mut self, recipe: Recipe) -> bool {
+        if self.is_unlocked(&recipe) {
+            return false;
+        }
+        self.unlocked.push(recipe);
+        true
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Deserialize, Serialize, Debug)]
-
-pub enum Recipe {
-    BigRock, // ...ect
-}
+/// The name of a recipe in `metadata/recipes.json`.
+#[derive(Clone, PartialEq, Eq, Hash, Deserialize, Serialize, Debug)]
+pub struct Recipe(pub String);
 
 #[derive(Clone, PartialEq, Deserialize, Serialize, Debug)]
 pub struct Achievements {
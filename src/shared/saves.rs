@@ -5,3 +5,23 @@ pub struct User {
     pub username: String,
     pub tag: u16,
 }
+
+impl User {
+    /// Parses a user written as `username#tag`, like `Player#1234`.
+    pub fn parse(from: &str) -> Option<User> {
+        let (username, tag) = from.trim().rsplit_once('#')?;
+        if username.is_empty() {
+            return None;
+        }
+        Some(User {
+            username: username.to_string(),
+            tag: tag.parse().ok()?,
+        })
+    }
+}
+
+impl std::fmt::Display for User {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}#{}", self.username, self.tag)
+    }
+}
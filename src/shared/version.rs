@@ -0,0 +1,41 @@
+use crate::prelude::*;
+
+/// Sent by both sides when a client connects, so each knows what the other
+/// understands.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct ProtocolVersion {
+    /// Changes whenever a change would break older clients or servers
+    pub major: u16,
+    /// Changes when something is added that older peers can do without
+    pub minor: u16,
+    /// Optional features supported, by name. Names are used instead of an enum
+    /// so that features from newer versions can still be read.
+    pub capabilities: Vec<String>,
+}
+
+impl ProtocolVersion {
+    /// The version of this build.
+    pub fn current() -> ProtocolVersion {
+        ProtocolVersion {
+            major: PROTOCOL_MAJOR,
+            minor: PROTOCOL_MINOR,
+            capabilities: PROTOCOL_CAPABILITIES
+                .iter()
+                .map(|capability| capability.to_string())
+                .collect(),
+        }
+    }
+    /// Can this version talk to `other`? Only the major versions need to match.
+    pub fn compatible_with(&self, other: &ProtocolVersion) -> bool {
+        self.major == other.major
+    }
+    pub fn has(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|has| has == capability)
+    }
+}
+
+impl std::fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
@@ -0,0 +1,76 @@
+use crate::prelude::*;
+
+/// The weather in a world. Chosen by the server, which changes it every few
+/// minutes of game time.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum Weather {
+    Clear,
+    Rain,
+    Storm,
+    Fog,
+}
+
+impl Weather {
+    /// Picks the weather after this one. `roll` is a random number in `0..1`.
+    pub fn next(self, roll: f32) -> Weather {
+        // (chance, weather) pairs, with chances adding up to 1
+        let odds: &[(f32, Weather)] = match self {
+            Weather::Clear => &[(0.2, Weather::Clear), (0.5, Weather::Rain), (0.3, Weather::Fog)],
+            Weather::Rain => &[(0.5, Weather::Clear), (0.2, Weather::Rain), (0.3, Weather::Storm)],
+            Weather::Storm => &[(0.3, Weather::Clear), (0.7, Weather::Rain)],
+            Weather::Fog => &[(0.7, Weather::Clear), (0.3, Weather::Rain)],
+        };
+        let mut total = 0.0;
+        for (chance, weather) in odds {
+            total += chance;
+            if roll < total {
+                return *weather;
+            }
+        }
+        odds[odds.len() - 1].1
+    }
+    /// Parses a weather's name, as typed in commands.
+    pub fn parse(name: &str) -> Option<Weather> {
+        match name.to_lowercase().as_str() {
+            "clear" => Some(Weather::Clear),
+            "rain" => Some(Weather::Rain),
+            "storm" => Some(Weather::Storm),
+            "fog" => Some(Weather::Fog),
+            _ => None,
+        }
+    }
+    /// Does this weather water crops left outside?
+    pub fn waters_crops(self) -> bool {
+        matches!(self, Weather::Rain | Weather::Storm)
+    }
+    /// How much more often fish bite in this weather.
+    pub fn bite_multiplier(self) -> f32 {
+        match self {
+            Weather::Clear => 1.0,
+            Weather::Rain => 1.5,
+            Weather::Storm => 0.5,
+            Weather::Fog => 1.25,
+        }
+    }
+    /// The name of the looping sound played during this weather, if any.
+    /// TODO: "rain loop" and "storm loop" still point at silence.ogg until
+    /// real recordings are added to assets/audio.
+    pub fn ambience(self) -> Option<&'static str> {
+        match self {
+            Weather::Clear | Weather::Fog => None,
+            Weather::Rain => Some("rain loop"),
+            Weather::Storm => Some("storm loop"),
+        }
+    }
+}
+
+impl std::fmt::Display for Weather {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Weather::Clear => write!(f, "clear"),
+            Weather::Rain => write!(f, "rain"),
+            Weather::Storm => write!(f, "storm"),
+            Weather::Fog => write!(f, "fog"),
+        }
+    }
+}
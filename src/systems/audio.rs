@@ -3,3 +3,6 @@ pub use title_screen_loop::title_screen_loop;
 
 mod audio_setup;
 pub use audio_setup::audio_setup;
+
+mod weather_ambience;
+pub use weather_ambience::weather_ambience;
@@ -0,0 +1,30 @@
+use crate::prelude::*;
+use crate::resources::Reality;
+use bevy_kira_audio::{Audio, AudioControl, AudioInstance, AudioTween};
+
+/// Loops the sound for the current weather, fading between sounds when it
+/// changes.
+pub fn weather_ambience(
+    audio: Res<Audio>,
+    core: Res<CoreAssets>,
+    audio_serve: Res<Assets<AudioSamples>>,
+    reality: Res<Reality>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+    mut playing: Local<Option<(&'static str, Handle<AudioInstance>)>>,
+) {
+    let wanted = reality.weather().ambience();
+    if playing.as_ref().map(|(name, _)| *name) == wanted {
+        return;
+    }
+    let fade = AudioTween::linear(std::time::Duration::from_secs(2));
+    if let Some((_, handle)) = playing.take() {
+        if let Some(instance) = instances.get_mut(&handle) {
+            instance.stop(fade.clone());
+        }
+    }
+    if let Some(name) = wanted {
+        let samples = audio_serve.get(&core.audio).unwrap();
+        let handle = audio.play(samples.get(name)).looped().fade_in(fade).handle();
+        *playing = Some((name, handle));
+    }
+}
@@ -32,6 +32,11 @@ pub fn text_input(
             // Remove the escape character
             tb.eat_buffer();
         }
+        // If we recieve a tab character...
+        if char.char == '\t' {
+            // Remove the tab character (used for switching chat tabs)
+            tb.eat_buffer();
+        }
     }
 }
 
@@ -53,7 +58,13 @@ pub fn user_creation(
     }
     let mut text = text.unwrap();
     text.sections[0].value = tb.grab_buffer() + "";
-    if tb.grab_buffer().contains('#') {
+    let rejected = disk.user().filter(|user| user.tag == 0).map(|user| user.username);
+    if rejected == Some(tb.grab_buffer()) {
+        // The server turned this name down
+        text.sections[0].style.color = Color::RED;
+        text.sections[1].value = lang.get_key(".core.create_user.overused");
+    }
+    else if tb.grab_buffer().contains('#') {
         text.sections[0].style.color = Color::RED;
         text.sections[1].value = lang.get_key(".core.create_user.no_hashtags");
     }
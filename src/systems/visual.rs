@@ -12,8 +12,12 @@ mod join_world;
 pub use join_world::join_world;
 mod settings;
 pub use settings::*;
+mod version_mismatch;
+pub use version_mismatch::version_mismatch;
 mod animated_sprites;
 pub use animated_sprites::*;
+mod weather;
+pub use weather::*;
 
 use crate::prelude::*;
 
@@ -14,9 +14,196 @@ pub fn clear_settings(
     ui.remove_tag("Settings");
     // Reset settings UI settings
     ui.on_page = false;
+    ui.adding_server = false;
     ui.settings_page = SettingsPage::Video;
 }
 
+/// Spawns a line of settings text with its left edge at `x`.
+fn settings_text(
+    commands: &mut Commands,
+    fonts: &FontAssets,
+    sections: Vec<(String, Color)>,
+    x: f32,
+    y: f32,
+    type_: u8,
+) {
+    commands.spawn((
+        Text2dBundle {
+            text: Text {
+                sections: sections
+                    .into_iter()
+                    .map(|(value, color)| TextSection {
+                        value,
+                        style: TextStyle {
+                            font: fonts.simvoni.clone(),
+                            font_size: 36.0,
+                            color,
+                        },
+                    })
+                    .collect(),
+                alignment: TextAlignment::Left,
+                linebreak_behavior: bevy::text::BreakLineOn::AnyCharacter
+            },
+            text_anchor: bevy::sprite::Anchor::CenterLeft,
+            transform: Transform::from_xyz(x, y, UI_TEXT + 105.0),
+            ..default()
+        },
+        SettingsPageComp { type_ },
+        RemoveOnStateChange,
+    ));
+}
+
+/// Adds a clickable area over text spawned by `settings_text`.
+fn settings_button(man: &mut UIManager, action: UIClickAction, x: f32, y: f32, width: f32) {
+    man.add_ui(UIClickable {
+        action,
+        location: (x, y + 18.0),
+        size: (width, 36.0),
+        removed_on_use: false,
+        tag: Some(String::from("Settings")),
+    });
+}
+
+/// Spawns the buttons for switching between settings pages.
+fn settings_tabs(commands: &mut Commands, man: &mut UIManager, fonts: &FontAssets, lang: &Language) {
+    let tabs = [
+        (".core.settings.tabs.video", UIClickAction::TabVideoSettings, SettingsPage::Video),
+        (".core.settings.tabs.online", UIClickAction::TabOnlineSettings, SettingsPage::Online),
+    ];
+    for (index, (key, action, page)) in tabs.into_iter().enumerate() {
+        let x = -600.0 + (index as f32 * 180.0);
+        let color = if man.settings_page == page {
+            Color::DARK_GRAY
+        }
+        else {
+            Color::BLUE
+        };
+        settings_text(commands, fonts, vec![(lang.get_key(key), color)], x, 300.0, 0);
+        settings_button(man, action, x, 300.0, 160.0);
+    }
+}
+
+pub fn settings_online(
+    mut commands: Commands,
+    mut man: ResMut<UIManager>,
+    disk: Res<Disk>,
+    fonts: Res<FontAssets>,
+    core: Res<CoreAssets>,
+    lang_serve: Res<Assets<Language>>,
+) {
+    if man.settings_page != SettingsPage::Online || man.on_page {
+        return;
+    }
+    let lang = lang_serve.get(&core.lang).unwrap();
+    man.on_page = true;
+    settings_tabs(&mut commands, &mut man, &fonts, lang);
+    let book = disk.address_book();
+    settings_text(
+        &mut commands,
+        &fonts,
+        vec![(lang.get_key(".core.settings.online.servers"), Color::BLACK)],
+        -400.0,
+        200.0,
+        0,
+    );
+    let mut y = 150.0;
+    for (index, server) in book.servers.iter().enumerate() {
+        settings_text(
+            &mut commands,
+            &fonts,
+            vec![(server.to_string(), Color::BLACK)],
+            -400.0,
+            y,
+            0,
+        );
+        if index == book.selected {
+            settings_text(
+                &mut commands,
+                &fonts,
+                vec![(lang.get_key(".core.settings.online.in_use"), Color::DARK_GREEN)],
+                200.0,
+                y,
+                0,
+            );
+        }
+        else {
+            settings_text(
+                &mut commands,
+                &fonts,
+                vec![(lang.get_key(".core.settings.online.use"), Color::BLUE)],
+                200.0,
+                y,
+                0,
+            );
+            settings_button(&mut man, UIClickAction::SelectServer(index), 200.0, y, 100.0);
+        }
+        settings_text(
+            &mut commands,
+            &fonts,
+            vec![(lang.get_key(".core.settings.online.remove"), Color::RED)],
+            360.0,
+            y,
+            0,
+        );
+        settings_button(&mut man, UIClickAction::RemoveServer(index), 360.0, y, 50.0);
+        y -= 44.0;
+    }
+    if man.adding_server {
+        settings_text(
+            &mut commands,
+            &fonts,
+            vec![
+                (lang.get_key(".core.settings.online.typing"), Color::BLACK),
+                (String::new(), Color::RED),
+            ],
+            -400.0,
+            y,
+            5,
+        );
+    }
+    else {
+        settings_text(
+            &mut commands,
+            &fonts,
+            vec![(lang.get_key(".core.settings.online.add"), Color::BLUE)],
+            -400.0,
+            y,
+            0,
+        );
+        settings_button(&mut man, UIClickAction::AddServer, -400.0, y, 220.0);
+    }
+    let note = if disk.has_server_override() {
+        ".core.settings.online.override"
+    }
+    else {
+        ".core.settings.online.restart"
+    };
+    settings_text(
+        &mut commands,
+        &fonts,
+        vec![(lang.get_key(note), Color::DARK_GRAY)],
+        -400.0,
+        -200.0,
+        0,
+    );
+    // Leave button/text
+    man.add_ui(UIClickable {
+        action: UIClickAction::CloseSettings,
+        location: (-600.0, -300.0),
+        size: (100.0, 36.0),
+        removed_on_use: true,
+        tag: Some(String::from("Settings")),
+    });
+    settings_text(
+        &mut commands,
+        &fonts,
+        vec![(lang.get_key(".core.settings.leave"), Color::BLACK)],
+        -600.0,
+        -300.0,
+        0,
+    );
+}
+
 pub fn settings_video(
     mut commands: Commands,
     mut man: ResMut<UIManager>,
@@ -28,6 +215,7 @@ pub fn settings_video(
     if man.settings_page == SettingsPage::Video && !man.on_page {
         let lang = lang_serve.get(&core.lang).unwrap();
         man.on_page = true;
+        settings_tabs(&mut commands, &mut man, &fonts, lang);
         // Background
         commands.spawn((
             SpriteBundle {
@@ -0,0 +1,107 @@
+use crate::prelude::*;
+use crate::shared::version::ProtocolVersion;
+
+/// Tells the player that the server runs a version of the game they can't
+/// play with, and which version it needs.
+pub fn version_mismatch(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    core: Res<CoreAssets>,
+    lang_serve: Res<Assets<Language>>,
+    netty: Option<Res<Netty>>,
+    mut ui: ResMut<UIManager>,
+) {
+    let lang = lang_serve.get(&core.lang).unwrap();
+    let client = ProtocolVersion::current();
+    let server = netty.and_then(|netty| netty.server_version());
+    let mut lines = vec![lang.get_key(".core.version_mismatch.title")];
+    if let Some(server) = server {
+        lines.push(
+            lang.get_key(".core.version_mismatch.details")
+                .replace("{server}", &server.to_string())
+                .replace("{client}", &client.to_string()),
+        );
+        if server.major > client.major {
+            lines.push(lang.get_key(".core.version_mismatch.update"));
+        }
+        else {
+            lines.push(lang.get_key(".core.version_mismatch.outdated"));
+        }
+    }
+    for (index, line) in lines.into_iter().enumerate() {
+        commands.spawn((
+            Text2dBundle {
+                text: Text {
+                    sections: vec![TextSection {
+                        value: line,
+                        style: TextStyle {
+                            font: font_assets.apple_tea.clone(),
+                            font_size: if index == 0 { 64.0 } else { 40.0 },
+                            color: Color::BLACK,
+                        },
+                    }],
+                    alignment: TextAlignment::Center,
+                    linebreak_behavior: bevy::text::BreakLineOn::AnyCharacter
+                },
+                transform: Transform::from_xyz(0.0, 200.0 - (index as f32 * 80.0), UI_TEXT),
+                ..default()
+            },
+            RemoveOnStateChange {},
+        ));
+    }
+    // Settings text, to pick another server
+    commands.spawn((
+        Text2dBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value: lang.get_key(".core.title_screen.settings"),
+                    style: TextStyle {
+                        font: font_assets.apple_tea.clone(),
+                        font_size: 64.0,
+                        color: Color::BLACK,
+                    },
+                }],
+                alignment: TextAlignment::Center,
+                linebreak_behavior: bevy::text::BreakLineOn::AnyCharacter
+            },
+            transform: Transform::from_xyz(512.0, -256.0, UI_TEXT),
+            ..default()
+        },
+        RemoveOnStateChange {},
+    ));
+    ui.add_ui(UIClickable {
+        action: UIClickAction::OpenSettings,
+        location: (330.0, -210.0),
+        size: (350.0, 100.0),
+        ..default()
+    });
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        // Quit game text
+        commands.spawn((
+            Text2dBundle {
+                text: Text {
+                    sections: vec![TextSection {
+                        value: lang.get_key(".core.title_screen.quit"),
+                        style: TextStyle {
+                            font: font_assets.apple_tea.clone(),
+                            font_size: 64.0,
+                            color: Color::BLACK,
+                        },
+                    }],
+                    alignment: TextAlignment::Center,
+                    linebreak_behavior: bevy::text::BreakLineOn::AnyCharacter
+                },
+                transform: Transform::from_xyz(-512.0, -256.0, UI_TEXT),
+                ..default()
+            },
+            RemoveOnStateChange {},
+        ));
+        ui.add_ui(UIClickable {
+            action: UIClickAction::CloseProgram,
+            location: (-710.0, -210.0),
+            size: (410.0, 100.0),
+            ..default()
+        });
+    }
+}
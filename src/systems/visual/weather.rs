@@ -0,0 +1,106 @@
+use crate::prelude::*;
+use crate::resources::Reality;
+use crate::shared::weather::Weather;
+
+/// Spawns the tint and rain drops drawn over the world for weather. They're
+/// hidden until the weather calls for them.
+pub fn spawn_weather(mut commands: Commands) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::NONE,
+                custom_size: Some(Vec2::new(1920.0, 1080.0)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, UI_IMG - 2.0),
+            ..default()
+        },
+        WeatherMarker {
+            type_: 1,
+            offset: Vec2::ZERO,
+        },
+        UILocked {},
+        RemoveOnStateChange {},
+    ));
+    for _ in 0..RAIN_DROPS {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0.6, 0.7, 0.9, 0.6),
+                    custom_size: Some(Vec2::new(2.0, 24.0)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, UI_IMG - 1.5),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            WeatherMarker {
+                type_: 2,
+                offset: random_drop_position(),
+            },
+            UILocked {},
+            RemoveOnStateChange {},
+        ));
+    }
+}
+
+/// Animates rain and tints the world for the current weather. Storms flash
+/// with lightning every so often.
+pub fn update_weather(
+    reality: Res<Reality>,
+    time: Res<Time>,
+    mut lightning: Local<f32>,
+    mut query: Query<(&mut WeatherMarker, &mut Transform, &mut Sprite, &mut Visibility)>,
+) {
+    let weather = reality.weather();
+    let delta = time.delta_seconds();
+    if weather == Weather::Storm && rand::random::<f32>() < delta / 8.0 {
+        *lightning = 1.0;
+    }
+    *lightning = (*lightning - delta * 3.0).max(0.0);
+    let (tint, drops, fall, wind) = match weather {
+        Weather::Clear => (Color::NONE, 0, 0.0, 0.0),
+        Weather::Rain => (Color::rgba(0.2, 0.2, 0.3, 0.15), RAIN_DROPS / 2, 900.0, -60.0),
+        Weather::Storm => (Color::rgba(0.1, 0.1, 0.15, 0.35), RAIN_DROPS, 1400.0, -300.0),
+        Weather::Fog => (Color::rgba(0.85, 0.85, 0.9, 0.55), 0, 0.0, 0.0),
+    };
+    let mut drop_index = 0;
+    query.for_each_mut(|(mut marker, mut transform, mut sprite, mut visibility)| {
+        if marker.type_ == 1 {
+            let flash = *lightning * 0.6;
+            sprite.color = Color::rgba(
+                tint.r() + (1.0 - tint.r()) * flash,
+                tint.g() + (1.0 - tint.g()) * flash,
+                tint.b() + (1.0 - tint.b()) * flash,
+                tint.a().max(flash),
+            );
+            transform.translation.x = 0.0;
+            transform.translation.y = 0.0;
+            return;
+        }
+        drop_index += 1;
+        if drop_index > drops {
+            *visibility = Visibility::Hidden;
+            return;
+        }
+        *visibility = Visibility::Inherited;
+        marker.offset.x += wind * delta;
+        marker.offset.y -= fall * delta;
+        if marker.offset.y < -(1080.0 / 2.0) || marker.offset.x.abs() > 1920.0 / 2.0 {
+            marker.offset = random_drop_position();
+            marker.offset.y = 1080.0 / 2.0;
+        }
+        // Lean into the wind
+        transform.rotation = Quat::from_rotation_z((wind / fall).atan());
+        transform.translation.x = marker.offset.x;
+        transform.translation.y = marker.offset.y;
+    });
+}
+
+/// A random place on the screen for a rain drop.
+fn random_drop_position() -> Vec2 {
+    Vec2::new(
+        (rand::random::<f32>() - 0.5) * 1920.0,
+        (rand::random::<f32>() - 0.5) * 1080.0,
+    )
+}
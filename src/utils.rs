@@ -9,8 +9,58 @@ pub fn distance(a: Transform, b: Transform) -> f32 {
         .sqrt()
 }
 
-/// Generates a random number between \[min, max]
-pub fn random(min: usize, max: usize) -> usize {
-    let a: f64 = rand::random();
+/// Finds the tile a world position is on, as (chunk, tile in chunk). Tiles are
+/// laid out the same way chunks are rendered, so the tile's index in the
+/// chunk's data is `tile.0 + (tile.1 * CHUNK_WIDTH)`.
+pub fn tile_at(position: Transform) -> ((isize, isize), (usize, usize)) {
+    const WIDTH: f32 = CHUNK_WIDTH as f32 * 64.0;
+    const HEIGHT: f32 = CHUNK_HEIGHT as f32 * 64.0;
+    // Distance from the bottom left of chunk (0, 0)
+    let x = position.translation.x + (1920.0 / 2.0);
+    let y = position.translation.y + (1080.0 / 2.0) + 64.0;
+    let chunk = ((x / WIDTH).floor(), (y / HEIGHT).floor());
+    let tile = (
+        ((x - chunk.0 * WIDTH) / 64.0).floor() as usize,
+        ((y - chunk.1 * HEIGHT) / 64.0).floor() as usize,
+    );
+    (
+        (chunk.0 as isize, chunk.1 as isize),
+        (tile.0.min(CHUNK_WIDTH - 1), tile.1.min(CHUNK_HEIGHT - 1)),
+    )
+}
+
+/// The centre of a tile, in world coordinates. The opposite of `tile_at`.
+pub fn tile_centre(chunk: (isize, isize), tile: (usize, usize)) -> Transform {
+    Transform::from_xyz(
+        (-1920.0 / 2.0) + (tile.0 as f32 * 64.0) + 32.0 + (1920.0 * chunk.0 as f32),
+        (-1080.0 / 2.0) + (tile.1 as f32 * 64.0) - 32.0 + (1088.0 * chunk.1 as f32),
+        0.0,
+    )
+}
+
+/// Generates a random number between \[min, max] using `rng`
+pub fn random(rng: &mut impl rand::Rng, min: usize, max: usize) -> usize {
+    let a: f64 = rng.gen();
     (a * (max - min) as f64).round() as usize + min
 }
+
+/// Generates a random ID for a new object using `rng`
+pub fn random_uuid(rng: &mut impl rand::Rng) -> uuid::Uuid {
+    uuid::Builder::from_random_bytes(rng.gen()).into_uuid()
+}
+
+/// Formats a number of seconds for players to read.
+pub fn format_duration(seconds: u64) -> String {
+    if seconds < 60 {
+        format!("{seconds}s")
+    }
+    else if seconds < 60 * 60 {
+        format!("{}m", seconds / 60)
+    }
+    else if seconds < 60 * 60 * 24 {
+        format!("{}h {}m", seconds / (60 * 60), seconds % (60 * 60) / 60)
+    }
+    else {
+        format!("{}d {}h", seconds / (60 * 60 * 24), seconds % (60 * 60 * 24) / (60 * 60))
+    }
+}